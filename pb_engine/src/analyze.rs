@@ -11,32 +11,62 @@ mod weaken;
 
 use std::{cmp::Reverse, usize};
 
-use calculate_propagation_level::CalculatePropagationLevel;
+use ::utility::Set;
+
+pub(crate) use calculate_propagation_level::CalculatePropagationLevel;
 use find_conflict_literal::FindConflictLiteral;
 use flatten::FlattenConflictConstraint;
 use identify_propagation_causals::IdentifyPropagationCausals;
 use resolve::Resolve;
 use utility::{drop_fixed_variable, lhs_sup_of_linear_constraint_at};
+use weaken::Weaken;
 
 use crate::{
-    Boolean, LinearConstraint, LinearConstraintTrait, Literal, PBEngine, PBExplainKey, Reason,
-    collections::LiteralSet,
+    Boolean, Coefficient, LinearConstraint, LinearConstraintTrait, LinearConstraintView, Literal, PBEngine,
+    PBExplainKey, Reason, calculate_plbd::CalculatePLBD, collections::LiteralSet,
 };
 
 // TODO: learnt_constraint は LinearConstraint でいい
-pub enum AnalyzeResult<LinearConstraintT, ConflictingAssignmentsT>
+pub enum AnalyzeResult<LinearConstraintT, ConflictingAssignmentsT, ParticipatingKeysT>
 where
-    LinearConstraintT: LinearConstraintTrait<Value = u64>,
+    LinearConstraintT: LinearConstraintTrait<Value = Coefficient>,
     ConflictingAssignmentsT: Iterator<Item = Literal>,
+    ParticipatingKeysT: Iterator<Item = PBExplainKey>,
 {
     Backjumpable {
         backjump_level: usize,
         learnt_constraint: LinearConstraintT,
         conflicting_assignments: ConflictingAssignmentsT,
+        /// 学習制約の PLBD (False が割り当てられているリテラルの決定レベルの種類数)
+        plbd: usize,
+        /// 矛盾が発生した決定レベル
+        conflict_level: usize,
+        /// backjump 後に学習制約が伝播するリテラル
+        asserting_literal: Literal,
+        /// 学習制約の導出に用いられた制約条件
+        participating_keys: ParticipatingKeysT,
     },
     Unsatisfiable,
 }
 
+impl<LinearConstraintT, ConflictingAssignmentsT, ParticipatingKeysT>
+    AnalyzeResult<LinearConstraintT, ConflictingAssignmentsT, ParticipatingKeysT>
+where
+    LinearConstraintT: LinearConstraintTrait<Value = Coefficient>,
+    ConflictingAssignmentsT: Iterator<Item = Literal>,
+    ParticipatingKeysT: Iterator<Item = PBExplainKey>,
+{
+    /// 充足不能であることが示された場合は None を返す
+    pub fn asserting_literal(&self) -> Option<Literal> {
+        match self {
+            AnalyzeResult::Backjumpable {
+                asserting_literal, ..
+            } => return Some(*asserting_literal),
+            AnalyzeResult::Unsatisfiable => return None,
+        }
+    }
+}
+
 pub struct Analyze {
     calculate_propagation_level: CalculatePropagationLevel,
     find_conflict_literal: FindConflictLiteral,
@@ -44,29 +74,99 @@ pub struct Analyze {
     resolve: Resolve,
     flatten: FlattenConflictConstraint,
     conflicting_assignments: LiteralSet,
-    conflict_constraint: LinearConstraint<u64>,
+    participating_keys: Vec<PBExplainKey>,
+    calculate_plbd: CalculatePLBD,
+    conflict_constraint: LinearConstraint<Coefficient>,
+    // 導出ごとに理由の制約条件を書き込むバッファ
+    reason_constraint: LinearConstraint<Coefficient>,
+    weakened_constraint: LinearConstraint<Coefficient>,
+    seen_variables: Set,
+    causal_assignments: Vec<Literal>,
+    clause_literals: Vec<Literal>,
+    weaken: Weaken<Coefficient>,
+    max_learnt_terms: Option<usize>,
+    minimization: bool,
+    target_weakening: bool,
 }
 
 impl Analyze {
     pub fn new(integrality_tolerance: f64) -> Self {
+        return Self::new_with_limits(integrality_tolerance, u32::MAX as Coefficient, None);
+    }
+
+    /// 学習制約の係数の上限 max_coefficient と項数の上限 max_learnt_terms を指定する
+    ///
+    /// 項数が max_learnt_terms を超えた学習制約からは，True が割り当てられているリテラルの項を
+    /// アクティビティの小さい順に取り除く (その分だけ右辺を小さくする)．
+    /// 学習制約は元の制約条件から導かれるもののままだが，取り除いた分だけ弱い制約条件となる
+    pub fn new_with_limits(
+        integrality_tolerance: f64,
+        max_coefficient: Coefficient,
+        max_learnt_terms: Option<usize>,
+    ) -> Self {
         Self {
             calculate_propagation_level: CalculatePropagationLevel::new(),
             find_conflict_literal: FindConflictLiteral::default(),
             identify_propagation_causals: IdentifyPropagationCausals::new(),
             resolve: Resolve::new(integrality_tolerance),
-            flatten: FlattenConflictConstraint::new(u32::MAX as u64),
+            flatten: FlattenConflictConstraint::new(max_coefficient),
             conflicting_assignments: LiteralSet::default(),
+            participating_keys: Vec::default(),
+            calculate_plbd: CalculatePLBD::default(),
             conflict_constraint: LinearConstraint::default(),
+            reason_constraint: LinearConstraint::default(),
+            weakened_constraint: LinearConstraint::default(),
+            seen_variables: Set::default(),
+            causal_assignments: Vec::default(),
+            clause_literals: Vec::default(),
+            weaken: Weaken::new(),
+            max_learnt_terms,
+            minimization: false,
+            target_weakening: false,
         }
     }
+
+    /// call_clausal で学習する節を最小化するかどうかを設定する (既定は無効)
+    pub fn set_minimization(&mut self, minimization: bool) {
+        self.minimization = minimization;
+    }
+
+    /// call で学習する制約条件を，最後に False が割り当てられたリテラルを伝播するように
+    /// weaken するかどうかを設定する (既定は無効)
+    ///
+    /// 矛盾の解析とは無関係なリテラルを伝播する浅い決定レベルへの backjump を避け，
+    /// 学習制約が目的のリテラルを伝播する決定レベルへ backjump する
+    pub fn set_target_weakening(&mut self, target_weakening: bool) {
+        self.target_weakening = target_weakening;
+    }
+
+    /// 導出の結果が矛盾を導くことを確認し，そうでなければ panic するかどうかを設定する (既定は無効)
+    ///
+    /// 導出のたびに学習途中の制約条件を走査するため遅くなる
+    pub fn enable_resolve_checks(&mut self, resolve_checks: bool) {
+        self.resolve.set_checks(resolve_checks);
+    }
+
+    pub fn integrality_tolerance(&self) -> f64 {
+        return self.resolve.integrality_tolerance();
+    }
+
+    /// 係数を整数とみなす許容誤差を設定する
+    ///
+    /// 大きくすると丸め誤差には強くなるが，整数でない係数を整数とみなして切り捨てるため cut が弱くなる．
+    /// 小さくすると丸め誤差の蓄積で整数のはずの係数が丸められ，誤った矛盾を導くおそれがある
+    pub fn set_integrality_tolerance(&mut self, integrality_tolerance: f64) {
+        self.resolve.set_integrality_tolerance(integrality_tolerance);
+    }
     pub fn call(
         &mut self,
         conflict_variable: usize,
         conflict_explain_keys: [PBExplainKey; 2],
         engine: &PBEngine,
     ) -> AnalyzeResult<
-        impl LinearConstraintTrait<Value = u64> + '_,
+        impl LinearConstraintTrait<Value = Coefficient> + '_,
         impl Iterator<Item = Literal> + '_,
+        impl Iterator<Item = PBExplainKey> + '_,
     > {
         self.conflicting_assignments.clear();
         // TODO: 意味があるのか確認
@@ -74,6 +174,9 @@ impl Analyze {
             .insert(Literal::new(conflict_variable, Boolean::FALSE));
         // self.conflicting_assignments
         //     .insert(Literal::new(conflict_variable, Boolean::TRUE));
+        self.participating_keys.clear();
+        self.participating_keys
+            .extend(conflict_explain_keys.iter().cloned());
 
         // conflict_constraint を初期化
         self.conflict_constraint.replace(
@@ -115,7 +218,7 @@ impl Analyze {
                 debug_assert!(sup < self.conflict_constraint.lower());
             }
 
-            let sup0: u64 = self
+            let sup0: Coefficient = self
                 .conflict_constraint
                 .iter_terms()
                 .map(|(_, coefficient)| coefficient)
@@ -128,19 +231,68 @@ impl Analyze {
                 self.calculate_propagation_level
                     .call(&self.conflict_constraint, engine, false)
             {
-                let backjump_level = self
+                // True が割り当てられている項を取り除いても，各決定レベルにおける
+                // 左辺値の上界と右辺値の差は変わらず，伝播し得るリテラルも False または
+                // 未割り当てのリテラルが残るため，矛盾と伝播が発生することは変わらない
+                if let Some(max_learnt_terms) = self.max_learnt_terms
+                    && self.conflict_constraint.len() > max_learnt_terms
+                {
+                    self.conflict_constraint.replace(self.weaken.drop_terms(
+                        &self.conflict_constraint,
+                        max_learnt_terms,
+                        |literal| engine.is_true(literal),
+                        |literal| engine.activity(literal.index()),
+                    ));
+                }
+                let mut backjump_level = self
                     .calculate_propagation_level
                     .call(&self.conflict_constraint, engine, true)
                     .unwrap();
+                if self.target_weakening {
+                    let target = self
+                        .conflict_constraint
+                        .iter_terms()
+                        .filter(|&(literal, _)| engine.is_false(literal))
+                        .max_by_key(|&(literal, _)| engine.get_assignment_order(literal.index()))
+                        .unwrap()
+                        .0;
+                    self.weakened_constraint.replace(self.weaken.weaken_to_literal(
+                        &self.conflict_constraint,
+                        target,
+                        |literal| engine.is_false(literal),
+                    ));
+                    if let Some(asserting_level) = self.calculate_propagation_level.asserting_level(
+                        &self.weakened_constraint,
+                        target,
+                        engine,
+                    ) {
+                        // target より係数の大きな項が，より前の決定レベルで伝播し得る
+                        let propagation_level = self
+                            .calculate_propagation_level
+                            .call(&self.weakened_constraint, engine, true)
+                            .unwrap_or(asserting_level);
+                        self.conflict_constraint.replace(&self.weakened_constraint);
+                        backjump_level = asserting_level.min(propagation_level);
+                    }
+                }
                 for (literal, _) in self.conflict_constraint.iter_terms() {
                     if engine.is_false(literal) {
                         self.conflicting_assignments.insert(literal);
                     }
                 }
+                let plbd = self
+                    .calculate_plbd
+                    .calculate_for_constraint(&self.conflict_constraint, engine);
+                let asserting_literal =
+                    asserting_literal(&self.conflict_constraint, backjump_level, engine);
                 return AnalyzeResult::Backjumpable {
-                    backjump_level: backjump_level,
+                    backjump_level,
                     learnt_constraint: &self.conflict_constraint,
                     conflicting_assignments: self.conflicting_assignments.iter(),
+                    plbd,
+                    conflict_level: engine.decision_level(),
+                    asserting_literal,
+                    participating_keys: self.participating_keys.iter().cloned(),
                 };
             }
 
@@ -161,15 +313,14 @@ impl Analyze {
 
             conflict_order = engine.get_assignment_order(conflict_literal.index());
 
-            let reason_constraint = {
-                let Reason::Propagation { explain_key } =
-                    engine.get_reason(conflict_literal.index()).unwrap()
-                else {
-                    unreachable!()
-                };
-                engine.explain(explain_key)
+            let Reason::Propagation { explain_key } =
+                engine.get_reason(conflict_literal.index()).unwrap()
+            else {
+                unreachable!()
             };
-            let reason_constraint = drop_fixed_variable(&reason_constraint, engine);
+            self.participating_keys.push(explain_key);
+            engine.explain_into(explain_key, &mut self.reason_constraint);
+            let reason_constraint = drop_fixed_variable(&self.reason_constraint, engine);
 
             let resolved_constraint = self.resolve.call(
                 &self.conflict_constraint,
@@ -189,4 +340,359 @@ impl Analyze {
     }
 }
 
+impl Analyze {
+    /// 含意グラフ上の導出により first-UIP 節を学習する
+    ///
+    /// 各伝播の理由は，伝播の時点で False であったリテラルのうち伝播に必要なものに限定した節とみなす．
+    /// 学習制約は係数が全て 1 で右辺が 1 の制約条件として返す
+    pub fn call_clausal(
+        &mut self,
+        conflict_variable: usize,
+        conflict_explain_keys: [PBExplainKey; 2],
+        engine: &PBEngine,
+    ) -> AnalyzeResult<
+        impl LinearConstraintTrait<Value = Coefficient> + '_,
+        impl Iterator<Item = Literal> + '_,
+        impl Iterator<Item = PBExplainKey> + '_,
+    > {
+        self.conflicting_assignments.clear();
+        self.conflicting_assignments
+            .insert(Literal::new(conflict_variable, Boolean::FALSE));
+        self.participating_keys.clear();
+        self.participating_keys
+            .extend(conflict_explain_keys.iter().cloned());
+        self.seen_variables.clear();
+        self.clause_literals.clear();
+
+        // 矛盾の原因となった割り当てを列挙
+        self.causal_assignments.clear();
+        for value in [Boolean::FALSE, Boolean::TRUE] {
+            let explain_key = conflict_explain_keys[value];
+            let (causal_assignments, _) = self.identify_propagation_causals.call(
+                &LinearConstraintView::new(
+                    engine.explain_terms(explain_key),
+                    engine.explain_lower(explain_key),
+                ),
+                Literal::new(conflict_variable, value),
+                |literal| Reverse(engine.get_decision_level(literal.index())),
+                engine,
+            );
+            self.causal_assignments.extend(causal_assignments.iter());
+        }
+
+        // 原因となった割り当ての決定レベルの最大値で UIP を探す
+        let conflict_level = self
+            .causal_assignments
+            .iter()
+            .map(|assignment| engine.get_decision_level(assignment.index()))
+            .max()
+            .unwrap_or(0);
+        if conflict_level == 0 {
+            return AnalyzeResult::Unsatisfiable;
+        }
+
+        // conflict_level で割り当てられた，未だ導出に用いていない割り当ての数
+        let mut number_of_unresolved_assignments = 0;
+        for &assignment in self.causal_assignments.iter() {
+            number_of_unresolved_assignments += mark_causal_assignment(
+                assignment,
+                conflict_level,
+                &mut self.seen_variables,
+                &mut self.clause_literals,
+                engine,
+            );
+        }
+
+        let mut order = engine.order_range(conflict_level).end;
+        let uip = loop {
+            order -= 1;
+            let assignment = engine.get_assignment(order);
+            if !self.seen_variables.contains_key(assignment.index()) {
+                continue;
+            }
+            self.conflicting_assignments.insert(assignment);
+            number_of_unresolved_assignments -= 1;
+            if number_of_unresolved_assignments == 0 {
+                break assignment;
+            }
+
+            let Some(Reason::Propagation { explain_key }) = engine.get_reason(assignment.index())
+            else {
+                unreachable!()
+            };
+            self.participating_keys.push(explain_key);
+            let (causal_assignments, _) = self.identify_propagation_causals.call(
+                &LinearConstraintView::new(
+                    engine.explain_terms(explain_key),
+                    engine.explain_lower(explain_key),
+                ),
+                assignment,
+                |literal| Reverse(engine.get_decision_level(literal.index())),
+                engine,
+            );
+            for assignment in causal_assignments.iter() {
+                number_of_unresolved_assignments += mark_causal_assignment(
+                    assignment,
+                    conflict_level,
+                    &mut self.seen_variables,
+                    &mut self.clause_literals,
+                    engine,
+                );
+            }
+        };
+
+        // 伝播の原因となった割り当てが全て導出に現れているリテラルは，節から除いても
+        // 残りのリテラルから導かれる (自己包含導出による最小化)
+        if self.minimization {
+            let identify_propagation_causals = &mut self.identify_propagation_causals;
+            let seen_variables = &self.seen_variables;
+            let participating_keys = &mut self.participating_keys;
+            self.clause_literals.retain(|&literal| {
+                let Some(Reason::Propagation { explain_key }) = engine.get_reason(literal.index())
+                else {
+                    return true;
+                };
+                let (causal_assignments, _) = identify_propagation_causals.call(
+                    &LinearConstraintView::new(
+                        engine.explain_terms(explain_key),
+                        engine.explain_lower(explain_key),
+                    ),
+                    !literal,
+                    |literal| Reverse(engine.get_decision_level(literal.index())),
+                    engine,
+                );
+                let is_redundant = causal_assignments.iter().all(|assignment| {
+                    engine.get_decision_level(assignment.index()) == 0
+                        || seen_variables.contains_key(assignment.index())
+                });
+                if is_redundant {
+                    participating_keys.push(explain_key);
+                }
+                return !is_redundant;
+            });
+        }
+
+        let backjump_level = self
+            .clause_literals
+            .iter()
+            .map(|literal| engine.get_decision_level(literal.index()))
+            .max()
+            .unwrap_or(0);
+        debug_assert!(backjump_level < engine.get_decision_level(uip.index()));
+        self.clause_literals.push(!uip);
+        debug_assert!(
+            self.clause_literals
+                .iter()
+                .all(|&literal| engine.is_false(literal))
+        );
+        self.conflicting_assignments
+            .extend(self.clause_literals.iter().cloned());
+        let plbd = self
+            .calculate_plbd
+            .calculate(self.clause_literals.iter().map(|&literal| !literal), engine);
+        self.conflict_constraint.replace(LinearConstraintView::new(
+            self.clause_literals.iter().map(|&literal| (literal, 1)),
+            1,
+        ));
+
+        return AnalyzeResult::Backjumpable {
+            backjump_level,
+            learnt_constraint: &self.conflict_constraint,
+            conflicting_assignments: self.conflicting_assignments.iter(),
+            plbd,
+            conflict_level: engine.decision_level(),
+            asserting_literal: !uip,
+            participating_keys: self.participating_keys.iter().cloned(),
+        };
+    }
+}
+
+/// backjump_level まで戻ったときに linear_constraint が伝播するリテラルを返す
+///
+/// 伝播するリテラルが複数ある場合は係数が最大のものを返す
+fn asserting_literal(
+    linear_constraint: &impl LinearConstraintTrait<Value = Coefficient>,
+    backjump_level: usize,
+    engine: &PBEngine,
+) -> Literal {
+    let is_assigned_at = |literal: Literal| {
+        engine.is_assigned(literal.index())
+            && engine.get_decision_level(literal.index()) <= backjump_level
+    };
+    let sup: Coefficient = linear_constraint
+        .iter_terms()
+        .filter(|&(literal, _)| !(is_assigned_at(literal) && engine.is_false(literal)))
+        .map(|(_, coefficient)| coefficient)
+        .sum();
+    let slack = sup - linear_constraint.lower();
+    return linear_constraint
+        .iter_terms()
+        .filter(|&(literal, coefficient)| !is_assigned_at(literal) && coefficient > slack)
+        .max_by_key(|&(_, coefficient)| coefficient)
+        .unwrap()
+        .0;
+}
+
+/// 導出に現れた割り当てを記録し， conflict_level で割り当てられた未記録の割り当てであれば 1 を返す
+///
+/// conflict_level より前の決定レベルの割り当ては，その否定を節に加える
+fn mark_causal_assignment(
+    assignment: Literal,
+    conflict_level: usize,
+    seen_variables: &mut Set,
+    clause_literals: &mut Vec<Literal>,
+    engine: &PBEngine,
+) -> usize {
+    let decision_level = engine.get_decision_level(assignment.index());
+    if decision_level == 0 || seen_variables.contains_key(assignment.index()) {
+        return 0;
+    }
+    seen_variables.insert(assignment.index());
+    if decision_level == conflict_level {
+        return 1;
+    } else {
+        clause_literals.push(!assignment);
+        return 0;
+    }
+}
+
 pub use utility::strengthen_integer_linear_constraint;
+
+#[cfg(test)]
+mod test {
+    use super::{Analyze, AnalyzeResult};
+    use crate::{Boolean, LinearConstraintTrait, Literal, PBEngine, PBState};
+
+    #[test]
+    fn test_call_clausal() {
+        // x0 → x2, x1 ∧ x2 → x3, x2 ∧ x3 → x4, x3 → x5, ¬x4 ∨ ¬x5
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..6 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        for clause in [
+            vec![!x(0), x(2)],
+            vec![!x(1), !x(2), x(3)],
+            vec![!x(2), !x(3), x(4)],
+            vec![!x(3), x(5)],
+            vec![!x(4), !x(5)],
+        ] {
+            pb_engine.add_clause(clause.into_iter(), false).unwrap();
+        }
+
+        // 決定レベル 1 で x0 ，決定レベル 2 で x1 を True とすると矛盾する
+        pb_engine.assume(x(0));
+        assert!(pb_engine.propagate().is_noconflict());
+        pb_engine.assume(x(1));
+        let PBState::Conflict {
+            index,
+            explain_keys,
+        } = pb_engine.propagate()
+        else {
+            unreachable!()
+        };
+
+        // 決定 x1 ではなく first-UIP である x3 の否定と，決定レベル 1 の x2 の否定からなる節を学習する
+        let mut analyze = Analyze::new(1e-10);
+        let AnalyzeResult::Backjumpable {
+            backjump_level,
+            learnt_constraint,
+            conflict_level,
+            asserting_literal,
+            ..
+        } = analyze.call_clausal(index, explain_keys, &pb_engine)
+        else {
+            unreachable!()
+        };
+        assert!(
+            learnt_constraint
+                .iter_terms()
+                .all(|(_, coefficient)| coefficient == 1)
+        );
+        let mut literals =
+            Vec::from_iter(learnt_constraint.iter_terms().map(|(literal, _)| literal));
+        literals.sort_unstable_by_key(|literal| literal.index());
+        assert!(literals == vec![!x(2), !x(3)]);
+        assert!(learnt_constraint.lower() == 1);
+        assert!(backjump_level == 1 && conflict_level == 2);
+        assert!(asserting_literal == !x(3));
+    }
+
+    #[test]
+    fn test_call_clausal_minimization() {
+        // x0 → x1, x2 ∧ x0 → x3, x2 ∧ x1 → x4, ¬x3 ∨ ¬x4
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        let mut learnt_clauses = Vec::default();
+        for minimization in [false, true] {
+            let mut pb_engine = PBEngine::new(10.0);
+            for _ in 0..5 {
+                pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+            }
+            for clause in [
+                vec![!x(0), x(1)],
+                vec![!x(2), !x(0), x(3)],
+                vec![!x(2), !x(1), x(4)],
+                vec![!x(3), !x(4)],
+            ] {
+                pb_engine.add_clause(clause.into_iter(), false).unwrap();
+            }
+            pb_engine.assume(x(0));
+            assert!(pb_engine.propagate().is_noconflict());
+            pb_engine.assume(x(2));
+            let PBState::Conflict {
+                index,
+                explain_keys,
+            } = pb_engine.propagate()
+            else {
+                unreachable!()
+            };
+
+            let mut analyze = Analyze::new(1e-10);
+            analyze.set_minimization(minimization);
+            let AnalyzeResult::Backjumpable {
+                backjump_level,
+                learnt_constraint,
+                asserting_literal,
+                ..
+            } = analyze.call_clausal(index, explain_keys, &pb_engine)
+            else {
+                unreachable!()
+            };
+            assert!(backjump_level == 1 && asserting_literal == !x(2));
+            let mut literals =
+                Vec::from_iter(learnt_constraint.iter_terms().map(|(literal, _)| literal));
+            literals.sort_unstable_by_key(|literal| literal.index());
+            learnt_clauses.push(literals);
+        }
+
+        // ¬x1 は x0 から伝播しており，x0 が節に含まれるため最小化で取り除かれる
+        assert!(learnt_clauses[0] == vec![!x(0), !x(1), !x(2)]);
+        assert!(learnt_clauses[1] == vec![!x(0), !x(2)]);
+    }
+
+    #[test]
+    fn test_call_clausal_unsatisfiable() {
+        // 決定レベル 0 で x0 が True となり，x1 と ¬x1 がともに伝播する
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..2 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        for clause in [vec![x(0)], vec![!x(0), x(1)], vec![!x(0), !x(1)]] {
+            pb_engine.add_clause(clause.into_iter(), false).unwrap();
+        }
+        let PBState::Conflict {
+            index,
+            explain_keys,
+        } = pb_engine.propagate()
+        else {
+            unreachable!()
+        };
+        let mut analyze = Analyze::new(1e-10);
+        assert!(matches!(
+            analyze.call_clausal(index, explain_keys, &pb_engine),
+            AnalyzeResult::Unsatisfiable
+        ));
+    }
+}
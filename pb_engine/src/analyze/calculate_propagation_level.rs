@@ -1,16 +1,18 @@
 use utility::Map;
 
-use crate::{LinearConstraintTrait, PBEngine};
+use crate::{Coefficient, LinearConstraintTrait, Literal, decision_stack::DecisionStack};
 
 #[derive(Clone, Copy)]
 struct State {
-    sup: u64,
-    max_interval: u64,
+    sup: Coefficient,
+    max_interval: Coefficient,
 }
 
+#[derive(Clone)]
 pub struct CalculatePropagationLevel {
     decision_level_to_difference: Map<State>,
     decision_level_and_state: Vec<(usize, State)>,
+    falsified_terms: Vec<(usize, Coefficient)>,
 }
 
 impl CalculatePropagationLevel {
@@ -18,13 +20,14 @@ impl CalculatePropagationLevel {
         Self {
             decision_level_to_difference: Map::default(),
             decision_level_and_state: Vec::default(),
+            falsified_terms: Vec::default(),
         }
     }
 
     pub fn call(
         &mut self,
-        linear_constraint: impl LinearConstraintTrait<Value = u64>,
-        engine: &PBEngine,
+        linear_constraint: impl LinearConstraintTrait<Value = Coefficient>,
+        decision_stack: &DecisionStack<impl Copy>,
         include_nonfalsified_literals: bool,
     ) -> Option<usize> {
         // 各決定レベルでの上界の減少量と，割り当てられた変数の係数の最大値を算出
@@ -37,8 +40,8 @@ impl CalculatePropagationLevel {
             },
         );
         for (literal, coefficient) in linear_constraint.iter_terms() {
-            if engine.is_false(literal) {
-                let decision_level = engine.get_decision_level(literal.index());
+            if decision_stack.is_false(literal) {
+                let decision_level = decision_stack.get_decision_level(literal.index());
                 if let Some(difference) = self.decision_level_to_difference.get_mut(decision_level)
                 {
                     difference.sup += coefficient;
@@ -52,8 +55,8 @@ impl CalculatePropagationLevel {
                         },
                     );
                 }
-            } else if include_nonfalsified_literals && engine.is_true(literal) {
-                let decision_level = engine.get_decision_level(literal.index());
+            } else if include_nonfalsified_literals && decision_stack.is_true(literal) {
+                let decision_level = decision_stack.get_decision_level(literal.index());
                 if let Some(difference) = self.decision_level_to_difference.get_mut(decision_level)
                 {
                     difference.max_interval = difference.max_interval.max(coefficient);
@@ -81,7 +84,7 @@ impl CalculatePropagationLevel {
 
         // 各決定レベルでの左辺値の上界を算出
         {
-            let mut sup = linear_constraint.iter_terms().map(|(_, c)| c).sum::<u64>();
+            let mut sup = linear_constraint.iter_terms().map(|(_, c)| c).sum::<Coefficient>();
             for i in 0..self.decision_level_and_state.len() {
                 sup -= self.decision_level_and_state[i].1.sup;
                 self.decision_level_and_state[i].1.sup = sup;
@@ -92,7 +95,7 @@ impl CalculatePropagationLevel {
         {
             let mut max_interval = linear_constraint
                 .iter_terms()
-                .filter(|(l, _)| !engine.is_assigned(l.index()))
+                .filter(|(l, _)| !decision_stack.is_assigned(l.index()))
                 .map(|(_, c)| c)
                 .max_by(|l, r| l.partial_cmp(r).unwrap())
                 .unwrap_or(0);
@@ -119,4 +122,116 @@ impl CalculatePropagationLevel {
         }
         return None;
     }
+
+    /// 制約条件が literal を伝播する最小の決定レベルを返す
+    ///
+    /// literal には現在 False が割り当てられている必要がある．literal の決定レベルより前に
+    /// literal を伝播しない場合や，伝播するより前に矛盾する場合は None を返す
+    pub fn asserting_level(
+        &mut self,
+        linear_constraint: impl LinearConstraintTrait<Value = Coefficient>,
+        literal: Literal,
+        decision_stack: &DecisionStack<impl Copy>,
+    ) -> Option<usize> {
+        debug_assert!(decision_stack.is_false(literal));
+        let literal_level = decision_stack.get_decision_level(literal.index());
+        let mut coefficient_of_literal = 0;
+        let mut sup: Coefficient = 0;
+        self.falsified_terms.clear();
+        for (term_literal, coefficient) in linear_constraint.iter_terms() {
+            sup += coefficient;
+            if term_literal == literal {
+                coefficient_of_literal = coefficient;
+            } else if decision_stack.is_false(term_literal) {
+                self.falsified_terms
+                    .push((decision_stack.get_decision_level(term_literal.index()), coefficient));
+            }
+        }
+        self.falsified_terms.sort_unstable_by_key(|&(decision_level, _)| decision_level);
+
+        // 決定レベル decision_level での左辺値の上界は，それ以前に False が割り当てられた項を除いた係数の和
+        let lower = linear_constraint.lower();
+        let mut decision_level = 0;
+        let mut i = 0;
+        while decision_level < literal_level {
+            while i < self.falsified_terms.len() && self.falsified_terms[i].0 <= decision_level {
+                sup -= self.falsified_terms[i].1;
+                i += 1;
+            }
+            if sup < lower {
+                return None;
+            }
+            if sup - coefficient_of_literal < lower {
+                return Some(decision_level);
+            }
+            // 上界が変化するのは次に項が False になる決定レベル
+            match self.falsified_terms.get(i) {
+                Some(&(next_level, _)) => decision_level = next_level,
+                None => decision_level = literal_level,
+            }
+        }
+        return None;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CalculatePropagationLevel;
+    use crate::{
+        Boolean, Coefficient, LinearConstraint, LinearConstraintTrait, Literal, PBEngine, analyze::weaken::Weaken,
+    };
+
+    #[test]
+    fn test_asserting_level() {
+        // 決定レベル 1, 2, 3 でそれぞれ y = True, a = False, b = False を決定する
+        let mut pb_engine = PBEngine::new(10.0);
+        for value in [Boolean::TRUE, Boolean::FALSE, Boolean::FALSE] {
+            pb_engine.add_variable_with_initial_value(value, 0.0);
+        }
+        pb_engine.set_decision_order(vec![0, 1, 2]);
+        for _ in 0..3 {
+            pb_engine.decide();
+            assert!(pb_engine.propagate().is_noconflict());
+        }
+        let (y, a, b) = (
+            Literal::new(0, Boolean::TRUE),
+            Literal::new(1, Boolean::TRUE),
+            Literal::new(2, Boolean::TRUE),
+        );
+
+        // 2 y + a + b >= 3 は決定レベル 0 で y を伝播する
+        let constraint = LinearConstraint::new([(y, 2), (a, 1), (b, 1)].into_iter(), 3);
+        let mut calculate_propagation_level = CalculatePropagationLevel::new();
+        let backjump_level = calculate_propagation_level.call(&constraint, &pb_engine, true);
+        assert!(backjump_level == Some(0));
+
+        // b に向けて weaken すると a + b >= 1 となり，決定レベル 2 で b を伝播する
+        let mut weaken = Weaken::new();
+        let mut weakened_constraint = LinearConstraint::default();
+        weakened_constraint.replace(weaken.weaken_to_literal(&constraint, b, |literal| {
+            pb_engine.is_false(literal)
+        }));
+        assert!(weakened_constraint.len() == 2);
+        assert!(weakened_constraint.lower() == 1);
+        let asserting_level =
+            calculate_propagation_level.asserting_level(&weakened_constraint, b, &pb_engine);
+        assert!(asserting_level == Some(2));
+        assert!(asserting_level.unwrap() > backjump_level.unwrap());
+        // 元の制約条件は a を伝播しない
+        assert!(calculate_propagation_level.asserting_level(&constraint, a, &pb_engine).is_none());
+
+        // 元の制約条件を満たす全ての割り当てが weaken された制約条件を満たす
+        for bits in 0..(1 << 3) {
+            let lhs = |constraint: &LinearConstraint<_>| -> Coefficient {
+                constraint
+                    .iter_terms()
+                    .filter(|(literal, _)| bits >> literal.index() & 1 == 1)
+                    .map(|(_, coefficient)| coefficient)
+                    .sum()
+            };
+            if lhs(&constraint) >= constraint.lower() {
+                assert!(lhs(&weakened_constraint) >= weakened_constraint.lower());
+            }
+        }
+    }
 }
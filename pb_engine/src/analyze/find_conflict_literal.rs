@@ -1,8 +1,8 @@
-use crate::{LinearConstraintTrait, Literal, PBEngine};
+use crate::{Coefficient, LinearConstraintTrait, Literal, PBEngine};
 
 struct Term {
     literal: Literal,
-    coefficient: u64,
+    coefficient: Coefficient,
     order: usize,
 }
 
@@ -20,7 +20,7 @@ impl Clone for FindConflictLiteral {
 impl FindConflictLiteral {
     pub fn find(
         &mut self,
-        conflict_constraint: impl LinearConstraintTrait<Value = u64>,
+        conflict_constraint: impl LinearConstraintTrait<Value = Coefficient>,
         engine: &PBEngine,
     ) -> Literal {
         self.falsified_literals.clear();
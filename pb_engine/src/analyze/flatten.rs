@@ -1,11 +1,10 @@
 use std::cmp::{max, min};
-use std::{u64, u128};
 
 use either::Either;
 use ordered_float::OrderedFloat;
 
 use crate::analyze::utility::divide_linear_constraint;
-use crate::{LinearConstraintTrait, PBEngine};
+use crate::{Coefficient, LinearConstraintTrait, PBEngine};
 
 use super::identify_conflict_causals::IdentifyConflictCausals;
 use super::round::{Round, Round2};
@@ -13,14 +12,14 @@ use super::weaken::Weaken;
 
 #[derive(Clone)]
 pub struct FlattenConflictConstraint {
-    threshold: u64,
+    threshold: Coefficient,
     identify_causals: IdentifyConflictCausals<u128>,
     weaken: Weaken<u128>,
     round: Round2<u128>,
 }
 
 impl FlattenConflictConstraint {
-    pub fn new(threshold: u64) -> Self {
+    pub fn new(threshold: Coefficient) -> Self {
         Self {
             threshold,
             identify_causals: IdentifyConflictCausals::default(),
@@ -1,9 +1,9 @@
-use crate::{LinearConstraintTrait, Literal, PBEngine, collections::LiteralSet};
+use crate::{Coefficient, LinearConstraintTrait, Literal, PBEngine, collections::LiteralSet};
 
 #[derive(Clone)]
 struct Term {
     literal: Literal,
-    coefficient: u64,
+    coefficient: Coefficient,
 }
 
 #[derive(Clone)]
@@ -22,11 +22,11 @@ impl IdentifyPropagationCausals {
 
     pub fn call<PriorityT: Ord>(
         &mut self,
-        reason_constraint: &impl LinearConstraintTrait<Value = u64>,
+        reason_constraint: &impl LinearConstraintTrait<Value = Coefficient>,
         propagated_literal: Literal,
         get_priority: impl Fn(Literal) -> PriorityT,
         pb_engine: &PBEngine,
-    ) -> (&'_ LiteralSet, u64) {
+    ) -> (&'_ LiteralSet, Coefficient) {
         let resolving_order = pb_engine.get_assignment_order(propagated_literal.index());
 
         self.causal_terms.clear();
@@ -1,7 +1,7 @@
 use num::integer::gcd;
 
 use crate::{
-    LinearConstraintTrait, PBEngine,
+    Coefficient, LinearConstraintTrait, LinearConstraintView, Literal, PBEngine,
     analyze::utility::{lhs_sup_of_linear_constraint_at, strengthen_integer_linear_constraint},
     constraints::RandomAccessibleLinearConstraint,
 };
@@ -11,6 +11,7 @@ use super::round_reason_constraint::RoundReasonConstraint;
 pub struct Resolve {
     round_constraint: RoundReasonConstraint,
     resolved_constraint: RandomAccessibleLinearConstraint<u128>,
+    checks: bool,
 }
 
 impl Resolve {
@@ -18,12 +19,27 @@ impl Resolve {
         Self {
             round_constraint: RoundReasonConstraint::new(integrality_tolerance),
             resolved_constraint: RandomAccessibleLinearConstraint::default(),
+            checks: false,
         }
     }
+
+    /// call の結果が矛盾を導くことを確認するかどうかを設定する (既定は無効)
+    pub fn set_checks(&mut self, checks: bool) {
+        self.checks = checks;
+    }
+
+    pub fn integrality_tolerance(&self) -> f64 {
+        return self.round_constraint.integrality_tolerance();
+    }
+
+    pub fn set_integrality_tolerance(&mut self, integrality_tolerance: f64) {
+        self.round_constraint
+            .set_integrality_tolerance(integrality_tolerance);
+    }
     pub fn call(
         &mut self,
-        conflict_constraint: &impl LinearConstraintTrait<Value = u64>,
-        reason_constraint: &impl LinearConstraintTrait<Value = u64>,
+        conflict_constraint: &impl LinearConstraintTrait<Value = Coefficient>,
+        reason_constraint: &impl LinearConstraintTrait<Value = Coefficient>,
         resolving_variable: usize,
         engine: &PBEngine,
     ) -> impl LinearConstraintTrait<Value = u128> + '_ {
@@ -41,6 +57,57 @@ impl Resolve {
 
         let conflict_order = engine.get_assignment_order(propagated_literal.index());
 
+        // 項の数は変数の数を超えないため，一度確保すれば以降の矛盾では再確保されない
+        self.resolved_constraint.clear();
+        self.resolved_constraint.reserve(engine.number_of_variables());
+
+        if self
+            .try_resolve(
+                conflict_constraint,
+                reason_constraint,
+                resolving_variable,
+                propagated_literal,
+                conflict_order,
+                engine,
+            )
+            .is_none()
+        {
+            // 係数の積や和が u128 に収まらない場合は，両方の制約条件を節に weaken して導出する
+            self.resolve_weakened_clauses(
+                conflict_constraint,
+                reason_constraint,
+                propagated_literal,
+                conflict_order,
+                engine,
+            );
+        }
+
+        let resolved_constraint = strengthen_integer_linear_constraint(&self.resolved_constraint);
+        if self.checks {
+            check_resolved_constraint(
+                conflict_constraint,
+                reason_constraint,
+                &resolved_constraint,
+                resolving_variable,
+                conflict_order,
+                engine,
+            );
+        }
+        return resolved_constraint;
+    }
+
+    /// 係数を掛けて足し合わせた (必要であれば丸めた) 制約条件を resolved_constraint に格納する
+    ///
+    /// 係数の積や和が u128 に収まらない場合は None を返す
+    fn try_resolve(
+        &mut self,
+        conflict_constraint: &impl LinearConstraintTrait<Value = Coefficient>,
+        reason_constraint: &impl LinearConstraintTrait<Value = Coefficient>,
+        resolving_variable: usize,
+        propagated_literal: Literal,
+        conflict_order: usize,
+        engine: &PBEngine,
+    ) -> Option<()> {
         let conflict_coefficient = conflict_constraint
             .iter_terms()
             .find(|&(literal, _)| literal.index() == resolving_variable)
@@ -64,13 +131,15 @@ impl Resolve {
                 self.resolved_constraint.replace_by_linear_constraint(
                     conflict_constraint
                         .convert()
-                        .mul(reason_coefficient as u128),
-                );
-                self.resolved_constraint.add_assign(
-                    reason_constraint
-                        .convert()
-                        .mul(conflict_coefficient as u128),
+                        .try_mul(reason_coefficient as u128)?,
                 );
+                // u64 の係数どうしの積は u128 に収まるため，積と和が溢れるのは係数が u128 の場合
+                // (wide-coefficients) に限られる．その場合は折り返さずに None を返す
+                reason_constraint
+                    .convert()
+                    .try_mul(conflict_coefficient as u128)?
+                    .accumulate_into(&mut self.resolved_constraint)
+                    .ok()?;
             } else {
                 self.resolved_constraint
                     .replace_by_linear_constraint(conflict_constraint.convert());
@@ -82,18 +151,19 @@ impl Resolve {
                     engine,
                 );
 
-                self.resolved_constraint.add_assign(
-                    rounded_reason_constraint
-                        .convert()
-                        .mul(conflict_coefficient as u128),
-                );
+                rounded_reason_constraint
+                    .convert()
+                    .try_mul(conflict_coefficient as u128)?
+                    .accumulate_into(&mut self.resolved_constraint)
+                    .ok()?;
             }
         } else {
             let conflict_slack = conflict_sup - conflict_constraint.lower();
             // if (conflict_slack as f64) / (conflict_coefficient as f64) + (reason_slack as f64) / (reason_coefficient as f64) < 0.9999 {
-            if (conflict_slack as u128) * (reason_coefficient as u128)
-                + (reason_slack as u128) * (conflict_coefficient as u128)
-                < (conflict_coefficient as u128) * (reason_coefficient as u128)
+            if (conflict_slack as u128)
+                .checked_mul(reason_coefficient as u128)?
+                .checked_add((reason_slack as u128).checked_mul(conflict_coefficient as u128)?)?
+                < (conflict_coefficient as u128).checked_mul(reason_coefficient as u128)?
             {
                 // if conflict_slack == 0 || reason_slack == 0 {
                 // if conflict_slack == 0 && reason_slack == 0 {
@@ -101,20 +171,20 @@ impl Resolve {
                 self.resolved_constraint.replace_by_linear_constraint(
                     conflict_constraint
                         .convert()
-                        .mul((reason_coefficient / g) as u128),
-                );
-                self.resolved_constraint.add_assign(
-                    reason_constraint
-                        .convert()
-                        .mul((conflict_coefficient / g) as u128),
+                        .try_mul((reason_coefficient / g) as u128)?,
                 );
+                reason_constraint
+                    .convert()
+                    .try_mul((conflict_coefficient / g) as u128)?
+                    .accumulate_into(&mut self.resolved_constraint)
+                    .ok()?;
             } else {
                 // MEMO: どちらを丸めても大して変わらない？
                 // slack が小さい方を丸める
                 // if (conflict_slack as u128) * (reason_coefficient as u128) > (reason_slack as u128) * (conflict_coefficient as u128) {
                 // slack が大きい方を丸める
-                if (conflict_slack as u128) * (reason_coefficient as u128)
-                    < (reason_slack as u128) * (conflict_coefficient as u128)
+                if (conflict_slack as u128).saturating_mul(reason_coefficient as u128)
+                    < (reason_slack as u128).saturating_mul(conflict_coefficient as u128)
                 {
                     // 係数が小さい方を丸める
                     // if reason_coefficient <= conflict_coefficient {
@@ -131,11 +201,11 @@ impl Resolve {
                         engine,
                     );
 
-                    self.resolved_constraint.add_assign(
-                        rounded_reason_constraint
-                            .convert()
-                            .mul(conflict_coefficient as u128),
-                    );
+                    rounded_reason_constraint
+                        .convert()
+                        .try_mul(conflict_coefficient as u128)?
+                        .accumulate_into(&mut self.resolved_constraint)
+                        .ok()?;
                 } else {
                     self.resolved_constraint
                         .replace_by_linear_constraint(reason_constraint.convert());
@@ -147,15 +217,150 @@ impl Resolve {
                         engine,
                     );
 
-                    self.resolved_constraint.add_assign(
-                        rounded_conflict_constraint
-                            .convert()
-                            .mul(reason_coefficient as u128),
-                    );
+                    rounded_conflict_constraint
+                        .convert()
+                        .try_mul(reason_coefficient as u128)?
+                        .accumulate_into(&mut self.resolved_constraint)
+                        .ok()?;
                 }
             }
         }
 
-        return strengthen_integer_linear_constraint(&self.resolved_constraint);
+        return Some(());
+    }
+
+    /// 両方の制約条件を conflict_order の直前に False であるリテラルの節に weaken し，
+    /// それらの導出を resolved_constraint に格納する
+    ///
+    /// 節は係数が 1 であるため，係数の大きさによらず溢れない
+    fn resolve_weakened_clauses(
+        &mut self,
+        conflict_constraint: &impl LinearConstraintTrait<Value = Coefficient>,
+        reason_constraint: &impl LinearConstraintTrait<Value = Coefficient>,
+        propagated_literal: Literal,
+        conflict_order: usize,
+        engine: &PBEngine,
+    ) {
+        // 矛盾している制約条件は False のリテラルのみを残すと右辺が正のままであり，それらの節を導く
+        self.resolved_constraint
+            .replace_by_linear_constraint(LinearConstraintView::new(
+                conflict_constraint
+                    .iter_terms()
+                    .filter(|&(literal, _)| engine.is_false_at(literal, conflict_order))
+                    .map(|(literal, _)| (literal, 1)),
+                1,
+            ));
+        // 伝播の理由は propagated_literal と伝播の時点で False のリテラルからなる節を導く
+        LinearConstraintView::new(
+            reason_constraint
+                .iter_terms()
+                .filter(|&(literal, _)| {
+                    literal == propagated_literal || engine.is_false_at(literal, conflict_order - 1)
+                })
+                .map(|(literal, _)| (literal, 1)),
+            1,
+        )
+        .accumulate_into(&mut self.resolved_constraint)
+        .unwrap();
+    }
+}
+
+/// resolved_constraint が resolving_variable を含まず， resolving_variable の割り当ての直前に
+/// 矛盾していることを確認し，そうでなければ入力の制約条件を表示して panic する
+///
+/// 2 つの制約条件の正の線形結合 (と丸め) は割り当てによらず導かれるため，
+/// 直前の割り当てで矛盾していなければ符号や相殺の誤りにより健全でない制約条件を導いている
+fn check_resolved_constraint(
+    conflict_constraint: &impl LinearConstraintTrait<Value = Coefficient>,
+    reason_constraint: &impl LinearConstraintTrait<Value = Coefficient>,
+    resolved_constraint: &impl LinearConstraintTrait<Value = u128>,
+    resolving_variable: usize,
+    conflict_order: usize,
+    engine: &PBEngine,
+) {
+    let contains_resolving_variable = resolved_constraint
+        .iter_terms()
+        .any(|(literal, coefficient)| literal.index() == resolving_variable && coefficient != 0);
+    let sup = lhs_sup_of_linear_constraint_at(resolved_constraint, conflict_order - 1, engine);
+    if contains_resolving_variable || sup >= resolved_constraint.lower() {
+        panic!(
+            "unsound resolved constraint on variable {}: conflict {:?} >= {}, reason {:?} >= {}, resolved {:?} >= {}",
+            resolving_variable,
+            conflict_constraint.iter_terms().collect::<Vec<_>>(),
+            conflict_constraint.lower(),
+            reason_constraint.iter_terms().collect::<Vec<_>>(),
+            reason_constraint.lower(),
+            resolved_constraint.iter_terms().collect::<Vec<_>>(),
+            resolved_constraint.lower(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Resolve, check_resolved_constraint};
+    use crate::{Boolean, Coefficient, LinearConstraint, LinearConstraintTrait, Literal, PBEngine};
+
+    #[test]
+    fn test_resolve_overflow() {
+        // アクティビティの大きい順に x1 = 0, x2 = 0, x0 = 1 と決定する
+        let mut engine = PBEngine::new(10.0);
+        engine.add_variable_with_initial_value(Boolean::TRUE, 1.0);
+        engine.add_variable_with_initial_value(Boolean::FALSE, 3.0);
+        engine.add_variable_with_initial_value(Boolean::FALSE, 2.0);
+        for _ in 0..3 {
+            engine.decide();
+            engine.propagate();
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+
+        // 係数を掛けて足し合わせると右辺が u128 に収まらないため，節に weaken して x1 + x2 >= 1 を導く
+        let m = Coefficient::MAX;
+        let conflict_constraint = LinearConstraint::new([(!x(0), m), (x(2), m)].into_iter(), m);
+        let reason_constraint =
+            LinearConstraint::new([(x(0), m - 1), (x(1), m - 1)].into_iter(), m - 1);
+        let mut resolve = Resolve::new(1e-10);
+        resolve.set_checks(true);
+        let resolved_constraint = resolve.call(&conflict_constraint, &reason_constraint, 0, &engine);
+        let mut terms = resolved_constraint.iter_terms().collect::<Vec<_>>();
+        terms.sort_by_key(|(literal, _)| literal.index());
+        assert!(terms == vec![(x(1), 1), (x(2), 1)] && resolved_constraint.lower() == 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "unsound resolved constraint on variable 0")]
+    fn test_check_resolved_constraint() {
+        // アクティビティの大きい順に x1 = 0, x2 = 0, x0 = 1 と決定する
+        let mut engine = PBEngine::new(10.0);
+        engine.add_variable_with_initial_value(Boolean::TRUE, 1.0);
+        engine.add_variable_with_initial_value(Boolean::FALSE, 3.0);
+        engine.add_variable_with_initial_value(Boolean::FALSE, 2.0);
+        for _ in 0..3 {
+            engine.decide();
+            engine.propagate();
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+
+        // ~x0 + x2 >= 1 と x0 + x1 >= 1 から x1 + x2 >= 1 を導く
+        let conflict_constraint = LinearConstraint::new([(!x(0), 1), (x(2), 1)].into_iter(), 1);
+        let reason_constraint = LinearConstraint::new([(x(0), 1), (x(1), 1)].into_iter(), 1);
+        let mut resolve = Resolve::new(1e-10);
+        resolve.set_checks(true);
+        let resolved_constraint = resolve.call(&conflict_constraint, &reason_constraint, 0, &engine);
+        let mut terms = resolved_constraint.iter_terms().collect::<Vec<_>>();
+        terms.sort_by_key(|(literal, _)| literal.index());
+        assert!(terms == vec![(x(1), 1), (x(2), 1)] && resolved_constraint.lower() == 1);
+
+        // x0 の割り当て前に矛盾しない制約条件は健全でない
+        let corrupted_constraint =
+            LinearConstraint::new([(x(0), 1u128), (x(1), 1), (x(2), 1)].into_iter(), 1);
+        check_resolved_constraint(
+            &conflict_constraint,
+            &reason_constraint,
+            &corrupted_constraint,
+            0,
+            engine.get_assignment_order(0),
+            &engine,
+        );
     }
 }
@@ -2,7 +2,7 @@ use std::{cmp::min, fmt::Debug};
 
 use num::{Integer, Num, PrimInt, Signed, Unsigned};
 
-use crate::{LinearConstraintTrait, LinearConstraintView, Literal, PBEngine};
+use crate::{Coefficient, LinearConstraintTrait, LinearConstraintView, Literal, PBEngine};
 
 #[derive(Clone)]
 pub struct Round {
@@ -18,6 +18,14 @@ impl Round {
         }
     }
 
+    pub fn integrality_tolerance(&self) -> f64 {
+        return self.integrality_tolerance;
+    }
+
+    pub fn set_integrality_tolerance(&mut self, integrality_tolerance: f64) {
+        self.integrality_tolerance = integrality_tolerance;
+    }
+
     pub fn calculate(
         &mut self,
         constraint: &impl LinearConstraintTrait<Value = f64>,
@@ -130,17 +138,17 @@ impl Round {
             }
         }
 
-        work.lower = (lower - self.integrality_tolerance).ceil() as u64;
+        work.lower = (lower - self.integrality_tolerance).ceil() as Coefficient;
     }
 
-    pub fn get(&self) -> impl LinearConstraintTrait<Value = u64> {
+    pub fn get(&self) -> impl LinearConstraintTrait<Value = Coefficient> {
         let rounded_constraint = LinearConstraintView::new(
             self.work.terms.iter().filter_map(move |term| {
                 let coefficient = min(
                     match term.rounding {
-                        Rounding::Integer => term.coefficient.round() as u64,
-                        Rounding::Up => term.coefficient.ceil() as u64,
-                        Rounding::Down => term.coefficient.floor() as u64,
+                        Rounding::Integer => term.coefficient.round() as Coefficient,
+                        Rounding::Up => term.coefficient.ceil() as Coefficient,
+                        Rounding::Down => term.coefficient.floor() as Coefficient,
                     },
                     self.work.lower,
                 );
@@ -174,7 +182,7 @@ struct Term {
 #[derive(Default)]
 struct Work {
     terms: Vec<Term>,
-    lower: u64,
+    lower: Coefficient,
 }
 
 impl Clone for Work {
@@ -184,14 +192,14 @@ impl Clone for Work {
 }
 
 impl LinearConstraintTrait for Work {
-    type Value = u64;
+    type Value = Coefficient;
     fn iter_terms(&self) -> impl Iterator<Item = (Literal, Self::Value)> + Clone + '_ {
         return self.terms.iter().filter_map(move |term| {
             let coefficient = min(
                 match term.rounding {
-                    Rounding::Integer => term.coefficient.round() as u64,
-                    Rounding::Up => term.coefficient.ceil() as u64,
-                    Rounding::Down => term.coefficient.floor() as u64,
+                    Rounding::Integer => term.coefficient.round() as Coefficient,
+                    Rounding::Up => term.coefficient.ceil() as Coefficient,
+                    Rounding::Down => term.coefficient.floor() as Coefficient,
                 },
                 self.lower,
             );
@@ -234,6 +242,12 @@ where
         get_anticoefficient: impl Fn(Literal) -> f64,
         engine: &PBEngine,
     ) -> impl LinearConstraintTrait<Value = ValueT> + 'a {
+        // 0 では割れないため，1 で割る (係数を lower で飽和させる以外は変更しない)
+        let divisor = if divisor.is_zero() {
+            ValueT::one()
+        } else {
+            divisor
+        };
         let work = &mut self.work;
 
         work.terms.clear();
@@ -332,10 +346,13 @@ where
                     }
                 }
                 Rounding::Down => {
-                    let d = divisor - term.coefficient % term.coefficient;
+                    // 切り下げのために lower から引いた余りを戻すと丸めた右辺値がちょうど 1 増える場合は，
+                    // 係数も 1 増えるため矛盾の度合いは変わらず，より強い制約条件となる
+                    let d = divisor - term.coefficient % divisor;
                     if (lower + divisor - ValueT::one()) / divisor
                         == (lower + divisor - d - ValueT::one()) / divisor
                     {
+                        // 切り下げていた係数を切り上げる
                         term.rounding = Rounding::Up;
                         lower = lower + divisor - d;
                     }
@@ -455,3 +472,100 @@ where
 //         self.lower
 //     }
 // }
+
+#[cfg(test)]
+mod test {
+    use super::{Round, Round2};
+    use crate::{
+        Boolean, LinearConstraint, LinearConstraintTrait, LinearConstraintView, Literal, PBEngine,
+    };
+
+    #[test]
+    fn test_integrality_tolerance() {
+        let mut engine = PBEngine::new(10.0);
+        for _ in 0..2 {
+            engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        // (2 - 1e-9) x0 + 2 x1 >= 4
+        let constraint = LinearConstraintView::new(
+            [(0, 2.0 - 1e-9), (1, 2.0)]
+                .into_iter()
+                .map(|(index, coefficient)| (Literal::new(index, Boolean::TRUE), coefficient)),
+            4.0,
+        );
+        let round = |round: &mut Round| {
+            round.calculate(&constraint, |_| false, |_| 0.0, &engine);
+            return round
+                .get()
+                .iter_terms()
+                .map(|(_, coefficient)| coefficient)
+                .collect::<Vec<_>>();
+        };
+
+        // 許容誤差の範囲内であれば整数とみなして丸める
+        let mut loose = Round::new(1e-8);
+        assert!(round(&mut loose) == vec![2, 2]);
+
+        // 許容誤差の範囲外であれば切り捨てる
+        let mut tight = Round::new(1e-8);
+        tight.set_integrality_tolerance(1e-10);
+        assert!(tight.integrality_tolerance() == 1e-10);
+        assert!(round(&mut tight) == vec![1, 2]);
+        assert!(tight.get().lower() == 4);
+    }
+
+    #[test]
+    fn test_round2() {
+        // x0 のアクティビティを小さくし，丸め方向の切り替えを x0, x1, x2 の順に試す
+        let mut engine = PBEngine::new(10.0);
+        for activity in [0.0, 1.0, 2.0] {
+            engine.add_variable_with_initial_value(Boolean::FALSE, activity);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        let mut round = Round2::<u64>::new();
+        // divisor で割って丸めた制約条件の項 (変数の順) と右辺値を返す
+        let mut calculate =
+            |terms: &[(Literal, u64)], lower: u64, divisor: u64, causals: &[Literal]| {
+                let rounded_constraint = round.calculate(
+                    LinearConstraint::new(terms.iter().copied(), lower),
+                    divisor,
+                    |literal| causals.contains(&literal),
+                    |_| 0.0,
+                    &engine,
+                );
+                let mut terms = rounded_constraint.iter_terms().collect::<Vec<_>>();
+                terms.sort_by_key(|(literal, _)| literal.index());
+                return (terms, rounded_constraint.lower());
+            };
+
+        // 係数が全て divisor の倍数であれば割るだけ
+        assert!(
+            calculate(&[(x(0), 4), (x(1), 6)], 5, 2, &[x(0), x(1)])
+                == (vec![(x(0), 2), (x(1), 3)], 3)
+        );
+
+        // 3 x0 + 5 x1 >= 4 は x0 を切り下げても丸めた右辺値が変わらない:
+        // 2 x0 + 5 x1 >= 3 を 2 で割って x0 + 3 x1 >= 2
+        assert!(
+            calculate(&[(x(0), 3), (x(1), 5)], 4, 2, &[x(0), x(1)])
+                == (vec![(x(0), 1), (x(1), 3)], 2)
+        );
+
+        // 3 x0 + 3 x1 + 2 x2 >= 5 の x2 を切り下げると x0 + x1 >= 1 だが，
+        // 切り上げると丸めた右辺値が 1 増え， x0 + x1 + x2 >= 2 となる
+        assert!(
+            calculate(&[(x(0), 3), (x(1), 3), (x(2), 2)], 5, 3, &[x(0), x(1)])
+                == (vec![(x(0), 1), (x(1), 1), (x(2), 1)], 2)
+        );
+
+        // 2 x0 + 3 x1 >= 3 の x1 も同様に切り上げて x0 + 2 x1 >= 2
+        assert!(
+            calculate(&[(x(0), 2), (x(1), 3)], 3, 2, &[x(0)]) == (vec![(x(0), 1), (x(1), 2)], 2)
+        );
+
+        // divisor が 0 であれば丸めない
+        assert!(
+            calculate(&[(x(0), 2), (x(1), 3)], 4, 0, &[x(0)]) == (vec![(x(0), 2), (x(1), 3)], 4)
+        );
+    }
+}
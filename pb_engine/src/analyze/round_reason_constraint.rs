@@ -1,7 +1,7 @@
 use ordered_float::OrderedFloat;
 
 use crate::{
-    LinearConstraintTrait, Literal, PBEngine,
+    Coefficient, LinearConstraintTrait, Literal, PBEngine,
     analyze::utility::{lhs_sup_of_linear_constraint_at, normalize_linear_constraint},
     constraints::RandomAccessibleLinearConstraint,
 };
@@ -17,7 +17,7 @@ pub struct RoundReasonConstraint {
     integrality_tolerance: f64,
     identify_causals: IdentifyPropagationCausals,
     // weaken: Weaken,
-    round: Round2<u64>,
+    round: Round2<Coefficient>,
     // linear_constraint: LinearConstraint<u64>,
 }
 
@@ -31,13 +31,21 @@ impl RoundReasonConstraint {
         }
     }
 
+    pub fn integrality_tolerance(&self) -> f64 {
+        return self.integrality_tolerance;
+    }
+
+    pub fn set_integrality_tolerance(&mut self, integrality_tolerance: f64) {
+        self.integrality_tolerance = integrality_tolerance;
+    }
+
     pub fn round(
         &mut self,
-        reason_constraint: &impl LinearConstraintTrait<Value = u64>,
+        reason_constraint: &impl LinearConstraintTrait<Value = Coefficient>,
         conflict_constraint: &RandomAccessibleLinearConstraint<u128>,
         propagated_assignment: Literal,
         pb_engine: &PBEngine,
-    ) -> impl LinearConstraintTrait<Value = u64> + '_ {
+    ) -> impl LinearConstraintTrait<Value = Coefficient> + '_ {
         assert!(
             reason_constraint
                 .iter_terms()
@@ -3,12 +3,12 @@ use num::{Integer, Num, PrimInt, Unsigned, integer::gcd};
 use std::ops::AddAssign;
 use std::{cmp::min, fmt::Debug};
 
-use crate::{LinearConstraintTrait, LinearConstraintView, Literal, PBEngine};
+use crate::{Coefficient, LinearConstraintTrait, LinearConstraintView, Literal, PBEngine};
 
 pub fn drop_fixed_variable(
-    constraint: &impl LinearConstraintTrait<Value = u64>,
+    constraint: &impl LinearConstraintTrait<Value = Coefficient>,
     engine: &PBEngine,
-) -> impl LinearConstraintTrait<Value = u64> {
+) -> impl LinearConstraintTrait<Value = Coefficient> {
     let mut lower = constraint.lower();
     for (literal, coefficient) in constraint.iter_terms() {
         if engine.is_true(literal) && engine.get_decision_level(literal.index()) == 0 {
@@ -39,7 +39,7 @@ where
 }
 
 pub fn normalize_linear_constraint<'a>(
-    constraint: &'a impl LinearConstraintTrait<Value = u64>,
+    constraint: &'a impl LinearConstraintTrait<Value = Coefficient>,
     target_literal: Literal,
 ) -> impl LinearConstraintTrait<Value = f64> + 'a {
     let target_coefficient = constraint
@@ -95,27 +95,34 @@ where
             ValueT::one(),
         ));
     } else {
-        let gcd = calculate_gcd(
+        // lower = 0 の場合は全ての係数が 0 に丸められ，最大公約数が定まらないので割らない
+        let gcd = calculate_gcd_checked(
             constraint
                 .iter_terms()
                 .map(|(_, coefficient)| min(coefficient, lower)),
-        );
+        )
+        .unwrap_or(ValueT::one());
+        // 割った後も飽和している: lower 以上の係数があれば gcd は lower を割り切り，
+        // なければ各係数は lower 未満であるため，割った係数は lower.div_ceil(gcd) を超えない
+        let divided_lower = lower.div_ceil(&gcd);
         return Either::Right(LinearConstraintView::new(
             constraint
                 .iter_terms()
                 .filter_map(move |(literal, coefficient)| {
                     if coefficient != ValueT::zero() {
                         debug_assert!(min(coefficient, lower) % gcd == ValueT::zero());
+                        debug_assert!(min(coefficient, lower) / gcd <= divided_lower);
                         Some((literal, min(coefficient, lower) / gcd))
                     } else {
                         None
                     }
                 }),
-            constraint.lower().div_ceil(&gcd),
+            divided_lower,
         ));
     }
 }
 
+/// 最大公約数 (values が空であるか全て 0 であれば 0 を返す)
 pub fn calculate_gcd<ValueT>(values: impl Iterator<Item = ValueT>) -> ValueT
 where
     ValueT: Integer,
@@ -130,6 +137,15 @@ where
     return x;
 }
 
+/// 除数として用いることのできる最大公約数 (values が空であるか全て 0 であれば None を返す)
+pub fn calculate_gcd_checked<ValueT>(values: impl Iterator<Item = ValueT>) -> Option<ValueT>
+where
+    ValueT: Integer,
+{
+    let gcd = calculate_gcd(values);
+    return if gcd.is_zero() { None } else { Some(gcd) };
+}
+
 // pub fn gcd<ValueT>(mut x: ValueT, mut y: ValueT) -> ValueT
 // where
 //     ValueT: Unsigned + Ord + Copy,
@@ -150,3 +166,60 @@ where
 //         x = z;
 //     }
 // }
+
+#[cfg(test)]
+mod test {
+    use super::{calculate_gcd, calculate_gcd_checked, strengthen_integer_linear_constraint};
+    use crate::{Boolean, LinearConstraint, LinearConstraintTrait, Literal};
+
+    #[test]
+    fn test_calculate_gcd() {
+        assert!(calculate_gcd([0u64; 0].into_iter()) == 0);
+        assert!(calculate_gcd_checked([0u64; 0].into_iter()).is_none());
+        assert!(calculate_gcd_checked([0u64, 0].into_iter()).is_none());
+        assert!(calculate_gcd_checked([6u64].into_iter()) == Some(6));
+        assert!(calculate_gcd_checked([4u64, 4, 4].into_iter()) == Some(4));
+        assert!(calculate_gcd_checked([0u64, 6, 4].into_iter()) == Some(2));
+    }
+
+    #[test]
+    fn test_strengthen_trivial_constraint() {
+        // lower = 0 の制約条件は最大公約数で割らない
+        let constraint = LinearConstraint::new(
+            [(0, 3), (1, 2)]
+                .into_iter()
+                .map(|(index, coefficient)| (Literal::new(index, Boolean::TRUE), coefficient)),
+            0u64,
+        );
+        let strengthened_constraint = strengthen_integer_linear_constraint(&constraint);
+        assert!(strengthened_constraint.lower() == 0);
+        assert!(
+            strengthened_constraint
+                .iter_terms()
+                .all(|(_, coefficient)| coefficient == 0)
+        );
+    }
+
+    #[test]
+    fn test_strengthen_saturates_after_division() {
+        // 6x0 + 2x1 + 2x2 >= 4 は飽和すると 4x0 + 2x1 + 2x2 >= 4 となり， 2 で割ると 2x0 + x1 + x2 >= 2
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        let constraint = LinearConstraint::new([(x(0), 6u64), (x(1), 2), (x(2), 2)].into_iter(), 4);
+        let strengthened_constraint = strengthen_integer_linear_constraint(&constraint);
+        assert!(strengthened_constraint.lower() == 2);
+        assert!(
+            strengthened_constraint.iter_terms().collect::<Vec<_>>()
+                == vec![(x(0), 2), (x(1), 1), (x(2), 1)]
+        );
+
+        // lower 以上の係数がなければ割った係数は割った右辺値未満となる
+        let constraint = LinearConstraint::new([(x(0), 3u64), (x(1), 3), (x(2), 3)].into_iter(), 7);
+        let strengthened_constraint = strengthen_integer_linear_constraint(&constraint);
+        assert!(strengthened_constraint.lower() == 3);
+        assert!(
+            strengthened_constraint
+                .iter_terms()
+                .all(|(_, coefficient)| coefficient == 1)
+        );
+    }
+}
@@ -153,4 +153,155 @@ where
 
         return weakened_constraint;
     }
+
+    /// target 以外の False が割り当てられていない項を全て取り除き，係数を飽和させる
+    ///
+    /// 取り除いた項のリテラルは True であるとみなして lower から係数を差し引く．
+    /// 得られる制約条件は False が割り当てられたリテラルのみからなるため，
+    /// backjump 後に伝播するリテラルを target に限定できる
+    pub fn weaken_to_literal(
+        &mut self,
+        constraint: &impl LinearConstraintTrait<Value = ValueT>,
+        target: Literal,
+        is_false: impl Fn(Literal) -> bool,
+    ) -> impl LinearConstraintTrait<Value = ValueT> + '_ {
+        let work = &mut self.work;
+
+        work.fixed_terms.clear();
+        work.weakening_terms.clear();
+
+        let mut lower = constraint.lower();
+        for (literal, coefficient) in constraint.iter_terms() {
+            if literal == target || is_false(literal) {
+                work.fixed_terms.push(CausalTerm {
+                    literal,
+                    coefficient,
+                    probability: 0.0,
+                });
+            } else {
+                lower = lower.saturating_sub(coefficient);
+            }
+        }
+
+        return LinearConstraintView::new(
+            work.fixed_terms
+                .iter()
+                .map(move |term| (term.literal, min(term.coefficient, lower))),
+            lower,
+        );
+    }
+
+    /// can_drop を満たす項を priority の小さい順に取り除き，項数を max_number_of_terms 以下に抑える
+    ///
+    /// 取り除いた項のリテラルは True であるとみなして lower から係数を差し引く．
+    /// 得られる制約条件は元の制約条件から導かれるが，元の制約条件より弱い
+    /// (導出される切除平面の強さと引き換えに制約条件を小さくする)．係数は飽和させないため，
+    /// 必要であれば呼び出し側で strengthen_integer_linear_constraint を適用する
+    pub fn drop_terms(
+        &mut self,
+        constraint: &impl LinearConstraintTrait<Value = ValueT>,
+        max_number_of_terms: usize,
+        can_drop: impl Fn(Literal) -> bool,
+        priority: impl Fn(Literal) -> f64,
+    ) -> impl LinearConstraintTrait<Value = ValueT> + '_ {
+        let work = &mut self.work;
+
+        work.fixed_terms.clear();
+        work.weakening_terms.clear();
+
+        for (literal, coefficient) in constraint.iter_terms() {
+            if can_drop(literal) {
+                // probability には取り除く優先度を格納する
+                work.weakening_terms.push(WeakeningTerm {
+                    literal,
+                    coefficient,
+                    coefficient_lower: ValueT::zero(),
+                    probability: priority(literal),
+                });
+            } else {
+                work.fixed_terms.push(CausalTerm {
+                    literal,
+                    coefficient,
+                    probability: 0.0,
+                });
+            }
+        }
+
+        // 優先度が小さい順にソート
+        work.weakening_terms
+            .sort_unstable_by(|l, r| l.probability.partial_cmp(&r.probability).unwrap());
+
+        let number_of_terms = work.fixed_terms.len() + work.weakening_terms.len();
+        let number_of_dropped_terms = min(
+            number_of_terms.saturating_sub(max_number_of_terms),
+            work.weakening_terms.len(),
+        );
+        let lower: ValueT = constraint.lower().saturating_sub(
+            work.weakening_terms[..number_of_dropped_terms]
+                .iter()
+                .map(|term| term.coefficient)
+                .sum(),
+        );
+
+        return LinearConstraintView::new(
+            work.fixed_terms
+                .iter()
+                .map(|term| (term.literal, term.coefficient))
+                .chain(
+                    work.weakening_terms[number_of_dropped_terms..]
+                        .iter()
+                        .map(|term| (term.literal, term.coefficient)),
+                ),
+            lower,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Weaken;
+    use crate::{Boolean, LinearConstraint, LinearConstraintTrait, Literal};
+
+    #[test]
+    fn test_drop_terms() {
+        // 3 x0 + 2 x1 + 2 x2 + x3 + x4 >= 5 から x0 と x1 以外の項を取り除く候補とする
+        let constraint = LinearConstraint::new(
+            [(0, 3), (1, 2), (2, 2), (3, 1), (4, 1)]
+                .into_iter()
+                .map(|(index, coefficient)| (Literal::new(index, Boolean::TRUE), coefficient)),
+            5u64,
+        );
+        let mut weaken = Weaken::new();
+        let weakened_constraint = weaken.drop_terms(
+            &constraint,
+            3,
+            |literal| literal.index() >= 2,
+            |literal| literal.index() as f64,
+        );
+
+        // 優先度の小さい x2, x3 が取り除かれる
+        assert!(weakened_constraint.len() == 3);
+        assert!(weakened_constraint.lower() == 2);
+        assert!(
+            weakened_constraint
+                .iter_terms()
+                .all(|(literal, _)| literal.index() != 2 && literal.index() != 3)
+        );
+
+        // 元の制約条件を満たす全ての割り当てが，取り除いた後の制約条件を満たす
+        for bits in 0..(1 << 5) {
+            if is_satisfied(&constraint, bits) {
+                assert!(is_satisfied(&weakened_constraint, bits));
+            }
+        }
+    }
+
+    fn is_satisfied(constraint: &impl LinearConstraintTrait<Value = u64>, bits: u32) -> bool {
+        let lhs: u64 = constraint
+            .iter_terms()
+            .filter(|(literal, _)| bits >> literal.index() & 1 == 1)
+            .map(|(_, coefficient)| coefficient)
+            .sum();
+        return lhs >= constraint.lower();
+    }
 }
@@ -1,6 +1,6 @@
 use utility::Set;
 
-use crate::{Literal, decision_stack::DecisionStack};
+use crate::{Literal, LinearConstraintTrait, decision_stack::DecisionStack};
 
 #[derive(Default)]
 pub struct CalculatePLBD {
@@ -31,4 +31,19 @@ impl CalculatePLBD {
         }
         return self.decision_level_set.len();
     }
+
+    /// constraint の項のうち，否定が True であるリテラルの決定レベルの種類数
+    pub fn calculate_for_constraint(
+        &mut self,
+        constraint: &(impl LinearConstraintTrait + ?Sized),
+        decision_stack: &DecisionStack<impl Copy>,
+    ) -> usize {
+        return self.calculate(
+            constraint
+                .iter_terms()
+                .map(|(literal, _)| !literal)
+                .filter(|&literal| decision_stack.is_true(literal)),
+            decision_stack,
+        );
+    }
 }
@@ -29,6 +29,14 @@ impl<ValueT> LiteralArray<ValueT> {
     //     }
     // }
 
+    /// literal の変数が登録されていなければ None を返す
+    #[inline(always)]
+    pub fn get(&self, literal: Literal) -> Option<&ValueT> {
+        self.array
+            .get(literal.index())
+            .map(|values| &values[literal.value()])
+    }
+
     pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut [ValueT; 2]> + '_ {
         self.array.iter_mut()
     }
@@ -38,6 +46,12 @@ impl<ValueT> Index<Literal> for LiteralArray<ValueT> {
     type Output = ValueT;
     #[inline(always)]
     fn index(&self, literal: Literal) -> &Self::Output {
+        debug_assert!(
+            self.get(literal).is_some(),
+            "variable {} is not registered (number of variables: {})",
+            literal.index(),
+            self.array.len()
+        );
         &self.array[literal.index()][literal.value()]
     }
 }
@@ -45,6 +59,40 @@ impl<ValueT> Index<Literal> for LiteralArray<ValueT> {
 impl<ValueT> IndexMut<Literal> for LiteralArray<ValueT> {
     #[inline(always)]
     fn index_mut(&mut self, literal: Literal) -> &mut Self::Output {
+        debug_assert!(
+            self.get(literal).is_some(),
+            "variable {} is not registered (number of variables: {})",
+            literal.index(),
+            self.array.len()
+        );
         &mut self.array[literal.index()][literal.value()]
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::LiteralArray;
+    use crate::{Boolean, Literal};
+
+    #[test]
+    fn test_get() {
+        let mut literal_array = LiteralArray::default();
+        literal_array.push([1, 2]);
+        literal_array.push([3, 4]);
+        assert!(literal_array.get(Literal::new(1, Boolean::FALSE)) == Some(&3));
+        assert!(literal_array.get(Literal::new(1, Boolean::TRUE)) == Some(&4));
+        // 登録されていない変数
+        assert!(literal_array.get(Literal::new(2, Boolean::TRUE)).is_none());
+    }
+
+    // 登録の検証は debug_assert で行うため，リリースビルドでは検証しない
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "variable 2 is not registered")]
+    fn test_index_out_of_range() {
+        let mut literal_array = LiteralArray::default();
+        literal_array.push([1, 2]);
+        literal_array.push([3, 4]);
+        let _ = literal_array[Literal::new(2, Boolean::TRUE)];
+    }
+}
@@ -4,6 +4,7 @@ mod monadic_clause;
 
 pub use count_constraint::{CountConstraint, CountConstraintTrait, CountConstraintView};
 pub use linear_constraint::{
-    LinearConstraint, LinearConstraintTrait, LinearConstraintView, RandomAccessibleLinearConstraint,
+    ConstraintError, LinearConstraint, LinearConstraintTrait, LinearConstraintView,
+    RandomAccessibleLinearConstraint,
 };
 pub use monadic_clause::MonadicClause;
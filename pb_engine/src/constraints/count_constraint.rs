@@ -1,11 +1,31 @@
-use crate::Literal;
+use crate::{Coefficient, Literal};
 
 pub trait CountConstraintTrait {
     fn iter_terms(&self) -> impl Iterator<Item = Literal> + Clone + '_;
-    fn lower(&self) -> u64;
+    fn lower(&self) -> Coefficient;
     fn len(&self) -> usize {
         self.iter_terms().count()
     }
+    /// 節 (いずれかのリテラルが True) であるか
+    fn is_clause(&self) -> bool {
+        self.lower() == 1
+    }
+    /// 常に充足される (リテラルとその否定の組が lower 個以上含まれる) か
+    ///
+    /// 節の場合は，あるリテラルとその否定をともに含むかと同じ
+    fn is_tautological(&self) -> bool {
+        let mut literals = Vec::from_iter(
+            self.iter_terms()
+                .map(|literal| (literal.index(), literal.value() as usize)),
+        );
+        literals.sort_unstable();
+        literals.dedup();
+        let number_of_complementary_pairs = literals
+            .windows(2)
+            .filter(|pair| pair[0].0 == pair[1].0)
+            .count();
+        number_of_complementary_pairs as Coefficient >= self.lower()
+    }
 }
 
 impl<CountConstraintT> CountConstraintTrait for &CountConstraintT
@@ -15,7 +35,7 @@ where
     fn iter_terms(&self) -> impl Iterator<Item = Literal> + Clone + '_ {
         (*self).iter_terms()
     }
-    fn lower(&self) -> u64 {
+    fn lower(&self) -> Coefficient {
         (*self).lower()
     }
     fn len(&self) -> usize {
@@ -26,7 +46,21 @@ where
 #[derive(Clone, Debug)]
 pub struct CountConstraint {
     literals: Vec<Literal>,
-    lower: u64,
+    lower: Coefficient,
+}
+
+impl CountConstraint {
+    pub fn new(literals: impl Iterator<Item = Literal>, lower: Coefficient) -> Self {
+        Self {
+            literals: literals.collect(),
+            lower,
+        }
+    }
+
+    /// 節 literals を lower = 1 の基数制約として作成する
+    pub fn from_clause(literals: impl Iterator<Item = Literal>) -> Self {
+        return Self::new(literals, 1);
+    }
 }
 
 impl CountConstraintTrait for CountConstraint {
@@ -34,7 +68,7 @@ impl CountConstraintTrait for CountConstraint {
         self.literals.iter().cloned()
     }
 
-    fn lower(&self) -> u64 {
+    fn lower(&self) -> Coefficient {
         self.lower
     }
 }
@@ -42,14 +76,14 @@ impl CountConstraintTrait for CountConstraint {
 #[derive(Clone, Debug)]
 pub struct CountConstraintView<IteratorT: Iterator<Item = Literal> + Clone> {
     literals: IteratorT,
-    lower: u64,
+    lower: Coefficient,
 }
 
 impl<IteratorT> CountConstraintView<IteratorT>
 where
     IteratorT: Iterator<Item = Literal> + Clone,
 {
-    pub fn new(literals: IteratorT, lower: u64) -> Self {
+    pub fn new(literals: IteratorT, lower: Coefficient) -> Self {
         Self { literals, lower }
     }
 }
@@ -61,7 +95,42 @@ impl<IteratorT: Iterator<Item = Literal> + Clone> CountConstraintTrait
         self.literals.clone()
     }
 
-    fn lower(&self) -> u64 {
+    fn lower(&self) -> Coefficient {
         self.lower
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{CountConstraint, CountConstraintTrait, CountConstraintView};
+    use crate::{Boolean, Literal};
+
+    #[test]
+    fn test_from_clause() {
+        let x = |index| Literal::new(index, Boolean::TRUE);
+
+        let clause = CountConstraint::from_clause([x(0), !x(1), x(2)].into_iter());
+        assert!(clause.is_clause());
+        assert!(clause.lower() == 1);
+        assert!(Vec::from_iter(clause.iter_terms()) == vec![x(0), !x(1), x(2)]);
+
+        let count_constraint = CountConstraintView::new([x(0), x(1), x(2)].into_iter(), 2);
+        assert!(!count_constraint.is_clause());
+        assert!(count_constraint.len() == 3);
+    }
+
+    #[test]
+    fn test_is_tautological() {
+        let x = |index| Literal::new(index, Boolean::TRUE);
+
+        assert!(CountConstraint::from_clause([x(0), x(1), !x(0)].into_iter()).is_tautological());
+        assert!(!CountConstraint::from_clause([x(0), x(1), x(0)].into_iter()).is_tautological());
+        // x0 + ¬x0 は常に 1 なので x1 + x2 >= 1 と同値
+        assert!(
+            !CountConstraintView::new([x(0), !x(0), x(1), x(2)].into_iter(), 2).is_tautological()
+        );
+        assert!(
+            CountConstraintView::new([x(0), !x(0), x(1), !x(1)].into_iter(), 2).is_tautological()
+        );
+    }
+}
@@ -4,10 +4,10 @@ use std::{
 };
 
 use either::Either;
-use num::{Integer, Num, NumCast, PrimInt, Unsigned};
+use num::{CheckedAdd, CheckedMul, Integer, Num, NumCast, One, PrimInt, Unsigned, Zero};
 use utility::Map;
 
-use crate::{Boolean, Literal};
+use crate::{Boolean, Literal, calculate_plbd::CalculatePLBD, decision_stack::DecisionStack};
 
 // 値の型をジェネリックパラメータとして，整数と浮動小数点数とで Constraint の実装を統合できないか
 // ※ Theory の実装は tolerance と，数値誤差の蓄積を考慮する必要があるので実装を分けざるを得ない
@@ -33,6 +33,84 @@ pub trait LinearConstraintTrait {
         );
     }
 
+    /// mul と同様に係数と右辺を multipler 倍する．いずれかが Value の範囲を超える場合は None を返す
+    fn try_mul(
+        &self,
+        multipler: Self::Value,
+    ) -> Option<impl LinearConstraintTrait<Value = Self::Value>>
+    where
+        Self::Value: AddAssign + Mul + CheckedMul,
+    {
+        if self.lower().checked_mul(&multipler).is_none()
+            || self
+                .iter_terms()
+                .any(|(_, coefficient)| coefficient.checked_mul(&multipler).is_none())
+        {
+            return None;
+        }
+        return Some(self.mul(multipler));
+    }
+
+    /// 係数と右辺を divisor で割って切り上げる (Chvátal-Gomory 丸め)
+    ///
+    /// 左辺が下界を持つ (>=) 制約条件で変数が 0-1 であるため，係数の切り捨てではなく切り上げが妥当な丸めとなる
+    fn divide_and_round(
+        &self,
+        divisor: Self::Value,
+    ) -> impl LinearConstraintTrait<Value = Self::Value> + '_
+    where
+        Self::Value: Integer,
+    {
+        debug_assert!(!divisor.is_zero());
+        return LinearConstraintView::new(
+            self.iter_terms()
+                .map(move |(literal, coefficient)| (literal, coefficient.div_ceil(&divisor))),
+            self.lower().div_ceil(&divisor),
+        );
+    }
+
+    /// 否定 sum(c_i * ~l_i) >= sum(c_i) - lower + 1 を返す
+    ///
+    /// sum(c_i * l_i) >= lower の否定は sum(c_i * l_i) <= lower - 1 であり，
+    /// ~l_i = 1 - l_i を代入すると右辺は sum(c_i) - (lower - 1) となる (+1 を忘れると否定にならない)．
+    /// lower が sum(c_i) + 1 を超える (充足不能な) 場合，否定は自明に充足されるため右辺は 0 とする．
+    /// sum(c_i) + 1 が Value の範囲を超える場合は panic する
+    fn negate(&self) -> impl LinearConstraintTrait<Value = Self::Value> + '_
+    where
+        Self::Value: PrimInt + Unsigned,
+    {
+        // sum(c_i) + 1
+        let sum_of_coefficients = self
+            .iter_terms()
+            .try_fold(Self::Value::one(), |sum, (_, coefficient)| {
+                sum.checked_add(&coefficient)
+            })
+            .expect("the sum of coefficients overflowed");
+        return LinearConstraintView::new(
+            self.iter_terms()
+                .map(|(literal, coefficient)| (!literal, coefficient)),
+            if sum_of_coefficients > self.lower() {
+                sum_of_coefficients - self.lower()
+            } else {
+                Self::Value::zero()
+            },
+        );
+    }
+
+    /// 自身を target に加える (キャンセルされる項は target.add_assign と同様に処理される)
+    ///
+    /// 係数または右辺が Value の範囲を超える場合は ConstraintError::Overflow を返す
+    fn accumulate_into(
+        &self,
+        target: &mut RandomAccessibleLinearConstraint<Self::Value>,
+    ) -> Result<(), ConstraintError>
+    where
+        Self: Sized,
+        Self::Value: AddAssign + SubAssign + PartialOrd + CheckedAdd,
+    {
+        return target.add_assign(self);
+    }
+
     fn convert<ValueT>(&self) -> impl LinearConstraintTrait<Value = ValueT>
     where
         Self::Value: PrimInt,
@@ -44,6 +122,13 @@ pub trait LinearConstraintTrait {
             ValueT::from(self.lower()).unwrap(),
         );
     }
+
+    /// 項のうち False であるリテラルの決定レベル (0 を除く) の種類数 (PLBD)
+    ///
+    /// 繰り返し計算する場合はバッファを使い回す CalculatePLBD::calculate_for_constraint を使う
+    fn glue<ExplainKeyT: Copy>(&self, decision_stack: &DecisionStack<ExplainKeyT>) -> usize {
+        return CalculatePLBD::default().calculate_for_constraint(self, decision_stack);
+    }
 }
 
 impl<LinearConstraintT> LinearConstraintTrait for &LinearConstraintT
@@ -83,6 +168,39 @@ where
     }
 }
 
+/// {"terms": [[literal, coefficient], ...], "lower": lower} として書き出す (リテラルは Literal::to_dimacs の整数)
+#[cfg(feature = "serde")]
+impl<ValueT> serde::Serialize for LinearConstraint<ValueT>
+where
+    ValueT: Copy + Debug + serde::Serialize,
+{
+    fn serialize<SerializerT>(&self, serializer: SerializerT) -> Result<SerializerT::Ok, SerializerT::Error>
+    where
+        SerializerT: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let terms: Vec<(i64, ValueT)> = self
+            .terms
+            .iter()
+            .map(|&(literal, coefficient)| (literal.to_dimacs(), coefficient))
+            .collect();
+        let mut state = serializer.serialize_struct("LinearConstraint", 2)?;
+        state.serialize_field("terms", &terms)?;
+        state.serialize_field("lower", &self.lower)?;
+        return state.end();
+    }
+}
+
+/// 制約条件の構築時に検出された不正な入力
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConstraintError {
+    /// 項が残らないにもかかわらず lower が正であり，充足不能
+    Infeasible,
+    /// 係数または右辺の和が値の型の範囲を超えた
+    Overflow,
+}
+
 #[derive(Default, Clone, Debug)]
 pub struct LinearConstraint<ValueT>
 where
@@ -110,6 +228,140 @@ where
     }
 }
 
+impl<ValueT> LinearConstraint<ValueT>
+where
+    ValueT: Num + AddAssign + SubAssign + PartialOrd + Copy + Debug,
+{
+    /// new と異なり，同じ変数の項をまとめ (逆の極性の項は相殺し)，係数が 0 の項を除いて構築する
+    ///
+    /// 負の係数の項は否定リテラルの項に変換する．lower が 0 以下になった場合は 0 とし，
+    /// 項が残らず lower が正であれば Err(ConstraintError::Infeasible) を返す
+    pub fn try_new(
+        terms: impl Iterator<Item = (Literal, ValueT)>,
+        lower: ValueT,
+    ) -> Result<Self, ConstraintError> {
+        let mut folded_terms: Map<(Boolean, ValueT)> = Map::default();
+        let mut lower = lower;
+        // ValueT が符号なしの場合に備えて，lower から差し引く値は別に集計する
+        let mut decrement = ValueT::zero();
+        for (literal, coefficient) in terms {
+            // c * l = -c * ¬l + c
+            let (literal, coefficient) = if coefficient < ValueT::zero() {
+                lower += ValueT::zero() - coefficient;
+                (!literal, ValueT::zero() - coefficient)
+            } else {
+                (literal, coefficient)
+            };
+            let term = folded_terms
+                .get_or_insert_with(literal.index(), || (literal.value(), ValueT::zero()));
+            if term.0 == literal.value() {
+                term.1 += coefficient;
+            } else if term.1 >= coefficient {
+                // a * l + c * ¬l = (a - c) * l + c
+                decrement += coefficient;
+                term.1 -= coefficient;
+            } else {
+                decrement += term.1;
+                term.0 = !term.0;
+                term.1 = coefficient - term.1;
+            }
+        }
+
+        let lower = if lower > decrement {
+            lower - decrement
+        } else {
+            ValueT::zero()
+        };
+        let terms = Vec::from_iter(
+            folded_terms
+                .iter()
+                .filter(|(_, term)| term.1 != ValueT::zero())
+                .map(|(&index, term)| (Literal::new(index, term.0), term.1)),
+        );
+        if terms.is_empty() && lower > ValueT::zero() {
+            return Err(ConstraintError::Infeasible);
+        }
+        return Ok(Self { terms, lower });
+    }
+}
+
+impl<ValueT> LinearConstraint<ValueT>
+where
+    ValueT: PrimInt + Unsigned + Integer + AddAssign + SubAssign + Debug,
+{
+    /// 項の順序と係数の公約数の違いを除いて other と同値であるか
+    ///
+    /// 双方の項をまとめ，係数の最大公約数での除算 (右辺は切り上げ) と飽和を繰り返して得られる
+    /// 正規形を比較する．自明に充足される制約条件どうしと充足不能な制約条件どうしは同値とする．
+    /// 正規形が異なっても論理的には同値である場合があり，その場合も false を返す
+    pub fn is_equivalent_to(&self, other: &impl LinearConstraintTrait<Value = ValueT>) -> bool {
+        return normalize_for_comparison(self) == normalize_for_comparison(other);
+    }
+}
+
+/// is_equivalent_to で比較する正規形
+#[derive(PartialEq, Eq, Debug)]
+struct NormalForm<ValueT> {
+    /// (変数のインデックス, 値, 係数) を変数のインデックスと値の順に並べたもの
+    terms: Vec<(usize, Boolean, ValueT)>,
+    lower: ValueT,
+}
+
+/// constraint の正規形 (充足不能な場合は None)
+fn normalize_for_comparison<ValueT>(
+    constraint: &impl LinearConstraintTrait<Value = ValueT>,
+) -> Option<NormalForm<ValueT>>
+where
+    ValueT: PrimInt + Unsigned + Integer + AddAssign + SubAssign + Debug,
+{
+    let Ok(constraint) = LinearConstraint::try_new(constraint.iter_terms(), constraint.lower())
+    else {
+        return None;
+    };
+    let mut lower = constraint.lower;
+    if lower.is_zero() {
+        return Some(NormalForm {
+            terms: Vec::default(),
+            lower,
+        });
+    }
+    let mut terms = Vec::from_iter(
+        constraint
+            .terms
+            .iter()
+            .map(|&(literal, coefficient)| (literal.index(), literal.value(), coefficient)),
+    );
+    loop {
+        let gcd = terms
+            .iter()
+            .fold(ValueT::zero(), |gcd, term| gcd.gcd(&term.2));
+        if gcd > ValueT::one() {
+            for term in terms.iter_mut() {
+                term.2 = term.2 / gcd;
+            }
+            lower = Integer::div_ceil(&lower, &gcd);
+        }
+        let mut is_saturated = true;
+        for term in terms.iter_mut() {
+            if term.2 > lower {
+                term.2 = lower;
+                is_saturated = false;
+            }
+        }
+        if gcd <= ValueT::one() && is_saturated {
+            break;
+        }
+    }
+    let sup = terms
+        .iter()
+        .fold(ValueT::zero(), |sup, term| sup.saturating_add(term.2));
+    if sup < lower {
+        return None;
+    }
+    terms.sort_unstable_by_key(|&(index, value, _)| (index, value as usize));
+    return Some(NormalForm { terms, lower });
+}
+
 impl<ValueT> LinearConstraintTrait for LinearConstraint<ValueT>
 where
     ValueT: Num + Copy + Debug,
@@ -184,7 +436,7 @@ where
 
 impl<ValueT> RandomAccessibleLinearConstraint<ValueT>
 where
-    ValueT: Num + AddAssign + SubAssign + PartialOrd + Copy + Debug,
+    ValueT: Num + AddAssign + SubAssign + PartialOrd + CheckedAdd + Copy + Debug,
 {
     pub fn get(&self, literal: Literal) -> Option<ValueT> {
         return self
@@ -202,6 +454,17 @@ where
             .map(|term| &mut term.1);
     }
 
+    /// 項を全て取り除き，右辺を 0 とする (確保した領域は保たれる)
+    pub fn clear(&mut self) {
+        self.terms.clear();
+        self.lower = ValueT::zero();
+    }
+
+    /// 少なくとも additional 個の項を再確保せずに追加できるようにする
+    pub fn reserve(&mut self, additional: usize) {
+        self.terms.reserve(additional);
+    }
+
     pub fn replace_by_linear_constraint(
         &mut self,
         linear_constraint: impl LinearConstraintTrait<Value = ValueT>,
@@ -227,30 +490,40 @@ where
         &mut self.lower
     }
 
-    pub fn add_assign(&mut self, reason_constraint: impl LinearConstraintTrait<Value = ValueT>) {
-        self.lower += reason_constraint.lower();
+    /// reason_constraint を加える
+    ///
+    /// 係数または右辺の和が ValueT の範囲を超える場合は，折り返さずに ConstraintError::Overflow を返す．
+    /// このとき自身の内容は途中まで加算された状態となるため，呼び出し側で作り直す必要がある
+    pub fn add_assign(
+        &mut self,
+        reason_constraint: impl LinearConstraintTrait<Value = ValueT>,
+    ) -> Result<(), ConstraintError> {
+        self.lower = self
+            .lower
+            .checked_add(&reason_constraint.lower())
+            .ok_or(ConstraintError::Overflow)?;
         for (literal, coefficient) in reason_constraint.iter_terms() {
-            if let Some(term) = self.terms.get_mut(literal.index()) {
-                if term.0 == literal.value() {
-                    term.1 += coefficient;
-                } else {
-                    if term.1 > coefficient {
-                        self.lower -= coefficient;
-                        term.1 -= coefficient;
-                    } else if term.1 < coefficient {
-                        self.lower -= term.1;
-                        term.0 = !term.0;
-                        term.1 = coefficient - term.1;
-                    } else {
-                        self.lower -= term.1;
-                        self.terms.remove(literal.index());
-                    }
-                }
+            let term = self
+                .terms
+                .get_or_insert_with(literal.index(), || (literal.value(), ValueT::zero()));
+            if term.0 == literal.value() {
+                term.1 = term
+                    .1
+                    .checked_add(&coefficient)
+                    .ok_or(ConstraintError::Overflow)?;
+            } else if term.1 > coefficient {
+                self.lower -= coefficient;
+                term.1 -= coefficient;
+            } else if term.1 < coefficient {
+                self.lower -= term.1;
+                term.0 = !term.0;
+                term.1 = coefficient - term.1;
             } else {
-                self.terms
-                    .insert(literal.index(), (literal.value(), coefficient));
+                self.lower -= term.1;
+                self.terms.remove(literal.index());
             }
         }
+        return Ok(());
     }
 
     // fn strengthen(&mut self) {
@@ -274,3 +547,264 @@ where
     //     }
     // }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        ConstraintError, LinearConstraint, LinearConstraintTrait, RandomAccessibleLinearConstraint,
+    };
+    use crate::{Boolean, Literal, decision_stack::DecisionStack, engine::Reason};
+
+    #[test]
+    fn test_is_equivalent_to() {
+        let x = |index| Literal::new(index, Boolean::TRUE);
+
+        // 6 x0 + 4 x1 + 2 x2 >= 5 と 2 x2 + 3 x0 + 2 x1 >= 3 は係数の公約数と項の順序のみが異なる
+        let linear_constraint =
+            LinearConstraint::<u64>::new([(x(0), 6), (x(1), 4), (x(2), 2)].into_iter(), 5);
+        let scaled_constraint =
+            LinearConstraint::<u64>::new([(x(2), 1), (x(0), 3), (x(1), 2)].into_iter(), 3);
+        assert!(linear_constraint.is_equivalent_to(&scaled_constraint));
+        assert!(scaled_constraint.is_equivalent_to(&linear_constraint));
+
+        // 飽和させると一致する
+        let saturated_constraint =
+            LinearConstraint::<u64>::new([(x(0), 3), (x(1), 2), (x(2), 1)].into_iter(), 3);
+        let unsaturated_constraint =
+            LinearConstraint::<u64>::new([(x(0), 7), (x(1), 2), (x(2), 1)].into_iter(), 3);
+        assert!(saturated_constraint.is_equivalent_to(&unsaturated_constraint));
+
+        // 同じ変数の項はまとめられる
+        let folded_constraint = LinearConstraint::<u64>::new(
+            [(x(0), 2), (x(1), 2), (x(0), 1), (!x(2), 1), (x(2), 2)].into_iter(),
+            4,
+        );
+        assert!(folded_constraint.is_equivalent_to(&saturated_constraint));
+
+        // 右辺や極性が異なれば同値でない
+        let weaker_constraint =
+            LinearConstraint::<u64>::new([(x(0), 3), (x(1), 2), (x(2), 1)].into_iter(), 2);
+        assert!(!saturated_constraint.is_equivalent_to(&weaker_constraint));
+        let negated_constraint =
+            LinearConstraint::<u64>::new([(!x(0), 3), (x(1), 2), (x(2), 1)].into_iter(), 3);
+        assert!(!saturated_constraint.is_equivalent_to(&negated_constraint));
+
+        // 自明に充足される制約条件どうし，充足不能な制約条件どうしは同値
+        let trivial_constraint = LinearConstraint::<u64>::new([(x(0), 1)].into_iter(), 0);
+        let empty_constraint = LinearConstraint::<u64>::new([].into_iter(), 0);
+        assert!(trivial_constraint.is_equivalent_to(&empty_constraint));
+        let infeasible_constraint =
+            LinearConstraint::<u64>::new([(x(0), 1), (x(1), 1)].into_iter(), 3);
+        let empty_infeasible_constraint = LinearConstraint::<u64>::new([].into_iter(), 1);
+        assert!(infeasible_constraint.is_equivalent_to(&empty_infeasible_constraint));
+        assert!(!infeasible_constraint.is_equivalent_to(&trivial_constraint));
+    }
+
+    #[test]
+    fn test_glue() {
+        let mut decision_stack = DecisionStack::<usize>::default();
+        for _ in 0..6 {
+            decision_stack.add_variable(Boolean::FALSE);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        // x0 はレベル 0, x1, x2 はレベル 1, x3 はレベル 2, x4 はレベル 3 で False となり， x5 は未割り当て
+        decision_stack.assign(!x(0), Reason::Propagation { explain_key: 0 });
+        decision_stack.assign(!x(1), Reason::Decision);
+        decision_stack.assign(!x(2), Reason::Propagation { explain_key: 1 });
+        decision_stack.assign(!x(3), Reason::Decision);
+        decision_stack.assign(!x(4), Reason::Decision);
+
+        let glue = |terms: &[Literal]| {
+            LinearConstraint::<u64>::new(terms.iter().map(|&literal| (literal, 1)), 1)
+                .glue(&decision_stack)
+        };
+        // レベル 0 のリテラルと未割り当てのリテラルは数えない
+        assert!(glue(&[x(0), x(1), x(2), x(5)]) == 1);
+        assert!(glue(&[x(0), x(2), x(3)]) == 2);
+        assert!(glue(&[x(1), x(2), x(3), x(4), x(5)]) == 3);
+        // True であるリテラルは数えない
+        assert!(glue(&[!x(1), x(3), !x(4)]) == 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize() {
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        let constraint = LinearConstraint::<u64>::new([(x(0), 3), (!x(2), 2)].into_iter(), 4);
+        assert!(
+            serde_json::to_value(&constraint).unwrap()
+                == serde_json::json!({"terms": [[1, 3], [-3, 2]], "lower": 4})
+        );
+    }
+
+    #[test]
+    fn test_negate() {
+        let x = |index| Literal::new(index, Boolean::TRUE);
+        let lhs = |constraint: &LinearConstraint<u64>, bits: u32| -> u64 {
+            constraint
+                .iter_terms()
+                .filter(|(literal, _)| {
+                    Boolean::from(bits >> literal.index() & 1 == 1) == literal.value()
+                })
+                .map(|(_, coefficient)| coefficient)
+                .sum()
+        };
+
+        // 3 x0 + 2 x1 + ~x2 >= 4 の否定は 3 ~x0 + 2 ~x1 + x2 >= 3
+        let linear_constraint =
+            LinearConstraint::<u64>::new([(x(0), 3), (x(1), 2), (!x(2), 1)].into_iter(), 4);
+        let negated_constraint = linear_constraint.negate();
+        assert_eq!(
+            Vec::from_iter(negated_constraint.iter_terms()),
+            vec![(!x(0), 3), (!x(1), 2), (x(2), 1)]
+        );
+        assert_eq!(negated_constraint.lower(), 3);
+
+        // 元の制約条件と否定のちょうど一方が充足される
+        let negated_constraint =
+            LinearConstraint::new(negated_constraint.iter_terms(), negated_constraint.lower());
+        for bits in 0..(1u32 << 3) {
+            let is_satisfied = lhs(&linear_constraint, bits) >= linear_constraint.lower();
+            let is_negation_satisfied = lhs(&negated_constraint, bits) >= negated_constraint.lower();
+            assert!(is_satisfied != is_negation_satisfied);
+        }
+
+        // 充足不能な制約条件の否定は自明に充足される
+        let infeasible_constraint = LinearConstraint::<u64>::new([(x(0), 1)].into_iter(), 3);
+        assert_eq!(infeasible_constraint.negate().lower(), 0);
+        // 自明に充足される制約条件の否定は充足不能
+        let trivial_constraint = LinearConstraint::<u64>::new([(x(0), 1)].into_iter(), 0);
+        assert_eq!(trivial_constraint.negate().lower(), 2);
+    }
+
+    #[test]
+    fn test_divide_and_round() {
+        let x = |index| Literal::new(index, Boolean::TRUE);
+
+        // 3 x0 + 2 x1 + x2 >= 4 を 2 で割ると 2 x0 + x1 + x2 >= 2
+        let linear_constraint =
+            LinearConstraint::<u64>::new([(x(0), 3), (x(1), 2), (x(2), 1)].into_iter(), 4);
+        let divided_constraint = linear_constraint.divide_and_round(2);
+        assert_eq!(
+            Vec::from_iter(divided_constraint.iter_terms()),
+            vec![(x(0), 2), (x(1), 1), (x(2), 1)]
+        );
+        assert_eq!(divided_constraint.lower(), 2);
+
+        // 元の制約条件を満たす全ての割り当てが，割った後の制約条件を満たす
+        let divided_constraint =
+            LinearConstraint::new(divided_constraint.iter_terms(), divided_constraint.lower());
+        for bits in 0..(1u32 << 3) {
+            let lhs = |constraint: &LinearConstraint<u64>| -> u64 {
+                constraint
+                    .iter_terms()
+                    .filter(|(literal, _)| bits >> literal.index() & 1 == 1)
+                    .map(|(_, coefficient)| coefficient)
+                    .sum()
+            };
+            if lhs(&linear_constraint) >= linear_constraint.lower() {
+                assert!(lhs(&divided_constraint) >= divided_constraint.lower());
+            }
+        }
+    }
+
+    #[test]
+    fn test_try_new() {
+        let x = |index| Literal::new(index, Boolean::TRUE);
+
+        // 3 x0 + x1 + 2 ¬x0 + 0 x2 >= 3  =>  x0 + x1 >= 1
+        let linear_constraint = LinearConstraint::<u64>::try_new(
+            [(x(0), 3), (x(1), 1), (!x(0), 2), (x(2), 0)].into_iter(),
+            3,
+        )
+        .unwrap();
+        assert_eq!(
+            Vec::from_iter(linear_constraint.iter_terms()),
+            vec![(x(0), 1), (x(1), 1)]
+        );
+        assert_eq!(linear_constraint.lower(), 1);
+
+        // x0 + 2 ¬x0 + x1 >= 2  =>  ¬x0 + x1 >= 1
+        let linear_constraint =
+            LinearConstraint::<u64>::try_new([(x(0), 1), (!x(0), 2), (x(1), 1)].into_iter(), 2)
+                .unwrap();
+        assert_eq!(
+            Vec::from_iter(linear_constraint.iter_terms()),
+            vec![(!x(0), 1), (x(1), 1)]
+        );
+        assert_eq!(linear_constraint.lower(), 1);
+
+        // -2 x0 >= -1  =>  2 ¬x0 >= 1
+        let linear_constraint =
+            LinearConstraint::<i64>::try_new([(x(0), -2)].into_iter(), -1).unwrap();
+        assert_eq!(
+            Vec::from_iter(linear_constraint.iter_terms()),
+            vec![(!x(0), 2)]
+        );
+        assert_eq!(linear_constraint.lower(), 1);
+
+        // x0 + ¬x0 >= 1 は自明に充足され，x0 + ¬x0 >= 2 は充足不能
+        let linear_constraint =
+            LinearConstraint::<u64>::try_new([(x(0), 1), (!x(0), 1)].into_iter(), 1).unwrap();
+        assert_eq!(linear_constraint.len(), 0);
+        assert_eq!(linear_constraint.lower(), 0);
+        assert_eq!(
+            LinearConstraint::<u64>::try_new([(x(0), 1), (!x(0), 1)].into_iter(), 2).unwrap_err(),
+            ConstraintError::Infeasible
+        );
+    }
+
+    #[test]
+    fn test_try_mul() {
+        let x = |index| Literal::new(index, Boolean::TRUE);
+        let half = u64::MAX / 2;
+        let constraint = LinearConstraint::<u64>::new([(x(0), half - 1), (x(1), 1)].into_iter(), 2);
+
+        let doubled = constraint.try_mul(2).unwrap();
+        assert!(doubled.iter_terms().collect::<Vec<_>>() == vec![(x(0), 2 * half - 2), (x(1), 2)]);
+        assert!(doubled.lower() == 4);
+
+        // 係数が u64 の範囲を超える
+        assert!(constraint.try_mul(3).is_none());
+        // 右辺が u64 の範囲を超える
+        let constraint = LinearConstraint::<u64>::new([(x(0), 1)].into_iter(), half);
+        assert!(constraint.try_mul(3).is_none());
+    }
+
+    #[test]
+    fn test_add_assign_overflow() {
+        let x = |index| Literal::new(index, Boolean::TRUE);
+        let new_target = || {
+            let mut target = RandomAccessibleLinearConstraint::<u8>::default();
+            target.replace_by_linear_constraint(LinearConstraint::new(
+                [(x(0), 200), (x(1), 1)].into_iter(),
+                200,
+            ));
+            return target;
+        };
+
+        // 逆の極性の項は打ち消し合うので溢れない
+        let mut target = new_target();
+        assert!(
+            LinearConstraint::<u8>::new([(!x(0), 100)].into_iter(), 50)
+                .accumulate_into(&mut target)
+                .is_ok()
+        );
+        assert_eq!(target.get(x(0)), Some(100));
+        assert_eq!(target.lower(), 150);
+
+        // 同じ極性の係数の和が u8 の範囲を超える
+        let mut target = new_target();
+        assert_eq!(
+            target.add_assign(LinearConstraint::<u8>::new([(x(0), 100)].into_iter(), 1)),
+            Err(ConstraintError::Overflow)
+        );
+
+        // 右辺の和が u8 の範囲を超える
+        let mut target = new_target();
+        assert_eq!(
+            target.add_assign(LinearConstraint::<u8>::new([(x(1), 100)].into_iter(), 100)),
+            Err(ConstraintError::Overflow)
+        );
+    }
+}
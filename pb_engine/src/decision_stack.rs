@@ -119,6 +119,19 @@ where
         return std::ops::Range { start, end };
     }
 
+    /// 決定レベル decision_level (現在の決定レベル以下) で割り当てられたリテラルとその理由を割り当て順に列挙する
+    pub fn level_literals(
+        &self,
+        decision_level: usize,
+    ) -> impl Iterator<Item = (Literal, &Reason<ExplainKeyT>)> + '_ {
+        return self.order_range(decision_level).map(|order| {
+            (
+                self.get_assignment(order),
+                &self.assignment_stack[order].reason,
+            )
+        });
+    }
+
     pub fn get_assignment(&self, order: usize) -> Literal {
         let index = self.assignment_stack[order].index;
         let value = self.states[index].value;
@@ -197,3 +210,44 @@ struct State {
     value: Boolean,
     order: usize,
 }
+
+#[cfg(test)]
+mod test {
+    use super::DecisionStack;
+    use crate::{Boolean, Literal, engine::Reason};
+
+    #[test]
+    fn test_level_literals() {
+        let mut decision_stack = DecisionStack::<usize>::default();
+        for _ in 0..5 {
+            decision_stack.add_variable(Boolean::FALSE);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        decision_stack.assign(x(0), Reason::Propagation { explain_key: 0 });
+        decision_stack.assign(!x(1), Reason::Decision);
+        decision_stack.assign(x(2), Reason::Propagation { explain_key: 1 });
+        decision_stack.assign(x(3), Reason::Decision);
+        decision_stack.assign(!x(4), Reason::Propagation { explain_key: 2 });
+
+        let levels = Vec::from_iter((0..=decision_stack.decision_level()).map(|decision_level| {
+            Vec::from_iter(
+                decision_stack
+                    .level_literals(decision_level)
+                    .map(|(literal, reason)| (literal, *reason)),
+            )
+        }));
+        assert!(levels.iter().map(Vec::len).eq([1, 2, 2]));
+        assert!(levels[1][0].0 == !x(1) && levels[2][1].0 == !x(4));
+        // 決定レベル 0 以外の最初のリテラルは決定
+        assert!(levels[0][0].1.is_propagation());
+        assert!(levels[1..].iter().all(|level| level[0].1.is_decision()));
+        assert!(
+            levels[1..]
+                .iter()
+                .all(|level| level[1..].iter().all(|(_, reason)| reason.is_propagation()))
+        );
+
+        decision_stack.backjump(1);
+        assert!(decision_stack.level_literals(1).count() == 2);
+    }
+}
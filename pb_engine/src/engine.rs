@@ -1,28 +1,53 @@
 mod activities;
 mod assignment_queue;
+mod branching_heuristic;
+mod decision_trace;
+mod drat_logger;
+mod implication_graph;
 mod reason;
+mod statistics;
 
 use crate::{
-    collections::LiteralArray, decision_stack::DecisionStack, theories::{
+    ConstraintError, CountConstraint, CountConstraintTrait, CountConstraintView, LinearConstraint,
+    LinearConstraintTrait, LinearConstraintView, MonadicClause,
+    analyze::CalculatePropagationLevel,
+    collections::LiteralArray,
+    decision_stack::DecisionStack,
+    strengthen_integer_linear_constraint,
+    theories::{
         CountConstraintExplainKey, CountConstraintTheory, IntegerLinearConstraintExplainKey,
-        IntegerLinearConstraintTheory, MonadicClauseExplainKey, MonadicClauseTheory,
-        TheoryAddConstraintTrait, TheoryTrait,
-    }, types::{Boolean, Literal}, CountConstraint, CountConstraintTrait, LinearConstraint, LinearConstraintTrait, MonadicClause
+        IntegerLinearConstraintTheory, MonadicClauseExplainKey, MonadicClauseTheory, Propagation,
+        ReduceDbCriterion, TheoryAddConstraintTrait, TheoryTrait,
+    },
+    types::{Boolean, Coefficient, Literal},
 };
 use activities::Activities;
 use assignment_queue::AssignmentQueue;
 use either::Either;
 use utility::Map;
-use std::ops::Deref;
+use std::{
+    io::Write,
+    ops::Deref,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
+pub use branching_heuristic::{BranchingHeuristic, RandomBranching, StaticOrderBranching};
+pub use decision_trace::{DecisionEvent, DecisionTrace, ParseDecisionTraceError};
+pub use drat_logger::DratLogger;
+pub use implication_graph::{ImplicationGraph, ImplicationGraphNode};
 pub use reason::Reason;
+use statistics::RestartCallback;
+pub use statistics::Statistics;
 
 pub enum PBConstraint<
     CountConstraintT = CountConstraint,
-    IntegerLinearConstraintT = LinearConstraint<u64>,
+    IntegerLinearConstraintT = LinearConstraint<Coefficient>,
 > where
     CountConstraintT: CountConstraintTrait,
-    IntegerLinearConstraintT: LinearConstraintTrait<Value = u64>,
+    IntegerLinearConstraintT: LinearConstraintTrait<Value = Coefficient>,
 {
     MonadicClause(MonadicClause),
     CountConstraint(CountConstraintT),
@@ -33,9 +58,9 @@ impl<CountConstraintT, IntegerLinearConstraintT> LinearConstraintTrait
     for PBConstraint<CountConstraintT, IntegerLinearConstraintT>
 where
     CountConstraintT: CountConstraintTrait,
-    IntegerLinearConstraintT: LinearConstraintTrait<Value = u64>,
+    IntegerLinearConstraintT: LinearConstraintTrait<Value = Coefficient>,
 {
-    type Value = u64;
+    type Value = Coefficient;
     fn iter_terms(&self) -> impl Iterator<Item = (Literal, Self::Value)> + Clone + '_ {
         return match self {
             Self::MonadicClause(monadic_clause) => {
@@ -86,6 +111,14 @@ impl From<IntegerLinearConstraintExplainKey> for PBExplainKey {
     }
 }
 
+/// 制約条件の由来
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConstraintOrigin {
+    /// 元の制約条件 (入力での番号)
+    Original(usize),
+    Learnt,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum PBState {
     Noconflict,
@@ -93,6 +126,8 @@ pub enum PBState {
         index: usize,
         explain_keys: [PBExplainKey; 2],
     },
+    /// set_cancellation で設定したフラグが立てられたため，割り当てを行わずに止まっている
+    Interrupted,
 }
 
 impl PBState {
@@ -103,6 +138,26 @@ impl PBState {
     pub fn is_conflict(&self) -> bool {
         return matches!(self, Self::Conflict { .. });
     }
+
+    pub fn is_interrupted(&self) -> bool {
+        return matches!(self, Self::Interrupted);
+    }
+
+    pub fn conflict_variable(&self) -> Option<usize> {
+        if let Self::Conflict { index, .. } = self {
+            return Some(*index);
+        } else {
+            return None;
+        }
+    }
+
+    pub fn conflict_explain_keys(&self) -> Option<[PBExplainKey; 2]> {
+        if let Self::Conflict { explain_keys, .. } = self {
+            return Some(*explain_keys);
+        } else {
+            return None;
+        }
+    }
 }
 
 pub struct PBEngine {
@@ -110,10 +165,60 @@ pub struct PBEngine {
     activities: Activities,
     monadic_clause_theory: MonadicClauseTheory,
     count_constraint_theory: CountConstraintTheory,
-    integer_linear_constraint_theory: IntegerLinearConstraintTheory,
+    // 基数制約のみを扱う場合は None
+    integer_linear_constraint_theory: Option<IntegerLinearConstraintTheory>,
     assignment_queue: AssignmentQueue<PBExplainKey>,
     state: PBState,
     variable_map: Map<f64>,
+    invariant_checks: bool,
+    // None の場合はアクティビティに基づいて決定変数を選択する
+    branching_heuristic: Option<Box<dyn BranchingHeuristic>>,
+    drat_logger: Option<DratLogger>,
+    // 単項節，個数制約，整数線形制約それぞれを理由とする割り当ての数
+    propagation_counts: [u64; 3],
+    // 個数制約と整数線形制約の学習制約の数の合計の上限
+    max_learnt: Option<usize>,
+    // 決定レベルの上限
+    max_decision_level: Option<usize>,
+    // 次に追加する元の制約条件の入力での番号
+    original_constraint_index: usize,
+    monadic_clause_origins: LiteralArray<Option<ConstraintOrigin>>,
+    // 各理論の row_id ごとの由来
+    count_constraint_origins: Vec<ConstraintOrigin>,
+    integer_linear_constraint_origins: Vec<ConstraintOrigin>,
+    // start_recording 以降に行われた操作 (記録していない場合は None)
+    recording: Option<Vec<DecisionEvent>>,
+    number_of_restarts: u64,
+    number_of_conflicts: u64,
+    // 追加した学習制約の項数の合計と個数 (平均の計算用)
+    learnt_size_sum: u64,
+    number_of_added_learnt_constraints: u64,
+    // リスタートごとに呼び出す関数
+    restart_callback: Option<RestartCallback>,
+    // 探索を打ち切るためのフラグ
+    cancellation: Option<Arc<AtomicBool>>,
+    // add_tagged_clause で導入した選択変数のインデックスからタグへの写像
+    selector_tags: Map<u32>,
+    // assume で決定した変数のインデックスから値への写像 (未割り当てに戻ると削除する)
+    assumptions: Map<Boolean>,
+}
+
+/// 決定レベル 0 における PBEngine の状態 (割り当て，制約条件，アクティビティ)
+///
+/// PBEngine::snapshot で作成し， PBEngine::restore で復元する
+#[derive(Clone)]
+pub struct EngineSnapshot {
+    decision_stack: DecisionStack<PBExplainKey>,
+    activities: Activities,
+    monadic_clause_theory: MonadicClauseTheory,
+    count_constraint_theory: CountConstraintTheory,
+    integer_linear_constraint_theory: Option<IntegerLinearConstraintTheory>,
+    assignment_queue: AssignmentQueue<PBExplainKey>,
+    state: PBState,
+    monadic_clause_origins: LiteralArray<Option<ConstraintOrigin>>,
+    count_constraint_origins: Vec<ConstraintOrigin>,
+    integer_linear_constraint_origins: Vec<ConstraintOrigin>,
+    selector_tags: Map<u32>,
 }
 
 impl Deref for PBEngine {
@@ -130,17 +235,219 @@ impl PBEngine {
             activities: Activities::new(activity_time_constant),
             monadic_clause_theory: MonadicClauseTheory::new(),
             count_constraint_theory: CountConstraintTheory::new(1e4),
-            integer_linear_constraint_theory: IntegerLinearConstraintTheory::new(1e4),
+            integer_linear_constraint_theory: Some(IntegerLinearConstraintTheory::new(1e4)),
             assignment_queue: AssignmentQueue::default(),
             state: PBState::Noconflict,
-            variable_map: Map::default()
+            variable_map: Map::default(),
+            invariant_checks: false,
+            branching_heuristic: None,
+            drat_logger: None,
+            propagation_counts: [0; 3],
+            max_learnt: None,
+            max_decision_level: None,
+            original_constraint_index: 0,
+            monadic_clause_origins: LiteralArray::default(),
+            count_constraint_origins: Vec::default(),
+            integer_linear_constraint_origins: Vec::default(),
+            recording: None,
+            number_of_restarts: 0,
+            number_of_conflicts: 0,
+            learnt_size_sum: 0,
+            number_of_added_learnt_constraints: 0,
+            restart_callback: None,
+            cancellation: None,
+            selector_tags: Map::default(),
+            assumptions: Map::default(),
+        }
+    }
+
+    /// 整数線形制約の理論を持たない，基数制約のみを扱うエンジンを作成する
+    ///
+    /// add_integer_linear_constraint には係数が全て 1 の制約条件のみを渡すことができ，
+    /// それ以外の制約条件を渡すと panic する
+    pub fn new_cardinality_only(activity_time_constant: f64) -> Self {
+        return Self {
+            integer_linear_constraint_theory: None,
+            ..Self::new(activity_time_constant)
+        };
+    }
+
+    pub fn is_cardinality_only(&self) -> bool {
+        return self.integer_linear_constraint_theory.is_none();
+    }
+
+    /// 決定レベル 0 の状態を複製する
+    ///
+    /// 学習制約とアクティビティも複製されるため，後から restore すれば
+    /// 作成した時点から同じように探索を再開できる
+    pub fn snapshot(&self) -> EngineSnapshot {
+        assert!(self.decision_stack.decision_level() == 0);
+        return EngineSnapshot {
+            decision_stack: self.decision_stack.clone(),
+            activities: self.activities.clone(),
+            monadic_clause_theory: self.monadic_clause_theory.clone(),
+            count_constraint_theory: self.count_constraint_theory.clone(),
+            integer_linear_constraint_theory: self.integer_linear_constraint_theory.clone(),
+            assignment_queue: self.assignment_queue.clone(),
+            state: self.state,
+            monadic_clause_origins: self.monadic_clause_origins.clone(),
+            count_constraint_origins: self.count_constraint_origins.clone(),
+            integer_linear_constraint_origins: self.integer_linear_constraint_origins.clone(),
+            selector_tags: self.selector_tags.clone(),
+        };
+    }
+
+    /// snapshot で複製した状態に戻す
+    ///
+    /// 伝播待ちの割り当ては snapshot を作成した時点のものに置き換えられる．
+    /// 分岐ヒューリスティックと DRAT 証明の出力先は復元しないため，DRAT 証明を書き出す場合は
+    /// snapshot 以降に削除された節が証明と食い違うことに注意する
+    pub fn restore(&mut self, snapshot: &EngineSnapshot) {
+        self.decision_stack = snapshot.decision_stack.clone();
+        self.activities = snapshot.activities.clone();
+        self.monadic_clause_theory = snapshot.monadic_clause_theory.clone();
+        self.count_constraint_theory = snapshot.count_constraint_theory.clone();
+        self.integer_linear_constraint_theory = snapshot.integer_linear_constraint_theory.clone();
+        self.assignment_queue = snapshot.assignment_queue.clone();
+        self.state = snapshot.state;
+        self.monadic_clause_origins = snapshot.monadic_clause_origins.clone();
+        self.count_constraint_origins = snapshot.count_constraint_origins.clone();
+        self.integer_linear_constraint_origins =
+            snapshot.integer_linear_constraint_origins.clone();
+        self.selector_tags = snapshot.selector_tags.clone();
+        self.assumptions.clear();
+    }
+
+    /// 以降の操作の記録を開始する (記録中であれば記録を破棄してやり直す)
+    pub fn start_recording(&mut self) {
+        self.recording = Some(Vec::default());
+    }
+
+    /// 記録を終了し， start_recording 以降に行われた操作を返す
+    pub fn take_recording(&mut self) -> DecisionTrace {
+        return DecisionTrace {
+            events: self.recording.take().unwrap_or_default(),
+        };
+    }
+
+    fn record(&mut self, event: impl FnOnce() -> DecisionEvent) {
+        if let Some(recording) = &mut self.recording {
+            recording.push(event());
+        }
+    }
+
+    /// trace の操作を順に行う
+    ///
+    /// 記録を開始した時点と同じ状態のエンジンに対して行えば，決定変数の選択を含めて同じ探索が再現される．
+    /// 行えない操作 (割り当て済みの変数の決定など) があればその位置を Err で返す
+    pub fn replay(&mut self, trace: &DecisionTrace) -> Result<(), usize> {
+        for (position, event) in trace.events.iter().enumerate() {
+            let is_replayable = match event {
+                DecisionEvent::AddVariable {
+                    initial_value,
+                    initial_activity,
+                } => {
+                    self.add_variable_with_initial_value(*initial_value, *initial_activity);
+                    true
+                }
+                &DecisionEvent::AddMonadicClause { literal, is_learnt } => self
+                    .add_monadic_clause(MonadicClause { literal }, is_learnt)
+                    .is_ok(),
+                DecisionEvent::AddCountConstraint {
+                    constraint,
+                    is_learnt,
+                } => self.add_count_constraint(constraint, *is_learnt).is_ok(),
+                DecisionEvent::AddIntegerLinearConstraint {
+                    constraint,
+                    is_learnt,
+                } => self
+                    .add_integer_linear_constraint(constraint, *is_learnt)
+                    .is_ok(),
+                DecisionEvent::Propagate => {
+                    self.state.is_noconflict() && {
+                        self.propagate();
+                        true
+                    }
+                }
+                DecisionEvent::PropagateStep => {
+                    self.state.is_noconflict() && {
+                        self.propagate_step();
+                        true
+                    }
+                }
+                &DecisionEvent::Decide(literal) => {
+                    self.state.is_noconflict()
+                        && self.assignment_queue.is_empty()
+                        && !self.decision_stack.is_assigned(literal.index())
+                        && {
+                            self.push_decision(literal);
+                            true
+                        }
+                }
+                &DecisionEvent::Backjump(backjump_level) => {
+                    backjump_level < self.decision_stack.decision_level() && {
+                        self.backjump(backjump_level);
+                        true
+                    }
+                }
+                DecisionEvent::UpdateAssignmentProbabilities => {
+                    self.update_assignment_probabilities();
+                    true
+                }
+                DecisionEvent::UpdateConflictProbabilities {
+                    conflict_assignments,
+                    backjump_level,
+                } => {
+                    self.update_conflict_probabilities(
+                        conflict_assignments.iter().copied(),
+                        *backjump_level,
+                    );
+                    true
+                }
+            };
+            if !is_replayable {
+                return Err(position);
+            }
         }
+        return Ok(());
+    }
+
+    /// 以降に追加する元の制約条件の入力での番号を設定する (既定は 0)
+    ///
+    /// 1 つの入力の制約条件を複数の制約条件に分けて追加する場合は，それらに同じ番号を設定する
+    pub fn set_original_constraint_index(&mut self, original_constraint_index: usize) {
+        self.original_constraint_index = original_constraint_index;
+    }
+
+    /// explain_key が指す制約条件の由来を返す
+    pub fn constraint_origin(&self, explain_key: PBExplainKey) -> ConstraintOrigin {
+        return match explain_key {
+            PBExplainKey::MonadicClause(explain_key) => {
+                self.monadic_clause_origins[explain_key.monadic_clause().literal].unwrap()
+            }
+            PBExplainKey::CountConstraint(explain_key) => {
+                self.count_constraint_origins[explain_key.row_id()]
+            }
+            PBExplainKey::IntegerLinearConstraint(explain_key) => {
+                self.integer_linear_constraint_origins[explain_key.row_id()]
+            }
+        };
     }
+
+    fn origin(&self, is_learnt: bool) -> ConstraintOrigin {
+        return if is_learnt {
+            ConstraintOrigin::Learnt
+        } else {
+            ConstraintOrigin::Original(self.original_constraint_index)
+        };
+    }
+
     pub fn state(&self) -> PBState {
         return self.state;
     }
 
     pub fn update_assignment_probabilities(&mut self) {
+        self.record(|| DecisionEvent::UpdateAssignmentProbabilities);
         self.activities.update_assignment_probabilities(
             (0..self.decision_stack.number_of_assignments())
                 .map(|order| self.decision_stack.get_assignment(order)),
@@ -154,9 +461,19 @@ impl PBEngine {
     ) {
         // self.activities
         //     .update_conflict_probabilities(conflict_assignments);
+        let mut recorded_assignments = self.recording.as_ref().map(|_| Vec::default());
         self.variable_map.clear();
         for literal in conflict_assignments {
             self.variable_map.insert(literal.index(), 1.0);
+            if let Some(recorded_assignments) = &mut recorded_assignments {
+                recorded_assignments.push(literal);
+            }
+        }
+        if let Some(conflict_assignments) = recorded_assignments {
+            self.record(|| DecisionEvent::UpdateConflictProbabilities {
+                conflict_assignments,
+                backjump_level,
+            });
         }
         for literal in (0..self.decision_stack.number_of_assignments()).map(|order| self.decision_stack.get_assignment(order)) {
             if !self.variable_map.contains_key(literal.index()) {
@@ -173,6 +490,33 @@ impl PBEngine {
         }
     }
 
+    /// 各変数のアクティビティを，元の制約条件に現れる回数に比例した値 (最大 1) とする
+    ///
+    /// 多くの制約条件に現れる変数から決定されるようになる．制約条件を追加した後に呼び出すこと
+    pub fn seed_activities_by_occurrence(&mut self) {
+        let mut number_of_occurrences = vec![0usize; self.number_of_variables()];
+        for (constraint, is_learnt) in self.iter_all_constraints() {
+            if is_learnt {
+                continue;
+            }
+            for (literal, _) in constraint.iter_terms() {
+                number_of_occurrences[literal.index()] += 1;
+            }
+        }
+        let Some(&max_number_of_occurrences) = number_of_occurrences.iter().max() else {
+            return;
+        };
+        if max_number_of_occurrences == 0 {
+            return;
+        }
+        for (index, number_of_occurrences) in number_of_occurrences.into_iter().enumerate() {
+            let activity = number_of_occurrences as f64 / max_number_of_occurrences as f64;
+            self.activities.set_activity(index, activity);
+            // 伝播待ちの割り当ての優先度はアクティビティと一致していなければならない
+            self.assignment_queue.update_priority(index, activity);
+        }
+    }
+
     pub fn assignment_probability(&self, literal: Literal) -> f64 {
         return self.activities.assignment_probability(literal);
     }
@@ -181,6 +525,29 @@ impl PBEngine {
         return self.activities.activity(index);
     }
 
+    /// 全ての変数のインデックス
+    pub fn iter_variables(&self) -> impl Iterator<Item = usize> + Clone + use<> {
+        return 0..self.decision_stack.number_of_variables();
+    }
+
+    /// 全ての変数について正と負の両方のリテラル
+    pub fn iter_literals(&self) -> impl Iterator<Item = Literal> + Clone + use<> {
+        return self.iter_variables().flat_map(|index| {
+            [Boolean::TRUE, Boolean::FALSE]
+                .into_iter()
+                .map(move |value| Literal::new(index, value))
+        });
+    }
+
+    /// 単項節，個数制約，整数線形制約それぞれの理論による伝播の回数
+    pub fn theory_propagation_counts(&self) -> [u64; 3] {
+        return self.propagation_counts;
+    }
+
+    pub fn reset_theory_propagation_counts(&mut self) {
+        self.propagation_counts = [0; 3];
+    }
+
     pub fn number_of_monadic_clauses(&self) -> usize {
         return self.monadic_clause_theory.number_of_monadic_clauses();
     }
@@ -192,7 +559,158 @@ impl PBEngine {
     pub fn number_of_integer_linear_constraints(&self) -> usize {
         return self
             .integer_linear_constraint_theory
-            .number_of_constraints();
+            .as_ref()
+            .map_or(0, |theory| theory.number_of_constraints());
+    }
+
+    /// 個数制約と整数線形制約の学習制約の数の合計
+    pub fn number_of_learnt_constraints(&self) -> usize {
+        return self.count_constraint_theory.number_of_learnt_constraints()
+            + self
+                .integer_linear_constraint_theory
+                .as_ref()
+                .map_or(0, |theory| theory.number_of_learnt_constraints());
+    }
+
+    /// 探索を打ち切るためのフラグを設定する
+    ///
+    /// 他のスレッドからフラグを立てると， decide と propagate は割り当てを行わずに
+    /// state を PBState::Interrupted とする．探索を再開するにはフラグを下ろして reset_to_root を呼ぶ
+    pub fn set_cancellation(&mut self, cancellation: Arc<AtomicBool>) {
+        self.cancellation = Some(cancellation);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        return self
+            .cancellation
+            .as_ref()
+            .is_some_and(|cancellation| cancellation.load(Ordering::Relaxed));
+    }
+
+    /// 決定レベルの上限を設定する (None の場合は上限を設けない)
+    ///
+    /// 決定レベルが上限に達している状態で decide を呼び出すと，決定を行わずに restart する
+    /// (リスタートの回数に数え， on_restart で登録した関数も呼び出す)．上限が低すぎると，
+    /// 学習制約が追加されない限り同じ割り当てを繰り返して探索が進まない (0 の場合は決定を一切行わない)
+    pub fn set_max_decision_level(&mut self, max_decision_level: Option<usize>) {
+        self.max_decision_level = max_decision_level;
+    }
+
+    /// 学習制約の数の上限を設定する (None の場合は上限を設けない)
+    ///
+    /// 学習制約を追加して上限を超えた時点で，backjump の回数による定期的な削除を待たずに
+    /// 削除基準での順位が低い学習制約を削除する．上限が小さすぎると有用な学習制約まで削除され，
+    /// 探索が遅くなる．割り当ての理由となっている学習制約と，削除基準が削除の候補としない学習制約
+    /// (Hybrid における PLBD が 2 以下のもの) は削除しないため，上限を超える場合がある
+    pub fn set_max_learnt(&mut self, max_learnt: Option<usize>) {
+        self.max_learnt = max_learnt;
+        self.reduce_to_max_learnt();
+    }
+
+    /// 学習制約の数が上限を超えていれば，各理論の学習制約の数に比例して上限を割り振って削除する
+    fn reduce_to_max_learnt(&mut self) {
+        let Some(max_learnt) = self.max_learnt else {
+            return;
+        };
+        let number_of_learnt_count_constraints =
+            self.count_constraint_theory.number_of_learnt_constraints();
+        let number_of_learnt_constraints = self.number_of_learnt_constraints();
+        if number_of_learnt_constraints <= max_learnt {
+            return;
+        }
+
+        // 伝播待ちの割り当てと矛盾の理由は削除しない
+        let explain_keys = Vec::from_iter(
+            self.assignment_queue
+                .iter_explain_keys()
+                .chain(self.state.conflict_explain_keys().into_iter().flatten()),
+        );
+        for &explain_key in explain_keys.iter() {
+            self.lock(explain_key);
+        }
+
+        let max_learnt_count_constraints = (max_learnt as u128
+            * number_of_learnt_count_constraints as u128
+            / number_of_learnt_constraints as u128) as usize;
+        self.count_constraint_theory
+            .reduce_learnt_constraints(max_learnt_count_constraints);
+        if let Some(theory) = &mut self.integer_linear_constraint_theory {
+            theory.reduce_learnt_constraints(max_learnt - max_learnt_count_constraints);
+        }
+
+        for &explain_key in explain_keys.iter() {
+            self.unlock(explain_key);
+        }
+        self.log_deleted_clauses();
+    }
+
+    pub fn set_reduce_db_criterion(&mut self, reduce_db_criterion: ReduceDbCriterion) {
+        self.count_constraint_theory
+            .set_reduce_db_criterion(reduce_db_criterion);
+        if let Some(theory) = &mut self.integer_linear_constraint_theory {
+            theory.set_reduce_db_criterion(reduce_db_criterion);
+        }
+    }
+
+    /// propagate のたびに制約条件の内部状態を再計算して検証するかどうかを設定する (既定は無効)
+    ///
+    /// 全ての制約条件を走査するため非常に遅い．不整合が見つかった場合は panic する
+    pub fn enable_invariant_checks(&mut self, invariant_checks: bool) {
+        self.invariant_checks = invariant_checks;
+    }
+
+    /// 節を学習するたびに，その節が包含する学習した節を削除するかどうかを設定する (既定は無効)
+    ///
+    /// 学習した節の数は減るが，節を学習するたびに同じリテラルを監視している節を走査する
+    pub fn enable_subsumption_checks(&mut self, subsumption_checks: bool) {
+        self.count_constraint_theory
+            .enable_subsumption_checks(subsumption_checks);
+    }
+
+    /// 決定変数を order の順に選択する (StaticOrderBranching を設定する)
+    ///
+    /// 各変数には保存された値が割り当てられる．backjump で取り消された変数は再び order の順に選択され，
+    /// order の変数が全て割り当て済みであればアクティビティに基づいて選択する
+    pub fn set_decision_order(&mut self, order: Vec<usize>) {
+        self.set_branching_heuristic(Some(Box::new(StaticOrderBranching::new(order))));
+    }
+
+    /// 変数を凍結する
+    ///
+    /// 凍結された変数は，凍結されていない変数が全て割り当てられるまで決定されず，それまでは伝播によってのみ割り当てられる．
+    /// 伝播と矛盾の解析には通常どおり現れる
+    pub fn freeze_variable(&mut self, index: usize) {
+        self.activities.freeze_variable(index);
+    }
+
+    pub fn thaw_variable(&mut self, index: usize) {
+        self.activities.thaw_variable(index);
+    }
+
+    pub fn is_frozen(&self, index: usize) -> bool {
+        return self.activities.is_frozen(index);
+    }
+
+    /// 決定変数の選択方法を設定する (None でアクティビティに基づく選択に戻す)
+    pub fn set_branching_heuristic(
+        &mut self,
+        branching_heuristic: Option<Box<dyn BranchingHeuristic>>,
+    ) {
+        self.branching_heuristic = branching_heuristic;
+    }
+
+    /// 学習した節 (lower = 1 の学習制約) と削除した節を DRAT 形式で書き出す
+    ///
+    /// lower が 2 以上の学習制約や整数線形制約は書き出さないため，証明として有効なのは
+    /// 入力が節のみからなり，節のみを学習する場合に限られる
+    pub fn set_drat_logger(&mut self, drat_logger: Option<DratLogger>) {
+        self.count_constraint_theory
+            .record_deleted_clauses(drat_logger.is_some());
+        self.drat_logger = drat_logger;
+    }
+
+    pub fn drat_logger_mut(&mut self) -> Option<&mut DratLogger> {
+        return self.drat_logger.as_mut();
     }
 
     pub fn add_variable_with_initial_value(
@@ -200,18 +718,143 @@ impl PBEngine {
         initial_value: Boolean,
         initial_activity: f64,
     ) {
+        self.record(|| DecisionEvent::AddVariable {
+            initial_value,
+            initial_activity,
+        });
         self.decision_stack.add_variable(initial_value);
         self.activities.add_variable(initial_activity);
         self.monadic_clause_theory.add_variable();
+        self.monadic_clause_origins.push([None, None]);
         self.count_constraint_theory.add_variable();
-        self.integer_linear_constraint_theory.add_variable();
+        if let Some(theory) = &mut self.integer_linear_constraint_theory {
+            theory.add_variable();
+        }
+    }
+
+    /// 全ての変数に値が割り当てられているときに，各制約条件が充足されているかを検証する
+    ///
+    /// 充足されていない制約条件があれば，そのうち最初のもののインデックスを返す
+    pub fn verify_against(
+        &self,
+        problem: &[impl LinearConstraintTrait<Value = Coefficient>],
+    ) -> Result<(), usize> {
+        assert!(
+            self.decision_stack.number_of_assignments() == self.decision_stack.number_of_variables()
+        );
+        for (k, constraint) in problem.iter().enumerate() {
+            let lhs: u128 = constraint
+                .iter_terms()
+                .filter(|&(literal, _)| self.decision_stack.is_true(literal))
+                .fold(0, |lhs: u128, (_, coefficient)| lhs.saturating_add(coefficient as u128));
+            if lhs < constraint.lower() as u128 {
+                return Err(k);
+            }
+        }
+        return Ok(());
+    }
+
+    /// 伝播待ちの割り当てがなく，矛盾も生じていないか
+    ///
+    /// 制約条件を追加した後は，propagate を呼ぶまで false となることがある
+    pub fn is_propagation_complete(&self) -> bool {
+        return self.assignment_queue.is_empty() && self.state.is_noconflict();
+    }
+
+    /// 値が割り当てられていない変数が存在するか
+    pub fn has_unassigned(&self) -> bool {
+        return self.decision_stack.number_of_assignments()
+            < self.decision_stack.number_of_variables();
     }
 
     pub fn number_of_fixed(&self) -> usize {
         self.decision_stack.order_range(0).end
     }
 
-    pub fn add_monadic_clause(&mut self, monadic_clause: MonadicClause, is_learnt: bool) {
+    /// 現在の割り当てのもとでの constraint の左辺値の上界 (False でないリテラルの係数の和)
+    pub fn current_sup(&self, constraint: &impl LinearConstraintTrait<Value = Coefficient>) -> Coefficient {
+        return constraint
+            .iter_terms()
+            .filter(|&(literal, _)| !self.decision_stack.is_false(literal))
+            .map(|(_, coefficient)| coefficient)
+            .sum();
+    }
+
+    /// 現在の割り当てのもとでの constraint のスラック (current_sup - lower)
+    ///
+    /// 負であれば違反しており，いずれかの未割り当てリテラルの係数より小さければ伝播が発生する
+    pub fn current_slack(&self, constraint: &impl LinearConstraintTrait<Value = Coefficient>) -> i128 {
+        return self.current_sup(constraint) as i128 - constraint.lower() as i128;
+    }
+
+    /// 節 (literals のいずれかが True) を追加する
+    ///
+    /// 重複したリテラルはまとめ，リテラルが 1 つであれば単項節として，2 つ以上であれば
+    /// lower = 1 の個数制約として追加する．空節と，現在の割り当てのもとで既に違反している節は
+    /// 追加せずに Err(ConstraintError::Infeasible) を返す．ただしリテラルが 2 つ以上の学習節
+    /// (is_learnt = true) は違反していないことを呼び出し側が保証するものとし，違反を検査しない
+    pub fn add_clause(
+        &mut self,
+        literals: impl Iterator<Item = Literal>,
+        is_learnt: bool,
+    ) -> Result<(), ConstraintError> {
+        let mut literals = Vec::from_iter(literals);
+        literals.sort_unstable_by_key(|literal| (literal.index(), literal.value() as usize));
+        literals.dedup();
+        return match literals.as_slice() {
+            [] => Err(ConstraintError::Infeasible),
+            &[literal] => self.add_monadic_clause(MonadicClause { literal }, is_learnt),
+            _ => self.add_count_constraint(
+                CountConstraintView::new(literals.iter().copied(), 1),
+                is_learnt,
+            ),
+        };
+    }
+
+    /// タグを付けた節 (ソフト制約) を追加し，その節を有効にする選択リテラルを返す
+    ///
+    /// 新しい変数 s を導入して節 literals ∨ ¬s を追加する． s を assume した探索で矛盾が生じた場合に，
+    /// tagged_core で矛盾の原因となった節のタグを得られる
+    pub fn add_tagged_clause(
+        &mut self,
+        literals: impl Iterator<Item = Literal>,
+        tag: u32,
+    ) -> Result<Literal, ConstraintError> {
+        let index = self.decision_stack.number_of_variables();
+        // decide で選択されても節を有効にしないよう，初期値は偽とする
+        self.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        let selector = Literal::new(index, Boolean::TRUE);
+        self.selector_tags.insert(index, tag);
+        self.add_clause(literals.chain([!selector]), false)?;
+        return Ok(selector);
+    }
+
+    /// 現在の割り当てのもとで既に違反している場合は追加せずに Err(ConstraintError::Infeasible) を返す
+    /// (決定レベル 0 では充足不能であることを意味する)
+    pub fn add_monadic_clause(
+        &mut self,
+        monadic_clause: MonadicClause,
+        is_learnt: bool,
+    ) -> Result<(), ConstraintError> {
+        if self.decision_stack.is_false(monadic_clause.literal) {
+            return Err(ConstraintError::Infeasible);
+        }
+        self.record(|| DecisionEvent::AddMonadicClause {
+            literal: monadic_clause.literal,
+            is_learnt,
+        });
+        if is_learnt {
+            self.count_learnt_size(1);
+            if let Some(drat_logger) = &mut self.drat_logger {
+                drat_logger.add_clause([monadic_clause.literal].into_iter());
+            }
+        }
+        // 同じ単項節が複数あれば元の制約条件を由来とする
+        let origin = self.origin(is_learnt);
+        let recorded_origin = &mut self.monadic_clause_origins[monadic_clause.literal];
+        if recorded_origin.is_none_or(|origin| origin == ConstraintOrigin::Learnt) {
+            recorded_origin.replace(origin);
+        }
         Self::add_constraint_to(
             &mut self.monadic_clause_theory,
             monadic_clause,
@@ -220,13 +863,47 @@ impl PBEngine {
             &mut self.assignment_queue,
             &self.activities,
         );
+        return Ok(());
     }
 
+    /// 現在の割り当てのもとで既に違反している制約条件 (項を持たず lower が正のものを含む) は
+    /// 追加せずに Err(ConstraintError::Infeasible) を返す
+    ///
+    /// 学習制約は backjump 後の割り当てのもとで違反せず恒真でもないため，これらの検査を省略する
     pub fn add_count_constraint(
         &mut self,
         count_constraint: impl CountConstraintTrait,
         is_learnt: bool,
-    ) {
+    ) -> Result<(), ConstraintError> {
+        if !is_learnt {
+            let number_of_non_false = count_constraint
+                .iter_terms()
+                .filter(|&literal| !self.decision_stack.is_false(literal))
+                .count();
+            if number_of_non_false < count_constraint.lower() as usize {
+                return Err(ConstraintError::Infeasible);
+            }
+            // 常に充足される制約条件は追加しない
+            if count_constraint.is_tautological() {
+                return Ok(());
+            }
+        }
+        self.record(|| DecisionEvent::AddCountConstraint {
+            constraint: CountConstraint::new(
+                count_constraint.iter_terms(),
+                count_constraint.lower(),
+            ),
+            is_learnt,
+        });
+        if is_learnt {
+            self.count_learnt_size(count_constraint.len());
+        }
+        if is_learnt
+            && count_constraint.is_clause()
+            && let Some(drat_logger) = &mut self.drat_logger
+        {
+            drat_logger.add_clause(count_constraint.iter_terms());
+        }
         Self::add_constraint_to(
             &mut self.count_constraint_theory,
             count_constraint,
@@ -235,21 +912,212 @@ impl PBEngine {
             &mut self.assignment_queue,
             &self.activities,
         );
+        self.count_constraint_origins.resize(
+            self.count_constraint_theory.number_of_rows(),
+            self.origin(is_learnt),
+        );
+        // 学習した節に包含されて削除された節
+        self.log_deleted_clauses();
+        if is_learnt {
+            self.reduce_to_max_learnt();
+        }
+        return Ok(());
+    }
+
+    /// 学習制約の項数の平均のために，追加した学習制約の項数を数える
+    fn count_learnt_size(&mut self, size: usize) {
+        self.learnt_size_sum += size as u64;
+        self.number_of_added_learnt_constraints += 1;
+    }
+
+    /// 個数制約の理論が削除した節を DRAT 証明に書き出す
+    fn log_deleted_clauses(&mut self) {
+        if let Some(drat_logger) = &mut self.drat_logger {
+            for deleted_clause in self.count_constraint_theory.take_deleted_clauses() {
+                drat_logger.delete_clause(deleted_clause.into_iter());
+            }
+        }
     }
 
+    /// 現在の割り当てのもとで既に違反している制約条件 (項を持たず lower が正のものを含む) は
+    /// 追加せずに Err(ConstraintError::Infeasible) を返す (学習制約は検査しない)
     pub fn add_integer_linear_constraint(
         &mut self,
-        constraint: impl LinearConstraintTrait<Value = u64>,
+        constraint: impl LinearConstraintTrait<Value = Coefficient>,
         is_learnt: bool,
-    ) {
-        Self::add_constraint_to(
-            &mut self.integer_linear_constraint_theory,
-            constraint,
-            is_learnt,
-            &self.decision_stack,
-            &mut self.assignment_queue,
-            &self.activities,
+    ) -> Result<(), ConstraintError> {
+        return self.add_integer_linear_row(constraint, is_learnt, false, false);
+    }
+
+    /// 学習制約として追加するが，学習制約の削除の対象としない (アクティビティは学習制約と同様に更新される)
+    ///
+    /// SAT 符号化の補助的な制約条件のように，学習制約として扱いつつモデルが依存するものに用いる．
+    /// 整数線形制約の理論を持たない PBEngine (new_cardinality_only) では使用できない
+    pub fn add_integer_linear_constraint_pinned(
+        &mut self,
+        constraint: impl LinearConstraintTrait<Value = Coefficient>,
+    ) -> Result<(), ConstraintError> {
+        assert!(
+            !self.is_cardinality_only(),
+            "a pinned constraint was added to a cardinality-only engine"
         );
+        return self.add_integer_linear_row(constraint, true, true, false);
+    }
+
+    /// presorted が true であれば，係数の降順に並んだ項をソートせずに追加する
+    fn add_integer_linear_row(
+        &mut self,
+        constraint: impl LinearConstraintTrait<Value = Coefficient>,
+        is_learnt: bool,
+        pinned: bool,
+        presorted: bool,
+    ) -> Result<(), ConstraintError> {
+        // 固定する制約条件は学習制約として追加するが，解析で導いたものではないため検査する
+        if (!is_learnt || pinned) && self.is_violated(&constraint) {
+            return Err(ConstraintError::Infeasible);
+        }
+        let Some(theory) = &mut self.integer_linear_constraint_theory else {
+            assert!(
+                constraint.iter_terms().all(|(_, coefficient)| coefficient == 1),
+                "a constraint with a non-unit coefficient was added to a cardinality-only engine"
+            );
+            return self.add_count_constraint(
+                CountConstraintView::new(
+                    constraint.iter_terms().map(|(literal, _)| literal),
+                    constraint.lower(),
+                ),
+                is_learnt,
+            );
+        };
+        if let Some(recording) = &mut self.recording {
+            recording.push(DecisionEvent::AddIntegerLinearConstraint {
+                constraint: LinearConstraint::new(constraint.iter_terms(), constraint.lower()),
+                is_learnt,
+            });
+        }
+        if is_learnt {
+            self.learnt_size_sum += constraint.len() as u64;
+            self.number_of_added_learnt_constraints += 1;
+        }
+        let row_id = if presorted {
+            let assignment_queue = &mut self.assignment_queue;
+            let activities = &self.activities;
+            theory
+                .add_constraint_presorted(
+                    constraint,
+                    is_learnt,
+                    &self.decision_stack,
+                    |propagation| Self::push_propagation(assignment_queue, activities, propagation),
+                )
+                .unwrap()
+        } else {
+            Self::add_constraint_to(
+                theory,
+                constraint,
+                is_learnt,
+                &self.decision_stack,
+                &mut self.assignment_queue,
+                &self.activities,
+            )
+        };
+        // 削除の対象となる前に固定する (自明な制約条件は行が追加されない)
+        if pinned && let Some(row_id) = row_id {
+            theory.pin_row(row_id);
+        }
+        let number_of_rows = theory.number_of_rows();
+        self.integer_linear_constraint_origins
+            .resize(number_of_rows, self.origin(is_learnt));
+        if is_learnt {
+            self.reduce_to_max_learnt();
+        }
+        return Ok(());
+    }
+
+    /// 係数の降順に並んだ制約条件を追加する (整数線形制約の理論での項のソートを省略する)
+    pub fn add_integer_linear_constraint_presorted(
+        &mut self,
+        constraint: impl LinearConstraintTrait<Value = Coefficient>,
+        is_learnt: bool,
+    ) -> Result<(), ConstraintError> {
+        return self.add_integer_linear_row(constraint, is_learnt, false, true);
+    }
+
+    /// add_monadic_clause に続けて伝播を行い，伝播後の状態を返す
+    pub fn add_monadic_clause_checked(
+        &mut self,
+        monadic_clause: MonadicClause,
+        is_learnt: bool,
+    ) -> Result<PBState, ConstraintError> {
+        self.add_monadic_clause(monadic_clause, is_learnt)?;
+        return Ok(self.propagate_after_add());
+    }
+
+    /// add_count_constraint に続けて伝播を行い，伝播後の状態を返す
+    pub fn add_count_constraint_checked(
+        &mut self,
+        count_constraint: impl CountConstraintTrait,
+        is_learnt: bool,
+    ) -> Result<PBState, ConstraintError> {
+        self.add_count_constraint(count_constraint, is_learnt)?;
+        return Ok(self.propagate_after_add());
+    }
+
+    /// add_integer_linear_constraint に続けて伝播を行い，伝播後の状態を返す
+    ///
+    /// 追加した制約条件による伝播が他の制約条件の伝播と矛盾した場合は PBState::Conflict を返す．
+    /// 追加する時点で違反している制約条件は add_integer_linear_constraint と同様に
+    /// Err(ConstraintError::Infeasible) となる
+    pub fn add_integer_linear_constraint_checked(
+        &mut self,
+        constraint: impl LinearConstraintTrait<Value = Coefficient>,
+        is_learnt: bool,
+    ) -> Result<PBState, ConstraintError> {
+        self.add_integer_linear_constraint(constraint, is_learnt)?;
+        return Ok(self.propagate_after_add());
+    }
+
+    // 既に矛盾しているか中断している場合は伝播せずにその状態を返す
+    fn propagate_after_add(&mut self) -> PBState {
+        if !self.state.is_noconflict() {
+            return self.state;
+        }
+        return self.propagate();
+    }
+
+    /// 現在の割り当てのもとで constraint が伝播を行う最小の決定レベルを返す
+    ///
+    /// 各決定レベルまでの割り当てのもとで，左辺値の上界から未割り当ての項の係数の最大値を引くと
+    /// lower を下回る最初の決定レベルであり，伝播しない場合や，それより前に違反している場合は None を返す
+    pub fn propagation_level_of(
+        &self,
+        constraint: &impl LinearConstraintTrait<Value = Coefficient>,
+    ) -> Option<usize> {
+        return CalculatePropagationLevel::new().call(constraint, &self.decision_stack, true);
+    }
+
+    /// 割り当て順が order 以下の割り当てのもとで constraint が違反しているか
+    ///
+    /// order が usize::MAX の場合は現在の割り当てのもとで判定する．
+    /// 左辺値の上界は is_violated と同様に u128 で飽和させて計算する
+    pub fn constraint_falsified_at(
+        &self,
+        constraint: &impl LinearConstraintTrait<Value = Coefficient>,
+        order: usize,
+    ) -> bool {
+        let sup: u128 = constraint
+            .iter_terms()
+            .filter(|&(literal, _)| !self.decision_stack.is_false_at(literal, order))
+            .fold(0, |sup: u128, (_, coefficient)| sup.saturating_add(coefficient as u128));
+        return sup < constraint.lower() as u128;
+    }
+
+    /// 左辺値の上界が lower 未満か (係数の和が Coefficient を超えうるため u128 で飽和させて計算する)
+    fn is_violated(&self, constraint: &impl LinearConstraintTrait<Value = Coefficient>) -> bool {
+        let sup: u128 = constraint
+            .iter_terms()
+            .filter(|&(literal, _)| !self.decision_stack.is_false(literal))
+            .fold(0, |sup: u128, (_, coefficient)| sup.saturating_add(coefficient as u128));
+        return sup < constraint.lower() as u128;
     }
 
     fn add_constraint_to<TheoryT, ConstraintT>(
@@ -259,74 +1127,197 @@ impl PBEngine {
         decision_stack: &DecisionStack<impl Copy>,
         assignment_queue: &mut AssignmentQueue<PBExplainKey>,
         activities: &Activities,
-    ) where
+    ) -> Option<usize>
+    where
         TheoryT: TheoryAddConstraintTrait<ConstraintT>,
         TheoryT::ExplainKey: Into<PBExplainKey>,
     {
-        theory
+        return theory
             .add_constraint(constraint, is_learnt, decision_stack, |propagation| {
-                assignment_queue.push(
-                    propagation.literal,
-                    Reason::Propagation {
-                        explain_key: propagation.explain_key.into(),
-                    },
-                    activities.activity(propagation.literal.index()),
-                    propagation.plbd,
-                );
+                Self::push_propagation(assignment_queue, activities, propagation)
             })
             .unwrap();
     }
 
+    /// 制約条件の追加時に発生した伝播を割り当て待ちのキューに入れる
+    fn push_propagation(
+        assignment_queue: &mut AssignmentQueue<PBExplainKey>,
+        activities: &Activities,
+        propagation: Propagation<impl Copy + Into<PBExplainKey>>,
+    ) {
+        assignment_queue.push(
+            propagation.literal,
+            Reason::Propagation {
+                explain_key: propagation.explain_key.into(),
+            },
+            activities.activity(propagation.literal.index()),
+            propagation.plbd,
+        );
+    }
+
     pub fn decide(&mut self) {
+        if self.is_cancelled() {
+            self.state = PBState::Interrupted;
+            return;
+        }
         assert!(self.state.is_noconflict());
         debug_assert!(self.assignment_queue.is_empty());
-        let decision_variable = {
+        if self
+            .max_decision_level
+            .is_some_and(|max_decision_level| self.decision_stack.decision_level() >= max_decision_level)
+        {
+            self.restart();
+            return;
+        }
+        let selected_variable = self.branching_heuristic.take().and_then(|mut heuristic| {
+            let literal = heuristic.select(self);
+            self.branching_heuristic = Some(heuristic);
+            literal.map(|literal| literal.index())
+        });
+        debug_assert!(selected_variable.is_none_or(|index| !self.decision_stack.is_assigned(index)));
+        let decision_variable = if let Some(decision_variable) = selected_variable {
+            decision_variable
+        } else {
             let mut decision_variable = None;
-            loop {
-                let variable = self.activities.pop_unassigned_variable().unwrap();
+            while let Some(variable) = self.activities.pop_unassigned_variable() {
                 if !self.decision_stack.is_assigned(variable) {
                     decision_variable.replace(variable);
                     break;
                 }
             }
-            decision_variable.unwrap()
+            // 凍結されていない変数が全て割り当て済みであれば，凍結された変数を決定する
+            decision_variable.unwrap_or_else(|| {
+                self.iter_variables()
+                    .find(|&index| !self.decision_stack.is_assigned(index))
+                    .unwrap()
+            })
         };
         let decision_value = self.decision_stack.get_value(decision_variable);
-        self.assignment_queue.push(
-            Literal::new(decision_variable, decision_value),
-            Reason::Decision,
-            f64::INFINITY,
-            0,
-        );
+        self.push_decision(Literal::new(decision_variable, decision_value));
         // self.propagate();
         // return self.state();
     }
 
-    pub fn backjump(&mut self, backjump_level: usize) -> PBState {
-        assert!(backjump_level < self.decision_stack.decision_level());
-        self.integer_linear_constraint_theory
-            .backjump(backjump_level, &self.decision_stack);
-        self.count_constraint_theory
-            .backjump(backjump_level, &self.decision_stack);
-        self.monadic_clause_theory
-            .backjump(backjump_level, &self.decision_stack);
+    /// literal を決定として割り当てる (伝播は propagate で行う)
+    ///
+    /// literal は未割り当てでなければならない．既に偽であれば tagged_core_of_false で原因を得られる
+    pub fn assume(&mut self, literal: Literal) {
+        assert!(self.state.is_noconflict());
+        assert!(!self.decision_stack.is_assigned(literal.index()));
+        debug_assert!(self.assignment_queue.is_empty());
+        self.assumptions.insert(literal.index(), literal.value());
+        self.push_decision(literal);
+    }
+
+    fn push_decision(&mut self, literal: Literal) {
+        self.record(|| DecisionEvent::Decide(literal));
+        self.assignment_queue
+            .push(literal, Reason::Decision, f64::INFINITY, 0);
+    }
 
+    pub fn backjump(&mut self, backjump_level: usize) -> PBState {
+        assert!(backjump_level < self.decision_stack.decision_level());
+        self.record(|| DecisionEvent::Backjump(backjump_level));
+
+        // 学習制約の削除は各理論の backjump で行われるため，先に参照を解除する
         for order in self.decision_stack.order_range(backjump_level).end
             ..self.decision_stack.number_of_assignments()
         {
-            self.activities
-                .push_unassigned_variable(self.decision_stack.get_assignment(order).index());
+            let index = self.decision_stack.get_assignment(order).index();
+            self.activities.push_unassigned_variable(index);
+            self.assumptions.remove(index);
+            if let Some(Reason::Propagation { explain_key }) = self.decision_stack.get_reason(index)
+            {
+                self.unlock(explain_key);
+            }
         }
 
+        if let Some(theory) = &mut self.integer_linear_constraint_theory {
+            theory.backjump(backjump_level, &self.decision_stack);
+        }
+        self.count_constraint_theory
+            .backjump(backjump_level, &self.decision_stack);
+        self.log_deleted_clauses();
+        self.monadic_clause_theory
+            .backjump(backjump_level, &self.decision_stack);
+
         self.decision_stack.backjump(backjump_level);
         self.state = PBState::Noconflict;
         return self.state();
     }
 
+    // 決定レベル 0 まで戻る．既に決定レベル 0 であれば PBState::Interrupted を解除する以外は何もしない
+    pub fn reset_to_root(&mut self) {
+        if self.decision_stack.decision_level() != 0 {
+            self.backjump(0);
+        } else if self.state.is_interrupted() {
+            self.state = PBState::Noconflict;
+        }
+    }
+
+    /// リスタートする (決定レベル 0 まで戻り， on_restart で登録した関数を呼び出す)
+    pub fn restart(&mut self) {
+        self.reset_to_root();
+        self.number_of_restarts += 1;
+        let statistics = self.statistics();
+        if let Some(restart_callback) = self.restart_callback.as_mut() {
+            restart_callback(&statistics);
+        }
+    }
+
+    /// restart のたびに，リスタート後の statistics を渡して呼び出す関数を設定する
+    pub fn on_restart(&mut self, restart_callback: impl FnMut(&Statistics) + 'static) {
+        let restart_callback: RestartCallback = Box::new(restart_callback);
+        self.restart_callback = Some(restart_callback);
+    }
+
+    pub fn statistics(&self) -> Statistics {
+        return Statistics {
+            number_of_restarts: self.number_of_restarts,
+            number_of_conflicts: self.number_of_conflicts,
+            number_of_fixed: self.number_of_fixed(),
+            number_of_count_constraints: self.number_of_count_constraints(),
+            number_of_integer_linear_constraints: self.number_of_integer_linear_constraints(),
+            number_of_learnt_constraints: self.number_of_learnt_constraints(),
+            learnt_size_sum: self.learnt_size_sum,
+            number_of_added_learnt_constraints: self.number_of_added_learnt_constraints,
+        };
+    }
+
+    fn lock(&mut self, explain_key: PBExplainKey) {
+        match explain_key {
+            PBExplainKey::MonadicClause(_) => {}
+            PBExplainKey::CountConstraint(explain_key) => {
+                self.count_constraint_theory.lock(explain_key);
+            }
+            PBExplainKey::IntegerLinearConstraint(explain_key) => {
+                self.integer_linear_constraint_theory
+                    .as_mut()
+                    .unwrap()
+                    .lock(explain_key);
+            }
+        }
+    }
+
+    fn unlock(&mut self, explain_key: PBExplainKey) {
+        match explain_key {
+            PBExplainKey::MonadicClause(_) => {}
+            PBExplainKey::CountConstraint(explain_key) => {
+                self.count_constraint_theory.unlock(explain_key);
+            }
+            PBExplainKey::IntegerLinearConstraint(explain_key) => {
+                self.integer_linear_constraint_theory
+                    .as_mut()
+                    .unwrap()
+                    .unlock(explain_key);
+            }
+        }
+    }
+
     pub fn explain(
         &self,
         explain_key: PBExplainKey,
-    ) -> PBConstraint<impl CountConstraintTrait + '_, impl LinearConstraintTrait<Value = u64> + '_>
+    ) -> PBConstraint<impl CountConstraintTrait + '_, impl LinearConstraintTrait<Value = Coefficient> + '_>
     {
         return match explain_key {
             PBExplainKey::MonadicClause(explain_key) => {
@@ -337,71 +1328,2054 @@ impl PBEngine {
             }
             PBExplainKey::IntegerLinearConstraint(explain_key) => {
                 PBConstraint::IntegerLinearConstraint(
-                    self.integer_linear_constraint_theory.explain(explain_key),
+                    self.integer_linear_constraint_theory
+                        .as_ref()
+                        .unwrap()
+                        .explain(explain_key),
                 )
             }
         };
     }
 
+    /// 各理論が保持している (削除されていない) 制約条件と，それが学習制約であるかを列挙する
+    pub fn iter_all_constraints(
+        &self,
+    ) -> impl Iterator<
+        Item = (
+            PBConstraint<impl CountConstraintTrait + '_, impl LinearConstraintTrait<Value = Coefficient> + '_>,
+            bool,
+        ),
+    > + '_ {
+        let monadic_clauses = self
+            .monadic_clause_theory
+            .iter_constraints()
+            .map(|(monadic_clause, is_learnt)| {
+                (PBConstraint::MonadicClause(monadic_clause), is_learnt)
+            });
+        let count_constraints = self
+            .count_constraint_theory
+            .iter_constraints()
+            .map(|(count_constraint, is_learnt)| {
+                (PBConstraint::CountConstraint(count_constraint), is_learnt)
+            });
+        let integer_linear_constraints = self
+            .integer_linear_constraint_theory
+            .iter()
+            .flat_map(|theory| theory.iter_constraints())
+            .map(|(integer_linear_constraint, is_learnt)| {
+                (
+                    PBConstraint::IntegerLinearConstraint(integer_linear_constraint),
+                    is_learnt,
+                )
+            });
+        return monadic_clauses
+            .chain(count_constraints)
+            .chain(integer_linear_constraints);
+    }
+
+    /// 個数制約と線形制約の余裕 (左辺値の上界 - lower) のヒストグラム
+    ///
+    /// 余裕が 0 の行を 0 番目に，余裕が [2^(k-1), 2^k) の行を k 番目に数える．
+    /// 伝播が完了した状態で呼び出すこと
+    pub fn constraint_slack_histogram(&self) -> Vec<usize> {
+        let mut histogram = Vec::default();
+        let slacks = self.count_constraint_theory.iter_slacks(&self.decision_stack).chain(
+            self.integer_linear_constraint_theory
+                .iter()
+                .flat_map(|theory| theory.iter_slacks()),
+        );
+        for slack in slacks {
+            let bucket = (Coefficient::BITS - slack.leading_zeros()) as usize;
+            if bucket >= histogram.len() {
+                histogram.resize(bucket + 1, 0);
+            }
+            histogram[bucket] += 1;
+        }
+        return histogram;
+    }
+
+    /// 元の制約条件 (学習制約以外) を [{"terms": [[literal, coefficient], ...], "lower": lower}, ...]
+    /// の JSON として書き出す
+    ///
+    /// リテラルは Literal::to_dimacs の整数で表し，節と個数制約の係数は 1 とする
+    pub fn export_constraints_json(&self, out: &mut impl Write) -> std::io::Result<()> {
+        write!(out, "[")?;
+        let original_constraints = self
+            .iter_all_constraints()
+            .filter(|(_, is_learnt)| !is_learnt)
+            .map(|(constraint, _)| constraint);
+        for (position, constraint) in original_constraints.enumerate() {
+            if position != 0 {
+                write!(out, ",")?;
+            }
+            write!(out, "{{\"terms\":[")?;
+            for (k, (literal, coefficient)) in constraint.iter_terms().enumerate() {
+                if k != 0 {
+                    write!(out, ",")?;
+                }
+                write!(out, "[{},{}]", literal.to_dimacs(), coefficient)?;
+            }
+            write!(out, "],\"lower\":{}}}", constraint.lower())?;
+        }
+        writeln!(out, "]")?;
+        return Ok(());
+    }
+
+    /// 削除されていない学習制約を全て LinearConstraint として複製する
+    ///
+    /// 節と個数制約の係数は 1 とする．エンジンの状態は変更しない (学習制約は削除されない)
+    pub fn learnt_constraints(&self) -> Vec<LinearConstraint<Coefficient>> {
+        return self
+            .iter_all_constraints()
+            .filter(|(_, is_learnt)| *is_learnt)
+            .map(|(constraint, _)| LinearConstraint::new(constraint.iter_terms(), constraint.lower()))
+            .collect();
+    }
+
+    /// 他の PBEngine の learnt_constraints で得た学習制約を，学習制約として追加する (並列探索での共有用)
+    ///
+    /// 決定レベル 0 でのみ呼び出せる．未知の変数を含むものと決定レベル 0 で充足されているものは追加せず，
+    /// それ以外は決定レベル 0 で割り当てられた変数を除いてから学習制約と同様に強化して追加する．
+    /// 伝播は行わないため，追加後に propagate を呼び出す．追加した制約条件の数を返し，決定レベル 0 で
+    /// 違反しているもの (元の制約条件が充足不能であることを意味する) があれば
+    /// Err(ConstraintError::Infeasible) を返す
+    pub fn import_learnt(
+        &mut self,
+        constraints: &[LinearConstraint<Coefficient>],
+    ) -> Result<usize, ConstraintError> {
+        assert!(self.decision_stack.decision_level() == 0);
+        let number_of_variables = self.decision_stack.number_of_variables();
+        let mut number_of_imported = 0;
+        for constraint in constraints {
+            if constraint
+                .iter_terms()
+                .any(|(literal, _)| literal.index() >= number_of_variables)
+            {
+                continue;
+            }
+            let fixed_sum: u128 = constraint
+                .iter_terms()
+                .filter(|&(literal, _)| self.decision_stack.is_true(literal))
+                .map(|(_, coefficient)| coefficient as u128)
+                .sum();
+            if fixed_sum >= constraint.lower() as u128 {
+                continue;
+            }
+            let unassigned = LinearConstraint::new(
+                constraint
+                    .iter_terms()
+                    .filter(|&(literal, _)| !self.decision_stack.is_assigned(literal.index())),
+                constraint.lower() - fixed_sum as Coefficient,
+            );
+            let strengthened = strengthen_integer_linear_constraint(&unassigned);
+            let constraint = LinearConstraint::new(strengthened.iter_terms(), strengthened.lower());
+            if constraint.iter_terms().all(|(_, coefficient)| coefficient == 1) {
+                if constraint.len() == constraint.lower() as usize {
+                    for (literal, _) in constraint.iter_terms() {
+                        self.add_monadic_clause(MonadicClause { literal }, true)?;
+                    }
+                } else {
+                    self.add_count_constraint(
+                        CountConstraintView::new(
+                            constraint.iter_terms().map(|(literal, _)| literal),
+                            constraint.lower(),
+                        ),
+                        true,
+                    )?;
+                }
+            } else {
+                self.add_integer_linear_constraint(constraint, true)?;
+            }
+            number_of_imported += 1;
+        }
+        return Ok(number_of_imported);
+    }
+
+    /// explain_key に対応する制約条件の項を PBConstraint を介さずに走査する
+    pub fn explain_terms(
+        &self,
+        explain_key: PBExplainKey,
+    ) -> impl Iterator<Item = (Literal, Coefficient)> + Clone + '_ {
+        return match explain_key {
+            PBExplainKey::MonadicClause(explain_key) => Either::Left(Either::Left(
+                [(self.monadic_clause_theory.explain(explain_key).literal, 1)].into_iter(),
+            )),
+            PBExplainKey::CountConstraint(explain_key) => Either::Left(Either::Right(
+                self.count_constraint_theory
+                    .explain_terms(explain_key)
+                    .map(|literal| (literal, 1)),
+            )),
+            PBExplainKey::IntegerLinearConstraint(explain_key) => Either::Right(
+                self.integer_linear_constraint_theory
+                    .as_ref()
+                    .unwrap()
+                    .explain_terms(explain_key),
+            ),
+        };
+    }
+
+    /// explain_key に対応する制約条件を target に書き込む
+    ///
+    /// 理論ごとに分岐するのは 1 回のみで，書き込んだ後は項ごとの分岐なしに走査できる
+    pub fn explain_into(&self, explain_key: PBExplainKey, target: &mut LinearConstraint<Coefficient>) {
+        match explain_key {
+            PBExplainKey::MonadicClause(explain_key) => {
+                let monadic_clause = self.monadic_clause_theory.explain(explain_key);
+                target.replace(LinearConstraintView::new(
+                    [(monadic_clause.literal, 1)].into_iter(),
+                    1,
+                ));
+            }
+            PBExplainKey::CountConstraint(explain_key) => {
+                target.replace(LinearConstraintView::new(
+                    self.count_constraint_theory
+                        .explain_terms(explain_key)
+                        .map(|literal| (literal, 1)),
+                    self.count_constraint_theory.explain_lower(explain_key),
+                ));
+            }
+            PBExplainKey::IntegerLinearConstraint(explain_key) => {
+                let theory = self.integer_linear_constraint_theory.as_ref().unwrap();
+                target.replace(LinearConstraintView::new(
+                    theory.explain_terms(explain_key),
+                    theory.explain_lower(explain_key),
+                ));
+            }
+        }
+    }
+
+    /// explain_key に対応する制約条件の右辺値
+    pub fn explain_lower(&self, explain_key: PBExplainKey) -> Coefficient {
+        return match explain_key {
+            PBExplainKey::MonadicClause(_) => 1,
+            PBExplainKey::CountConstraint(explain_key) => {
+                self.count_constraint_theory.explain_lower(explain_key)
+            }
+            PBExplainKey::IntegerLinearConstraint(explain_key) => self
+                .integer_linear_constraint_theory
+                .as_ref()
+                .unwrap()
+                .explain_lower(explain_key),
+        };
+    }
+
+    // order の時点で偽であったリテラルの否定が，explain_key による変数 index への伝播の前提となる
+    fn antecedents(&self, explain_key: PBExplainKey, index: usize, order: usize) -> Vec<Literal> {
+        return self
+            .explain_terms(explain_key)
+            .map(|(literal, _)| literal)
+            .filter(|&literal| {
+                literal.index() != index && self.decision_stack.is_false_at(literal, order)
+            })
+            .map(|literal| !literal)
+            .collect();
+    }
+
+    /// 現在の矛盾の原因となった，assume した選択リテラルのタグを昇順に返す
+    ///
+    /// 矛盾状態でのみ呼び出せる．矛盾が選択リテラルの assume 以外の決定にも依存する場合は None を返す
+    pub fn tagged_core(&self) -> Option<Vec<u32>> {
+        let graph = self.export_implication_graph(self.state);
+        return self.tags_of_decisions(
+            graph
+                .nodes
+                .iter()
+                .filter(|(_, node)| node.explain_key.is_none())
+                .map(|(_, node)| node.literal),
+        );
+    }
+
+    /// 偽である選択リテラル literal を assume できない原因となった，assume した選択リテラルのタグを
+    /// literal 自身のタグとあわせて昇順に返す
+    ///
+    /// literal が偽であることが選択リテラルの assume 以外の決定にも依存する場合は None を返す
+    pub fn tagged_core_of_false(&self, literal: Literal) -> Option<Vec<u32>> {
+        assert!(self.decision_stack.is_false(literal));
+        let tag = *self.selector_tags.get(literal.index()).unwrap();
+        let mut decisions = Vec::default();
+        let mut visited = Map::default();
+        let mut stack = vec![!literal];
+        while let Some(literal) = stack.pop() {
+            if visited.contains_key(literal.index()) {
+                continue;
+            }
+            visited.insert(literal.index(), ());
+            match self.decision_stack.get_reason(literal.index()).unwrap() {
+                Reason::Decision => decisions.push(literal),
+                Reason::Propagation { explain_key } => {
+                    let order = self.decision_stack.get_assignment_order(literal.index());
+                    stack.extend(self.antecedents(explain_key, literal.index(), order));
+                }
+            }
+        }
+        let mut tags = self.tags_of_decisions(decisions.into_iter())?;
+        tags.push(tag);
+        tags.sort_unstable();
+        tags.dedup();
+        return Some(tags);
+    }
+
+    // assume した選択リテラルのタグを重複なく昇順に並べる．それ以外の決定が含まれていれば None を返す
+    fn tags_of_decisions(&self, literals: impl Iterator<Item = Literal>) -> Option<Vec<u32>> {
+        let mut tags = Vec::default();
+        for literal in literals {
+            if literal.value() != Boolean::TRUE
+                || self.assumptions.get(literal.index()) != Some(&literal.value())
+            {
+                return None;
+            }
+            tags.push(*self.selector_tags.get(literal.index())?);
+        }
+        tags.sort_unstable();
+        tags.dedup();
+        return Some(tags);
+    }
+
+    /// 矛盾を導いた 2 つの制約条件から，理由を決定まで遡って含意グラフを構築する
+    ///
+    /// 矛盾状態でのみ呼び出せる
+    pub fn export_implication_graph(&self, conflict: PBState) -> ImplicationGraph {
+        let PBState::Conflict {
+            index,
+            explain_keys,
+        } = conflict
+        else {
+            panic!("export_implication_graph requires the conflict state");
+        };
+        assert!(self.state.is_conflict());
+
+        let conflict = explain_keys.map(|explain_key| {
+            let literal = self
+                .explain_terms(explain_key)
+                .map(|(literal, _)| literal)
+                .find(|literal| literal.index() == index)
+                .unwrap();
+            ImplicationGraphNode {
+                literal,
+                decision_level: self.decision_stack.decision_level(),
+                explain_key: Some(explain_key),
+                antecedents: self.antecedents(explain_key, index, usize::MAX),
+            }
+        });
+
+        // 前提を深さ優先で辿る
+        let mut stack: Vec<Literal> = conflict
+            .iter()
+            .flat_map(|node| node.antecedents.iter().copied())
+            .collect();
+        let mut nodes = Map::default();
+        while let Some(literal) = stack.pop() {
+            if nodes.contains_key(literal.index()) {
+                continue;
+            }
+            let order = self.decision_stack.get_assignment_order(literal.index());
+            let explain_key = match self.decision_stack.get_reason(literal.index()).unwrap() {
+                Reason::Decision => None,
+                Reason::Propagation { explain_key } => Some(explain_key),
+            };
+            let node = ImplicationGraphNode {
+                literal,
+                decision_level: self.decision_stack.get_decision_level(literal.index()),
+                explain_key,
+                antecedents: explain_key.map_or_else(Vec::default, |explain_key| {
+                    self.antecedents(explain_key, literal.index(), order)
+                }),
+            };
+            stack.extend(node.antecedents.iter().copied());
+            nodes.insert(literal.index(), (order, node));
+        }
+
+        // 割り当て順に並べる
+        let mut ordered_nodes: Vec<_> = nodes.iter().map(|(_, node)| node.clone()).collect();
+        ordered_nodes.sort_unstable_by_key(|(order, _)| *order);
+        let mut nodes = Map::default();
+        for (_, node) in ordered_nodes {
+            nodes.insert(node.literal.index(), node);
+        }
+        return ImplicationGraph { nodes, conflict };
+    }
+
     pub fn propagate(&mut self) -> PBState {
+        if self.state.is_interrupted() {
+            return self.state;
+        }
         debug_assert!(self.state.is_noconflict());
+        self.record(|| DecisionEvent::Propagate);
+        while self.assign_next().is_some() {}
+        // 割り当て待ちのリテラルを残さないよう，伝播を終えてからフラグを確認する
+        if self.state.is_noconflict() && self.is_cancelled() {
+            self.state = PBState::Interrupted;
+        }
+
+        if self.invariant_checks {
+            self.count_constraint_theory
+                .check_invariants(&self.decision_stack);
+            if let Some(theory) = &self.integer_linear_constraint_theory {
+                theory.check_invariants(&self.decision_stack);
+            }
+        }
+
+        return self.state;
+    }
+
+    /// 割り当てを 1 つだけ取り出して実行し，割り当てたリテラルとその理由を返す
+    ///
+    /// 伝播が完了したか矛盾が発生した場合は None を返す．その後は propagate と同じ状態になる
+    #[inline(always)]
+    pub fn propagate_step(&mut self) -> Option<(Literal, Reason<PBExplainKey>)> {
+        self.record(|| DecisionEvent::PropagateStep);
+        return self.assign_next();
+    }
+
+    #[inline(always)]
+    fn assign_next(&mut self) -> Option<(Literal, Reason<PBExplainKey>)> {
+        debug_assert!(self.state.is_noconflict());
+        if let Some((index, reasons)) = self.assignment_queue.peek_conflict() {
+            let explain_keys = reasons.map(|reason| {
+                if let Reason::Propagation { explain_key } = reason {
+                    explain_key
+                } else {
+                    unreachable!()
+                }
+            });
+            self.state = PBState::Conflict {
+                index,
+                explain_keys,
+            };
+            self.number_of_conflicts += 1;
+            self.assignment_queue.clear();
+            return None;
+        }
+        let Some((literal, reason)) = self.assignment_queue.pop_assignment() else {
+            self.assignment_queue.clear();
+            return None;
+        };
+
+        self.decision_stack.assign(literal, reason);
+        if let Reason::Propagation { explain_key } = reason {
+            self.lock(explain_key);
+            self.propagation_counts[match explain_key {
+                PBExplainKey::MonadicClause(_) => 0,
+                PBExplainKey::CountConstraint(_) => 1,
+                PBExplainKey::IntegerLinearConstraint(_) => 2,
+            }] += 1;
+        }
+
+        self.monadic_clause_theory
+            .assign(&self.decision_stack, |propagation| {
+                self.assignment_queue.push(
+                    propagation.literal,
+                    Reason::Propagation {
+                        explain_key: propagation.explain_key.into(),
+                    },
+                    self.activities.activity(propagation.literal.index()),
+                    propagation.plbd,
+                );
+            });
+        self.count_constraint_theory
+            .assign(&self.decision_stack, |propagation| {
+                self.assignment_queue.push(
+                    propagation.literal,
+                    Reason::Propagation {
+                        explain_key: propagation.explain_key.into(),
+                    },
+                    self.activities.activity(propagation.literal.index()),
+                    propagation.plbd,
+                );
+            });
+        if let Some(theory) = &mut self.integer_linear_constraint_theory {
+            theory.assign(&self.decision_stack, |propagation| {
+                self.assignment_queue.push(
+                    propagation.literal,
+                    Reason::Propagation {
+                        explain_key: propagation.explain_key.into(),
+                    },
+                    self.activities.activity(propagation.literal.index()),
+                    propagation.plbd,
+                )
+            });
+        }
+
+        return Some((literal, reason));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        ConstraintOrigin, DecisionEvent, DecisionTrace, PBEngine, PBExplainKey, PBState,
+        RandomBranching, Reason, StaticOrderBranching,
+    };
+    use crate::{LinearConstraintTrait, ReduceDbCriterion};
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    };
+    use crate::{
+        Analyze, AnalyzeResult, Boolean, Coefficient, ConstraintError, CountConstraintView,
+        LinearConstraint, Literal, MonadicClause,
+    };
+
+    #[test]
+    fn test_cardinality_only() {
+        let mut pb_engine = PBEngine::new_cardinality_only(10.0);
+        for _ in 0..3 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+
+        // x0 + x1 + x2 >= 2 と ¬x0 から x1, x2 が伝播する
+        pb_engine
+            .add_integer_linear_constraint(
+                LinearConstraint::new(
+                    (0..3).map(|index| (Literal::new(index, Boolean::TRUE), 1)),
+                    2,
+                ),
+                false,
+            )
+            .unwrap();
+        pb_engine
+            .add_monadic_clause(
+                MonadicClause {
+                    literal: Literal::new(0, Boolean::FALSE),
+                },
+                false,
+            )
+            .unwrap();
+        assert!(pb_engine.propagate().is_noconflict());
+        assert!(pb_engine.number_of_assignments() == 3);
+        assert!(pb_engine.is_true(Literal::new(1, Boolean::TRUE)));
+        assert!(pb_engine.is_true(Literal::new(2, Boolean::TRUE)));
+
+        assert!(pb_engine.is_cardinality_only());
+        assert!(pb_engine.number_of_count_constraints() == 1);
+        assert!(pb_engine.number_of_integer_linear_constraints() == 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_cardinality_only_rejects_non_unit_coefficient() {
+        let mut pb_engine = PBEngine::new_cardinality_only(10.0);
+        for _ in 0..2 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        pb_engine
+            .add_integer_linear_constraint(
+                LinearConstraint::new(
+                    [
+                        (Literal::new(0, Boolean::TRUE), 2),
+                        (Literal::new(1, Boolean::TRUE), 1),
+                    ]
+                    .into_iter(),
+                    2,
+                ),
+                false,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_is_propagation_complete() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..2 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        assert!(pb_engine.is_propagation_complete());
+        assert!(pb_engine.has_unassigned());
+
+        // 追加した単位節は propagate を呼ぶまで伝播待ちとなる
+        pb_engine
+            .add_monadic_clause(
+                MonadicClause {
+                    literal: Literal::new(0, Boolean::TRUE),
+                },
+                false,
+            )
+            .unwrap();
+        assert!(!pb_engine.is_propagation_complete());
+        pb_engine.propagate();
+        assert!(pb_engine.is_propagation_complete());
+        assert!(pb_engine.has_unassigned());
+
+        pb_engine.decide();
+        pb_engine.propagate();
+        assert!(pb_engine.is_propagation_complete());
+        assert!(!pb_engine.has_unassigned());
+    }
+
+    #[test]
+    fn test_export_implication_graph() {
+        let mut pb_engine = PBEngine::new(10.0);
+        pb_engine.add_variable_with_initial_value(Boolean::TRUE, 1.0);
+        pb_engine.add_variable_with_initial_value(Boolean::TRUE, 0.0);
+        pb_engine.add_variable_with_initial_value(Boolean::TRUE, 0.0);
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+
+        // x0 -> x1, x1 -> x2, x1 -> ¬x2
+        for literals in [[!x(0), x(1)], [!x(1), x(2)], [!x(1), !x(2)]] {
+            pb_engine
+                .add_count_constraint(CountConstraintView::new(literals.into_iter(), 1), false)
+                .unwrap();
+        }
+        assert!(pb_engine.propagate().is_noconflict());
+        pb_engine.decide();
+        let state = pb_engine.propagate();
+        assert!(state.conflict_variable() == Some(2));
+
+        let implication_graph = pb_engine.export_implication_graph(state);
+        assert!(implication_graph.nodes.len() == 2);
+        let decision = implication_graph.nodes.get(0).unwrap();
+        assert!(decision.literal == x(0));
+        assert!(decision.explain_key.is_none());
+        let propagation = implication_graph.nodes.get(1).unwrap();
+        assert!(propagation.literal == x(1));
+        assert!(propagation.antecedents == vec![x(0)]);
+        for node in implication_graph.conflict.iter() {
+            assert!(node.antecedents == vec![x(1)]);
+        }
+        assert!(implication_graph.conflict[0].literal == !implication_graph.conflict[1].literal);
+
+        let dot = implication_graph.to_string();
+        assert!(dot.starts_with("digraph implication_graph {"));
+        assert!(dot.contains("\"l_0\" -> \"l_1\""));
+        assert!(dot.contains("\"l_1\" -> \"conflict\""));
+    }
+
+    #[test]
+    fn test_propagate_step() {
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        // x0 と x0 -> x1 -> x2 -> x3 の連鎖
+        let build = || {
+            let mut pb_engine = PBEngine::new(10.0);
+            for _ in 0..4 {
+                pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+            }
+            for index in 0..3 {
+                pb_engine
+                    .add_count_constraint(
+                        CountConstraintView::new([!x(index), x(index + 1)].into_iter(), 1),
+                        false,
+                    )
+                    .unwrap();
+            }
+            pb_engine
+                .add_monadic_clause(MonadicClause { literal: x(0) }, false)
+                .unwrap();
+            return pb_engine;
+        };
+
+        let mut stepped = build();
+        let mut steps = Vec::default();
+        while let Some((literal, reason)) = stepped.propagate_step() {
+            assert!(reason.is_propagation());
+            steps.push(literal);
+        }
+        assert!(steps == (0..4).map(x).collect::<Vec<_>>());
+        assert!(stepped.state().is_noconflict());
+        assert!(stepped.propagate_step().is_none());
+
+        let mut propagated = build();
+        assert!(propagated.propagate().is_noconflict());
+        assert!(stepped.number_of_assignments() == propagated.number_of_assignments());
+        for order in 0..propagated.number_of_assignments() {
+            assert!(stepped.get_assignment(order) == propagated.get_assignment(order));
+        }
+
+        // 続けて propagate を呼び出せる
+        assert!(stepped.propagate().is_noconflict());
+
+        // 矛盾が発生すると None を返し，矛盾状態になる
+        let mut conflicting = build();
+        conflicting
+            .add_count_constraint(
+                CountConstraintView::new([!x(2), !x(3)].into_iter(), 1),
+                false,
+            )
+            .unwrap();
+        while conflicting.propagate_step().is_some() {}
+        assert!(conflicting.state().conflict_variable() == Some(3));
+    }
+
+    #[test]
+    fn test_iter_all_constraints() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..4 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        pb_engine.add_monadic_clause(MonadicClause { literal: x(0) }, false).unwrap();
+        pb_engine
+            .add_count_constraint(CountConstraintView::new([x(1), x(2), x(3)].into_iter(), 2), false)
+            .unwrap();
+        pb_engine
+            .add_integer_linear_constraint(
+                LinearConstraint::new([(x(1), 3), (x(2), 2), (x(3), 1)].into_iter(), 3),
+                false,
+            )
+            .unwrap();
+        pb_engine
+            .add_count_constraint(CountConstraintView::new([x(1), !x(3)].into_iter(), 1), true)
+            .unwrap();
+        pb_engine
+            .add_integer_linear_constraint(
+                LinearConstraint::new([(x(1), 2), (x(2), 1), (x(3), 1)].into_iter(), 2),
+                true,
+            )
+            .unwrap();
+
+        let number_of_original = pb_engine
+            .iter_all_constraints()
+            .filter(|(_, is_learnt)| !is_learnt)
+            .count();
+        let number_of_learnt = pb_engine
+            .iter_all_constraints()
+            .filter(|(_, is_learnt)| *is_learnt)
+            .count();
+        assert!(number_of_original == 3);
+        assert!(number_of_learnt == 2);
+        let lowers = Vec::from_iter(
+            pb_engine
+                .iter_all_constraints()
+                .map(|(constraint, _)| constraint.lower()),
+        );
+        assert!(lowers == vec![1, 2, 1, 3, 2]);
+    }
+
+    #[test]
+    fn test_seed_activities_by_occurrence() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..4 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        // x2 が最も多くの制約条件に現れる
+        for literals in [[x(0), x(2)], [x(1), !x(2)], [x(2), x(3)], [x(0), x(1)]] {
+            pb_engine
+                .add_count_constraint(CountConstraintView::new(literals.into_iter(), 1), false)
+                .unwrap();
+        }
+        pb_engine.seed_activities_by_occurrence();
+        assert!(pb_engine.activity(2) == 1.0);
+        assert!(pb_engine.activity(3) == 1.0 / 3.0);
+
+        assert!(pb_engine.propagate().is_noconflict());
+        pb_engine.decide();
+        pb_engine.propagate();
+        assert!(pb_engine.get_assignment(0).index() == 2);
+    }
+
+    #[test]
+    fn test_add_tautological_constraint() {
+        let mut pb_engine = PBEngine::new_cardinality_only(10.0);
+        for _ in 0..2 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        // x0 ∨ ¬x0 ∨ x1 と，整数線形制約として与えた x0 ∨ ¬x0 は追加されない
+        pb_engine
+            .add_count_constraint(CountConstraintView::new([x(0), !x(0), x(1)].into_iter(), 1), false)
+            .unwrap();
+        pb_engine
+            .add_integer_linear_constraint(
+                LinearConstraint::new([(x(0), 1), (!x(0), 1)].into_iter(), 1),
+                false,
+            )
+            .unwrap();
+        assert!(pb_engine.number_of_count_constraints() == 0);
+    }
+
+    #[test]
+    fn test_theory_propagation_counts() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..4 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        // x0 が単項節，x1 が個数制約，x2 が整数線形制約により伝播する
+        pb_engine.add_monadic_clause(MonadicClause { literal: x(0) }, false).unwrap();
+        pb_engine
+            .add_count_constraint(CountConstraintView::new([!x(0), x(1)].into_iter(), 1), false)
+            .unwrap();
+        pb_engine
+            .add_integer_linear_constraint(
+                LinearConstraint::new([(!x(1), 2), (x(2), 2), (x(3), 1)].into_iter(), 2),
+                false,
+            )
+            .unwrap();
+        assert!(pb_engine.propagate().is_noconflict());
+        assert!(pb_engine.is_true(x(2)) && !pb_engine.is_assigned(3));
+        assert!(pb_engine.theory_propagation_counts() == [1, 1, 1]);
+
+        pb_engine.reset_theory_propagation_counts();
+        assert!(pb_engine.theory_propagation_counts() == [0; 3]);
+    }
+
+    #[test]
+    fn test_replay() {
+        // ランダムな決定とリスタートを含む探索を記録する
+        let mut pb_engine = PBEngine::new(10.0);
+        pb_engine.set_branching_heuristic(Some(Box::new(RandomBranching::new(7))));
+        pb_engine.start_recording();
+        for _ in 0..20 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let mut state: u64 = 88172645463325252;
+        let mut random = |upper: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            return (state % upper) as usize;
+        };
+        for _ in 0..86 {
+            let literals: Vec<_> = (0..3)
+                .map(|_| Literal::new(random(20), Boolean::from(random(2) == 0)))
+                .collect();
+            let _ = pb_engine.add_clause(literals.into_iter(), false);
+        }
+        let mut analyze = Analyze::new(1e-10);
+        let mut number_of_conflicts = 0;
         loop {
-            if let Some((index, reasons)) = self.assignment_queue.pop_conflict() {
-                let explain_keys = reasons.map(|reason| {
-                    if let Reason::Propagation { explain_key } = reason {
-                        explain_key
-                    } else {
-                        unreachable!()
+            let PBState::Conflict {
+                index,
+                explain_keys,
+            } = pb_engine.propagate()
+            else {
+                if pb_engine.number_of_assignments() == pb_engine.number_of_variables() {
+                    break;
+                }
+                pb_engine.decide();
+                continue;
+            };
+            if pb_engine.decision_level() == 0 {
+                break;
+            }
+            number_of_conflicts += 1;
+            pb_engine.update_assignment_probabilities();
+            let AnalyzeResult::Backjumpable {
+                backjump_level,
+                learnt_constraint,
+                conflicting_assignments,
+                ..
+            } = analyze.call(index, explain_keys, &pb_engine)
+            else {
+                break;
+            };
+            let learnt_constraint =
+                LinearConstraint::new(learnt_constraint.iter_terms(), learnt_constraint.lower());
+            let conflicting_assignments: Vec<_> = conflicting_assignments.collect();
+            pb_engine
+                .update_conflict_probabilities(conflicting_assignments.into_iter(), backjump_level);
+            pb_engine.backjump(backjump_level);
+            pb_engine
+                .add_integer_linear_constraint(learnt_constraint, true)
+                .unwrap();
+            if number_of_conflicts % 3 == 0 {
+                pb_engine.reset_to_root();
+            }
+        }
+        assert!(number_of_conflicts > 0);
+        let trace = pb_engine.take_recording();
+
+        // 文字列形式を経由して新しいエンジンで再現すると同じ状態になる
+        let trace: DecisionTrace = trace.to_string().parse().unwrap();
+        let mut replayed_engine = PBEngine::new(10.0);
+        assert!(replayed_engine.replay(&trace).is_ok());
+        assert!(replayed_engine.state().is_noconflict() == pb_engine.state().is_noconflict());
+        assert!(replayed_engine.number_of_assignments() == pb_engine.number_of_assignments());
+        for order in 0..pb_engine.number_of_assignments() {
+            assert!(replayed_engine.get_assignment(order) == pb_engine.get_assignment(order));
+        }
+        assert!(
+            replayed_engine.number_of_learnt_constraints()
+                == pb_engine.number_of_learnt_constraints()
+        );
+        assert!(
+            pb_engine
+                .iter_variables()
+                .all(|index| replayed_engine.activity(index) == pb_engine.activity(index))
+        );
+
+        // 割り当て済みの変数の決定は再現できない
+        let mut trace = DecisionTrace::default();
+        trace.events.push(DecisionEvent::AddVariable {
+            initial_value: Boolean::FALSE,
+            initial_activity: 0.0,
+        });
+        let x = Literal::new(0, Boolean::TRUE);
+        trace.events.push(DecisionEvent::Decide(x));
+        trace.events.push(DecisionEvent::Propagate);
+        trace.events.push(DecisionEvent::Decide(!x));
+        assert!(PBEngine::new(10.0).replay(&trace) == Err(3));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        // 6 頂点の閉路の頂点被覆 (最小の大きさは 3)
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..6 {
+            pb_engine.add_variable_with_initial_value(Boolean::TRUE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        for index in 0..6 {
+            pb_engine
+                .add_integer_linear_constraint(
+                    LinearConstraint::new([(x(index), 1), (x((index + 1) % 6), 1)].into_iter(), 1),
+                    false,
+                )
+                .unwrap();
+        }
+        pb_engine
+            .add_integer_linear_constraint(
+                LinearConstraint::new([(x(0), 2), (x(2), 1), (x(4), 1)].into_iter(), 2),
+                false,
+            )
+            .unwrap();
+
+        // 解と矛盾の回数を返す
+        let solve = |pb_engine: &mut PBEngine| -> (Option<Vec<Boolean>>, usize) {
+            let mut analyze = Analyze::new(1e-10);
+            let mut number_of_conflicts = 0;
+            loop {
+                if let PBState::Conflict {
+                    index,
+                    explain_keys,
+                } = pb_engine.propagate()
+                {
+                    number_of_conflicts += 1;
+                    if pb_engine.decision_level() == 0 {
+                        return (None, number_of_conflicts);
                     }
-                });
-                self.state = PBState::Conflict {
+                    let AnalyzeResult::Backjumpable {
+                        backjump_level,
+                        learnt_constraint,
+                        ..
+                    } = analyze.call(index, explain_keys, pb_engine)
+                    else {
+                        return (None, number_of_conflicts);
+                    };
+                    let learnt_constraint = LinearConstraint::new(
+                        learnt_constraint.iter_terms(),
+                        learnt_constraint.lower(),
+                    );
+                    pb_engine.backjump(backjump_level);
+                    pb_engine
+                        .add_integer_linear_constraint(learnt_constraint, true)
+                        .unwrap();
+                } else if pb_engine.number_of_assignments() == pb_engine.number_of_variables() {
+                    let solution = pb_engine
+                        .iter_variables()
+                        .map(|index| pb_engine.get_value(index))
+                        .collect();
+                    return (Some(solution), number_of_conflicts);
+                } else {
+                    pb_engine.decide();
+                }
+            }
+        };
+        // 目的関数値が value 未満の解を求める制約条件 (¬x の個数が 6 - value + 1 以上)
+        let bound = |value: usize| {
+            LinearConstraint::new((0..6).map(|index| (!x(index), 1)), (6 - value + 1) as Coefficient)
+        };
+        let objective_value = |solution: &Vec<Boolean>| {
+            solution.iter().filter(|&&value| value == Boolean::TRUE).count()
+        };
+
+        assert!(pb_engine.propagate().is_noconflict());
+        let (solution, _) = solve(&mut pb_engine);
+        let mut value = objective_value(&solution.unwrap());
+        pb_engine.reset_to_root();
+        pb_engine.add_integer_linear_constraint(bound(value), false).unwrap();
+
+        // 同じ目的関数値の上界のもとでは，restore した後も同じように探索する
+        let snapshot = pb_engine.snapshot();
+        let result = solve(&mut pb_engine);
+        pb_engine.restore(&snapshot);
+        assert!(pb_engine.decision_level() == 0);
+        assert!(solve(&mut pb_engine) == result);
+
+        // 最適値 3 に達するまで上界を更新する
+        let mut result = result;
+        while let (Some(solution), _) = result {
+            value = objective_value(&solution);
+            pb_engine.reset_to_root();
+            if pb_engine.add_integer_linear_constraint(bound(value), false).is_err() {
+                break;
+            }
+            result = solve(&mut pb_engine);
+        }
+        assert!(value == 3);
+    }
+
+    #[test]
+    fn test_constraint_falsified_at() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for initial_value in [Boolean::FALSE, Boolean::TRUE, Boolean::FALSE] {
+            pb_engine.add_variable_with_initial_value(initial_value, 0.0);
+        }
+        pb_engine.set_branching_heuristic(Some(Box::new(StaticOrderBranching::new(vec![0, 1, 2]))));
+        for _ in 0..3 {
+            pb_engine.decide();
+            assert!(pb_engine.propagate().is_noconflict());
+        }
+
+        // ¬x0, x1, ¬x2 の順に割り当てると x0 + x1 + x2 >= 2 は x2 の割り当てで違反する
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        let constraint = LinearConstraint::new([(x(0), 1), (x(1), 1), (x(2), 1)].into_iter(), 2);
+        let order = (0..pb_engine.number_of_assignments())
+            .find(|&order| pb_engine.constraint_falsified_at(&constraint, order));
+        assert!(order == Some(2));
+        assert!(pb_engine.constraint_falsified_at(&constraint, usize::MAX));
+        assert!(!pb_engine.constraint_falsified_at(&constraint, 1));
+
+        // 係数の和が Coefficient を超えても桁あふれしない
+        let m = Coefficient::MAX;
+        let constraint = LinearConstraint::new([(x(0), m), (x(1), m), (x(2), m)].into_iter(), m);
+        assert!(!pb_engine.constraint_falsified_at(&constraint, usize::MAX));
+    }
+
+    #[test]
+    fn test_iter_literals() {
+        let mut pb_engine = PBEngine::new(10.0);
+        assert!(pb_engine.iter_literals().count() == 0);
+        for _ in 0..3 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        assert!(pb_engine.iter_variables().count() == 3);
+        assert!(pb_engine.iter_literals().count() == 6);
+        assert!(
+            pb_engine
+                .iter_literals()
+                .all(|literal| pb_engine.iter_literals().filter(|&l| l == !literal).count() == 1)
+        );
+    }
+
+    #[test]
+    fn test_reset_to_root() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..4 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+
+        // 決定レベル 0 で繰り返し呼んでも何も起きない
+        pb_engine.reset_to_root();
+        pb_engine.reset_to_root();
+        assert!(pb_engine.decision_level() == 0);
+
+        pb_engine.add_clause([x(0), x(1), x(2)].into_iter(), true).unwrap();
+        pb_engine.activities.update_activity(3, 1.0);
+        let activity = pb_engine.activity(3);
+        pb_engine.decide();
+        assert!(pb_engine.propagate().is_noconflict());
+        assert!(pb_engine.decision_level() == 1);
+
+        // 学習制約とアクティビティは保たれる
+        pb_engine.reset_to_root();
+        pb_engine.reset_to_root();
+        assert!(pb_engine.decision_level() == 0);
+        assert!(pb_engine.number_of_learnt_constraints() == 1);
+        assert!(pb_engine.activity(3) == activity);
+    }
+
+    #[test]
+    fn test_on_restart() {
+        use std::{cell::RefCell, rc::Rc};
+
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..4 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        pb_engine.add_clause([x(0), x(1)].into_iter(), true).unwrap();
+
+        let history = Rc::new(RefCell::new(Vec::new()));
+        pb_engine.on_restart({
+            let history = history.clone();
+            move |statistics| history.borrow_mut().push(*statistics)
+        });
+
+        // 決定レベル 0 でのリスタートも数える
+        pb_engine.restart();
+        for _ in 0..3 {
+            pb_engine.decide();
+            assert!(pb_engine.propagate().is_noconflict());
+            pb_engine.restart();
+            assert!(pb_engine.decision_level() == 0);
+        }
+
+        let history = history.borrow();
+        assert!(history.len() == 4);
+        for (index, statistics) in history.iter().enumerate() {
+            assert!(statistics.number_of_restarts == index as u64 + 1);
+            assert!(statistics.number_of_learnt_constraints == 1);
+        }
+        assert!(history[3] == pb_engine.statistics());
+    }
+
+    #[test]
+    fn test_avg_learnt_size() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..6 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        assert!(pb_engine.statistics().avg_learnt_size() == 0.0);
+
+        // 元の制約条件は数えない
+        pb_engine.add_clause([x(0), x(1), x(2)].into_iter(), false).unwrap();
+        assert!(pb_engine.statistics().avg_learnt_size() == 0.0);
+
+        pb_engine.add_clause([x(3)].into_iter(), true).unwrap();
+        pb_engine.add_clause([x(0), x(1)].into_iter(), true).unwrap();
+        pb_engine
+            .add_integer_linear_constraint(
+                LinearConstraint::new([(x(1), 2), (x(2), 1), (x(4), 1), (x(5), 1)].into_iter(), 2),
+                true,
+            )
+            .unwrap();
+        pb_engine
+            .add_integer_linear_constraint_presorted(
+                LinearConstraint::new([(x(2), 3), (x(4), 2), (x(5), 1)].into_iter(), 3),
+                true,
+            )
+            .unwrap();
+
+        let statistics = pb_engine.statistics();
+        assert!(statistics.number_of_added_learnt_constraints == 4);
+        assert!(statistics.learnt_size_sum == 1 + 2 + 4 + 3);
+        assert!(statistics.avg_learnt_size() == 2.5);
+    }
+
+    #[test]
+    fn test_add_constraint_checked() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..4 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        // x0 が真になると x1 と !x1 の両方が伝播される
+        pb_engine.add_clause([!x(0), x(1)].into_iter(), false).unwrap();
+        pb_engine.add_clause([!x(0), !x(1)].into_iter(), false).unwrap();
+
+        let state = pb_engine
+            .add_count_constraint_checked(CountConstraintView::new([x(2), x(3)].into_iter(), 2), false)
+            .unwrap();
+        assert!(state.is_noconflict());
+        assert!(pb_engine.is_true(x(2)) && pb_engine.is_true(x(3)));
+
+        let state = pb_engine
+            .add_integer_linear_constraint_checked(
+                LinearConstraint::new([(x(0), 2), (!x(2), 1)].into_iter(), 2),
+                false,
+            )
+            .unwrap();
+        assert!(state.is_conflict());
+        assert!(state.conflict_variable() == Some(1));
+        assert!(pb_engine.state().is_conflict());
+
+        // 違反している制約条件は追加しない
+        assert!(matches!(
+            pb_engine.add_monadic_clause_checked(MonadicClause { literal: !x(3) }, false),
+            Err(ConstraintError::Infeasible)
+        ));
+    }
+
+    #[test]
+    fn test_tagged_core() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..4 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        // x0 と x1 は同時に真にならず， x3 が真であれば x2 と !x2 の両方が伝播される
+        pb_engine.add_clause([!x(0), !x(1)].into_iter(), false).unwrap();
+        pb_engine.add_clause([!x(3), x(2)].into_iter(), false).unwrap();
+        pb_engine.add_clause([!x(3), !x(2)].into_iter(), false).unwrap();
+        let s10 = pb_engine.add_tagged_clause([x(0)].into_iter(), 10).unwrap();
+        let s20 = pb_engine.add_tagged_clause([x(1)].into_iter(), 20).unwrap();
+        let s30 = pb_engine.add_tagged_clause([x(2), x(0)].into_iter(), 30).unwrap();
+        let s40 = pb_engine.add_tagged_clause([x(3)].into_iter(), 40).unwrap();
+
+        pb_engine.assume(s30);
+        assert!(pb_engine.propagate().is_noconflict());
+        pb_engine.assume(s10);
+        assert!(pb_engine.propagate().is_noconflict());
+        // s10 により x1 が偽となり s20 は assume できない
+        assert!(pb_engine.is_false(s20));
+        assert!(pb_engine.tagged_core_of_false(s20) == Some(vec![10, 20]));
+
+        pb_engine.assume(s40);
+        assert!(pb_engine.propagate().is_conflict());
+        assert!(pb_engine.tagged_core() == Some(vec![40]));
+
+        // 選択リテラルでない変数の決定に依存する矛盾からはコアを得られない
+        pb_engine.backjump(0);
+        pb_engine.assume(x(3));
+        assert!(pb_engine.propagate().is_conflict());
+        assert!(pb_engine.tagged_core().is_none());
+    }
+
+    #[test]
+    fn test_max_decision_level() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..6 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        pb_engine.add_clause([x(0), x(1), x(2)].into_iter(), false).unwrap();
+        pb_engine.add_clause([x(3), x(4), x(5)].into_iter(), false).unwrap();
+        pb_engine.set_max_decision_level(Some(2));
+
+        // 上限に達した後の decide は決定を行わずにリスタートする
+        for _ in 0..20 {
+            if !pb_engine.has_unassigned() {
+                break;
+            }
+            pb_engine.decide();
+            assert!(pb_engine.propagate().is_noconflict());
+            assert!(pb_engine.decision_level() <= 2);
+        }
+        assert!(pb_engine.statistics().number_of_restarts > 0);
+    }
+
+    #[test]
+    fn test_import_learnt() {
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        let new_engine = || {
+            let mut pb_engine = PBEngine::new(10.0);
+            for _ in 0..4 {
+                pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+            }
+            pb_engine.add_clause([x(0), x(1)].into_iter(), false).unwrap();
+            pb_engine.add_clause([!x(0), x(1)].into_iter(), false).unwrap();
+            pb_engine.add_clause([x(2), x(3)].into_iter(), false).unwrap();
+            pb_engine.add_monadic_clause(MonadicClause { literal: x(2) }, false).unwrap();
+            assert!(pb_engine.propagate().is_noconflict());
+            return pb_engine;
+        };
+
+        // 元の制約条件から導かれる学習制約
+        let mut source = new_engine();
+        source.add_monadic_clause(MonadicClause { literal: x(1) }, true).unwrap();
+        source
+            .add_integer_linear_constraint(
+                LinearConstraint::new([(x(0), 2), (x(1), 2), (!x(3), 2)].into_iter(), 2),
+                true,
+            )
+            .unwrap();
+        let mut learnt = source.learnt_constraints();
+        // x2 は決定レベル 0 で真であるため充足されている
+        learnt.push(LinearConstraint::new([(x(2), 1), (x(3), 1)].into_iter(), 1));
+        // 未知の変数を含む
+        learnt.push(LinearConstraint::new([(x(1), 1), (x(4), 1)].into_iter(), 1));
+
+        let mut target = new_engine();
+        assert!(target.import_learnt(&learnt) == Ok(2));
+        assert!(target.propagate().is_noconflict());
+        assert!(target.is_true(x(1)) && target.get_decision_level(1) == 0);
+        // 係数は最大公約数で割られて x0 ∨ x1 ∨ !x3 として追加される
+        assert!(target.number_of_learnt_constraints() == 1);
+
+        while target.has_unassigned() {
+            target.decide();
+            assert!(target.propagate().is_noconflict());
+        }
+        let original = [
+            LinearConstraint::new([(x(0), 1), (x(1), 1)].into_iter(), 1),
+            LinearConstraint::new([(!x(0), 1), (x(1), 1)].into_iter(), 1),
+            LinearConstraint::new([(x(2), 1), (x(3), 1)].into_iter(), 1),
+            LinearConstraint::new([(x(2), 1)].into_iter(), 1),
+        ];
+        assert!(target.verify_against(&original).is_ok());
+    }
+
+    #[test]
+    fn test_propagation_level_of() {
+        use crate::theories::TheoryAddConstraintTrait;
+
+        // 決定レベル 1, 2, 3 でそれぞれ x0, x1, x2 に False を決定する
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..5 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        pb_engine.set_decision_order(vec![0, 1, 2]);
+        for _ in 0..3 {
+            pb_engine.decide();
+            assert!(pb_engine.propagate().is_noconflict());
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+
+        // 個数制約の理論が走査して求める決定レベルと一致する
+        let literals = [x(0), x(1), x(3), x(4)];
+        let mut count_constraint_theory = pb_engine.count_constraint_theory.clone();
+        let open_coded_level = count_constraint_theory
+            .add_constraint(
+                CountConstraintView::new(literals.into_iter(), 2),
+                true,
+                &pb_engine.decision_stack,
+                |_| {},
+            )
+            .unwrap_err();
+        let as_linear_constraint =
+            LinearConstraint::new(literals.into_iter().map(|literal| (literal, 1)), 2);
+        assert!(pb_engine.propagation_level_of(&as_linear_constraint) == Some(open_coded_level));
+        assert!(open_coded_level == 2);
+
+        // 2 x0 + 2 x1 + 2 x3 + x4 >= 3 は決定レベル 2 で x3 を伝播する
+        let constraint =
+            LinearConstraint::new([(x(0), 2), (x(1), 2), (x(3), 2), (x(4), 1)].into_iter(), 3);
+        assert!(pb_engine.propagation_level_of(&constraint) == Some(2));
+        let mut integer_linear_constraint_theory =
+            pb_engine.integer_linear_constraint_theory.clone().unwrap();
+        assert!(
+            integer_linear_constraint_theory.add_constraint(
+                &constraint,
+                true,
+                &pb_engine.decision_stack,
+                |_| {}
+            ) == Err(2)
+        );
+
+        // 伝播しない
+        let constraint = LinearConstraint::new([(x(2), 1), (x(3), 1), (x(4), 1)].into_iter(), 1);
+        assert!(pb_engine.propagation_level_of(&constraint).is_none());
+    }
+
+    #[test]
+    fn test_add_integer_linear_constraint_pinned() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..4 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        let pinned = LinearConstraint::new([(x(0), 2), (x(1), 1), (x(2), 1)].into_iter(), 2);
+        pb_engine.add_integer_linear_constraint_pinned(&pinned).unwrap();
+        pb_engine.set_max_learnt(Some(0));
+
+        // 学習制約を追加するたびに削除されるが，固定した制約条件は残る
+        for _ in 0..10 {
+            pb_engine
+                .add_integer_linear_constraint(
+                    LinearConstraint::new([(x(1), 2), (x(2), 1), (x(3), 1)].into_iter(), 2),
+                    true,
+                )
+                .unwrap();
+            assert!(pb_engine.number_of_learnt_constraints() == 1);
+        }
+        let learnt = pb_engine.learnt_constraints();
+        assert!(learnt.len() == 1 && learnt[0].is_equivalent_to(&pinned));
+    }
+
+    #[test]
+    fn test_add_trivial_constraint_pinned() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..3 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        let trivial = LinearConstraint::new([(x(0), 2), (x(1), 1)].into_iter(), 0);
+
+        // 行が 1 つもない状態で右辺値が 0 の制約条件を固定しても行は追加されない
+        pb_engine.add_integer_linear_constraint_pinned(&trivial).unwrap();
+        assert!(pb_engine.number_of_learnt_constraints() == 0);
+        assert!(pb_engine.learnt_constraints().is_empty());
+
+        // 直前に追加した元の制約条件は固定されない
+        pb_engine
+            .add_integer_linear_constraint(
+                LinearConstraint::new([(x(0), 2), (x(1), 1), (x(2), 1)].into_iter(), 2),
+                false,
+            )
+            .unwrap();
+        pb_engine.add_integer_linear_constraint_pinned(&trivial).unwrap();
+        assert!(pb_engine.number_of_learnt_constraints() == 0);
+        assert!(pb_engine.learnt_constraints().is_empty());
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..4 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        pb_engine.add_clause([x(0), x(1)].into_iter(), false).unwrap();
+        let cancellation = Arc::new(AtomicBool::new(false));
+        pb_engine.set_cancellation(cancellation.clone());
+
+        pb_engine.decide();
+        assert!(pb_engine.propagate().is_noconflict());
+        let number_of_assignments = pb_engine.number_of_assignments();
+
+        // フラグを立てると割り当てを行わずに止まる
+        cancellation.store(true, Ordering::Relaxed);
+        pb_engine.decide();
+        assert!(pb_engine.state().is_interrupted());
+        assert!(pb_engine.propagate().is_interrupted());
+        assert!(pb_engine.number_of_assignments() == number_of_assignments);
+
+        // フラグを下ろして決定レベル 0 に戻れば探索を再開できる
+        cancellation.store(false, Ordering::Relaxed);
+        pb_engine.reset_to_root();
+        assert!(pb_engine.state().is_noconflict());
+        assert!(pb_engine.decision_level() == 0);
+        while pb_engine.has_unassigned() {
+            pb_engine.decide();
+            assert!(pb_engine.propagate().is_noconflict());
+        }
+        assert!(pb_engine.is_true(x(0)) || pb_engine.is_true(x(1)));
+    }
+
+    #[test]
+    fn test_export_constraints_json() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..4 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        pb_engine
+            .add_monadic_clause(MonadicClause { literal: !x(3) }, false)
+            .unwrap();
+        pb_engine
+            .add_count_constraint(CountConstraintView::new([x(0), x(1), x(2)].into_iter(), 2), false)
+            .unwrap();
+        pb_engine
+            .add_integer_linear_constraint(
+                LinearConstraint::new([(x(0), 3), (!x(1), 2), (x(2), 1)].into_iter(), 3),
+                false,
+            )
+            .unwrap();
+        // 学習制約は書き出さない
+        pb_engine.add_clause([x(1), x(2)].into_iter(), true).unwrap();
+
+        let mut output = Vec::default();
+        pb_engine.export_constraints_json(&mut output).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        let mut constraints = json
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|constraint| {
+                let mut terms = constraint["terms"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|term| (term[0].as_i64().unwrap(), term[1].as_u64().unwrap()))
+                    .collect::<Vec<_>>();
+                terms.sort();
+                (terms, constraint["lower"].as_u64().unwrap())
+            })
+            .collect::<Vec<_>>();
+        constraints.sort();
+        assert!(
+            constraints
+                == vec![
+                    (vec![(-4, 1)], 1),
+                    (vec![(-2, 2), (1, 3), (3, 1)], 3),
+                    (vec![(1, 1), (2, 1), (3, 1)], 2),
+                ]
+        );
+    }
+
+    #[test]
+    fn test_asserting_literal() {
+        // 学習制約を追加し，backjump 後に割り当てられる最初の位置と伝播するはずのリテラルを返す
+        fn learn(
+            pb_engine: &mut PBEngine,
+            analyze_result: AnalyzeResult<
+                impl LinearConstraintTrait<Value = Coefficient>,
+                impl Iterator<Item = Literal>,
+                impl Iterator<Item = PBExplainKey>,
+            >,
+        ) -> (usize, Literal) {
+            let asserting_literal = analyze_result.asserting_literal().unwrap();
+            let AnalyzeResult::Backjumpable {
+                backjump_level,
+                learnt_constraint,
+                ..
+            } = analyze_result
+            else {
+                unreachable!()
+            };
+            let learnt_constraint =
+                LinearConstraint::new(learnt_constraint.iter_terms(), learnt_constraint.lower());
+            pb_engine.backjump(backjump_level);
+            let order = pb_engine.number_of_assignments();
+            pb_engine
+                .add_integer_linear_constraint(learnt_constraint, true)
+                .unwrap();
+            return (order, asserting_literal);
+        }
+
+        // 3 羽の鳩を 2 つの巣に入れる鳩の巣原理 (充足不能)
+        let p = |pigeon: usize, hole: usize| Literal::new(pigeon * 2 + hole, Boolean::TRUE);
+        for clausal in [false, true] {
+            let mut pb_engine = PBEngine::new(10.0);
+            for _ in 0..6 {
+                pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+            }
+            for pigeon in 0..3 {
+                pb_engine.add_clause([p(pigeon, 0), p(pigeon, 1)].into_iter(), false).unwrap();
+            }
+            for hole in 0..2 {
+                for i in 0..3 {
+                    for j in i + 1..3 {
+                        pb_engine
+                            .add_clause([!p(i, hole), !p(j, hole)].into_iter(), false)
+                            .unwrap();
+                    }
+                }
+            }
+
+            let mut analyze = Analyze::new(1e-10);
+            let mut number_of_learnt_constraints = 0;
+            let mut expected_assignment = None;
+            loop {
+                let state = pb_engine.propagate();
+                // backjump 後の最初の伝播は学習制約が伝播するリテラルの割り当て
+                if let Some((order, asserting_literal)) = expected_assignment.take() {
+                    assert!(pb_engine.get_assignment(order) == asserting_literal);
+                }
+                let PBState::Conflict {
                     index,
                     explain_keys,
+                } = state
+                else {
+                    pb_engine.decide();
+                    continue;
                 };
+                if pb_engine.decision_level() == 0 {
+                    break;
+                }
+                expected_assignment = Some(if clausal {
+                    let analyze_result = analyze.call_clausal(index, explain_keys, &pb_engine);
+                    learn(&mut pb_engine, analyze_result)
+                } else {
+                    let analyze_result = analyze.call(index, explain_keys, &pb_engine);
+                    learn(&mut pb_engine, analyze_result)
+                });
+                number_of_learnt_constraints += 1;
+            }
+            assert!(number_of_learnt_constraints > 0);
+        }
+    }
+
+    #[test]
+    fn test_max_learnt() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..20 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index % 20, Boolean::TRUE);
+        pb_engine.set_reduce_db_criterion(ReduceDbCriterion::Activity);
+        pb_engine.set_max_learnt(Some(5));
+        pb_engine
+            .add_count_constraint(CountConstraintView::new([x(0), x(1)].into_iter(), 1), false)
+            .unwrap();
+
+        // 節と整数線形制約を交互に学習しても，学習制約の数は上限以下に保たれる
+        for index in 0..20 {
+            pb_engine
+                .add_count_constraint(
+                    CountConstraintView::new([x(index), x(index + 1), x(index + 2)].into_iter(), 1),
+                    true,
+                )
+                .unwrap();
+            pb_engine
+                .add_integer_linear_constraint(
+                    LinearConstraint::new(
+                        [(x(index), 2), (x(index + 3), 1), (x(index + 5), 1)].into_iter(),
+                        2,
+                    ),
+                    true,
+                )
+                .unwrap();
+            assert!(pb_engine.number_of_learnt_constraints() <= 5);
+        }
+        let number_of_learnt = pb_engine
+            .iter_all_constraints()
+            .filter(|(_, is_learnt)| *is_learnt)
+            .count();
+        assert!(number_of_learnt == pb_engine.number_of_learnt_constraints());
+        assert!(number_of_learnt > 0);
+        // 元の制約条件は削除されない
+        assert!(
+            pb_engine
+                .iter_all_constraints()
+                .filter(|(_, is_learnt)| !is_learnt)
+                .count()
+                == 1
+        );
+    }
+
+    #[test]
+    fn test_add_clause() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..3 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+
+        // 空節は充足不能
+        assert!(pb_engine.add_clause([].into_iter(), false) == Err(ConstraintError::Infeasible));
+        assert!(pb_engine.number_of_count_constraints() == 0);
+
+        // 重複をまとめると単項節となり，x0 が伝播する
+        pb_engine.add_clause([x(0), x(0)].into_iter(), false).unwrap();
+        assert!(pb_engine.number_of_count_constraints() == 0);
+        assert!(pb_engine.propagate().is_noconflict());
+        assert!(pb_engine.is_true(x(0)));
+
+        // ¬x0 ∨ x1 ∨ x2 は個数制約として追加され，¬x1 のもとで x2 が伝播する
+        pb_engine.add_clause([!x(0), x(1), x(2)].into_iter(), false).unwrap();
+        assert!(pb_engine.number_of_count_constraints() == 1);
+        pb_engine.add_clause([!x(1)].into_iter(), false).unwrap();
+        assert!(pb_engine.propagate().is_noconflict());
+        assert!(pb_engine.is_true(x(2)));
+
+        // 既に違反している節
+        assert!(
+            pb_engine.add_clause([!x(0), x(1)].into_iter(), false)
+                == Err(ConstraintError::Infeasible)
+        );
+    }
+
+    #[test]
+    fn test_constraint_origin() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..5 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        pb_engine.set_original_constraint_index(0);
+        pb_engine.add_clause([x(0), x(1)].into_iter(), false).unwrap();
+        pb_engine.set_original_constraint_index(1);
+        pb_engine
+            .add_integer_linear_constraint(
+                LinearConstraint::new([(!x(1), 2), (x(2), 1), (x(3), 1)].into_iter(), 2),
+                false,
+            )
+            .unwrap();
+        pb_engine.set_original_constraint_index(2);
+        pb_engine.add_monadic_clause(MonadicClause { literal: !x(0) }, false).unwrap();
+        pb_engine.add_clause([x(0), x(4)].into_iter(), true).unwrap();
+        assert!(pb_engine.propagate().is_noconflict());
+
+        // 各割り当ての理由から入力での番号を求める
+        let origin = |index: usize| {
+            let Some(Reason::Propagation { explain_key }) = pb_engine.get_reason(index) else {
+                unreachable!()
+            };
+            pb_engine.constraint_origin(explain_key)
+        };
+        assert!(origin(0) == ConstraintOrigin::Original(2));
+        assert!(origin(1) == ConstraintOrigin::Original(0));
+        assert!(origin(2) == ConstraintOrigin::Original(1));
+        assert!(origin(3) == ConstraintOrigin::Original(1));
+        assert!(origin(4) == ConstraintOrigin::Learnt);
+    }
+
+    #[test]
+    fn test_learnt_constraints() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..3 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        let original = [
+            LinearConstraint::new([(x(0), 1), (x(1), 1)].into_iter(), 1),
+            LinearConstraint::new([(!x(0), 1), (x(2), 1)].into_iter(), 1),
+            LinearConstraint::new([(x(0), 2), (x(1), 1), (x(2), 1)].into_iter(), 2),
+        ];
+        pb_engine
+            .add_count_constraint(CountConstraintView::new([x(0), x(1)].into_iter(), 1), false)
+            .unwrap();
+        pb_engine
+            .add_count_constraint(CountConstraintView::new([!x(0), x(2)].into_iter(), 1), false)
+            .unwrap();
+        pb_engine.add_integer_linear_constraint(&original[2], false).unwrap();
+        // 元の制約条件から導かれる学習制約
+        pb_engine
+            .add_count_constraint(CountConstraintView::new([x(1), x(2)].into_iter(), 1), true)
+            .unwrap();
+        pb_engine
+            .add_integer_linear_constraint(
+                LinearConstraint::new([(x(0), 1), (x(1), 2), (x(2), 2)].into_iter(), 2),
+                true,
+            )
+            .unwrap();
+
+        let learnt = pb_engine.learnt_constraints();
+        assert!(learnt.len() == 2);
+        assert!(learnt[0].iter_terms().eq([(x(1), 1), (x(2), 1)].into_iter()));
+        assert!(learnt[0].lower() == 1);
+        // 元の制約条件を全て満たす割り当ては学習制約も満たす
+        let is_satisfied = |constraint: &LinearConstraint<Coefficient>, assignment: usize| {
+            let sum: Coefficient = constraint
+                .iter_terms()
+                .filter(|(literal, _)| {
+                    (assignment >> literal.index() & 1 == 1) == (literal.value() == Boolean::TRUE)
+                })
+                .map(|(_, coefficient)| coefficient)
+                .sum();
+            return sum >= constraint.lower();
+        };
+        for assignment in 0..1 << 3 {
+            if original.iter().all(|constraint| is_satisfied(constraint, assignment)) {
+                assert!(learnt.iter().all(|constraint| is_satisfied(constraint, assignment)));
+            }
+        }
+        // エンジンの状態は変更されない
+        assert!(pb_engine.learnt_constraints().len() == 2);
+    }
+
+    #[test]
+    fn test_constraint_slack_histogram() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..4 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        // 余裕 2, 0
+        pb_engine
+            .add_count_constraint(CountConstraintView::new([x(0), x(1), x(2)].into_iter(), 1), false)
+            .unwrap();
+        pb_engine
+            .add_count_constraint(CountConstraintView::new([x(1), x(2), x(3)].into_iter(), 3), false)
+            .unwrap();
+        // 余裕 5, 1
+        pb_engine
+            .add_integer_linear_constraint(
+                LinearConstraint::new([(x(0), 4), (x(1), 2), (!x(3), 1)].into_iter(), 2),
+                false,
+            )
+            .unwrap();
+        pb_engine
+            .add_integer_linear_constraint(
+                LinearConstraint::new([(x(0), 3), (x(2), 1)].into_iter(), 3),
+                false,
+            )
+            .unwrap();
+        assert!(pb_engine.propagate().is_noconflict());
+        // 全ての変数が True となり，余裕は 2, 0, 4, 1 となる
+        assert!(pb_engine.constraint_slack_histogram() == vec![1, 1, 1, 1]);
+    }
+
+    #[cfg(feature = "wide-coefficients")]
+    #[test]
+    fn test_wide_coefficients() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..3 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        let large = 1u128 << 70;
+        // u64::MAX を超える係数: x0 と x1 がともに True でなければならない
+        let constraints = [
+            LinearConstraint::new([(x(0), large), (x(1), large), (x(2), 1)].into_iter(), 2 * large),
+            LinearConstraint::new([(!x(1), large), (x(2), large)].into_iter(), large),
+        ];
+        for constraint in constraints.iter() {
+            pb_engine.add_integer_linear_constraint(constraint, false).unwrap();
+        }
+        assert!(pb_engine.propagate().is_noconflict());
+        assert!(pb_engine.number_of_assignments() == 3);
+        assert!(pb_engine.is_true(x(0)) && pb_engine.is_true(x(1)) && pb_engine.is_true(x(2)));
+        assert!(pb_engine.verify_against(&constraints).is_ok());
+        assert!(pb_engine.current_slack(&constraints[0]) == 1);
+
+        // 3 変数のうち 2 つ以上が True かつどの 2 つも同時には True でない (充足不能) ことを，
+        // u64::MAX を超える係数のまま矛盾の解析を繰り返して示す
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..3 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        pb_engine
+            .add_integer_linear_constraint(
+                LinearConstraint::new((0..3).map(|index| (x(index), large)), 2 * large),
+                false,
+            )
+            .unwrap();
+        for (i, j) in [(0, 1), (0, 2), (1, 2)] {
+            pb_engine
+                .add_integer_linear_constraint(
+                    LinearConstraint::new([(!x(i), large), (!x(j), large)].into_iter(), large),
+                    false,
+                )
+                .unwrap();
+        }
+        let mut analyze = Analyze::new(1e-10);
+        let mut number_of_conflicts = 0;
+        loop {
+            let PBState::Conflict {
+                index,
+                explain_keys,
+            } = pb_engine.propagate()
+            else {
+                pb_engine.decide();
+                continue;
+            };
+            number_of_conflicts += 1;
+            if pb_engine.decision_level() == 0 {
                 break;
             }
-            let Some((literal, reason)) = self.assignment_queue.pop_assignment() else {
+            let AnalyzeResult::Backjumpable {
+                backjump_level,
+                learnt_constraint,
+                ..
+            } = analyze.call(index, explain_keys, &pb_engine)
+            else {
                 break;
             };
+            let learnt_constraint =
+                LinearConstraint::new(learnt_constraint.iter_terms(), learnt_constraint.lower());
+            pb_engine.backjump(backjump_level);
+            pb_engine
+                .add_integer_linear_constraint(learnt_constraint, true)
+                .unwrap();
+        }
+        assert!(number_of_conflicts >= 2);
+        assert!(pb_engine.decision_level() == 0 && pb_engine.state().is_conflict());
+    }
 
-            self.decision_stack.assign(literal, reason);
-
-            self.monadic_clause_theory
-                .assign(&self.decision_stack, |propagation| {
-                    self.assignment_queue.push(
-                        propagation.literal,
-                        Reason::Propagation {
-                            explain_key: propagation.explain_key.into(),
-                        },
-                        self.activities.activity(propagation.literal.index()),
-                        propagation.plbd,
-                    );
-                });
-            self.count_constraint_theory
-                .assign(&self.decision_stack, |propagation| {
-                    self.assignment_queue.push(
-                        propagation.literal,
-                        Reason::Propagation {
-                            explain_key: propagation.explain_key.into(),
-                        },
-                        self.activities.activity(propagation.literal.index()),
-                        propagation.plbd,
-                    );
-                });
-            self.integer_linear_constraint_theory
-                .assign(&self.decision_stack, |propagation| {
-                    self.assignment_queue.push(
-                        propagation.literal,
-                        Reason::Propagation {
-                            explain_key: propagation.explain_key.into(),
-                        },
-                        self.activities.activity(propagation.literal.index()),
-                        propagation.plbd,
-                    )
-                });
+    #[test]
+    fn test_invariant_checks() {
+        // 4 羽の鳩を 3 つの巣に入れる鳩の巣原理 (充足不能) を，各鳩が入る巣を節で，
+        // 各巣に入る鳩が高々 1 羽であることを整数線形制約で与えて解き，
+        // 両方の理論の内部状態を propagate のたびに検証する
+        let p = |pigeon: usize, hole: usize| Literal::new(pigeon * 3 + hole, Boolean::TRUE);
+        let mut pb_engine = PBEngine::new(10.0);
+        pb_engine.enable_invariant_checks(true);
+        for _ in 0..12 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        for pigeon in 0..4 {
+            pb_engine
+                .add_clause((0..3).map(|hole| p(pigeon, hole)), false)
+                .unwrap();
         }
-        self.assignment_queue.clear();
+        for hole in 0..3 {
+            pb_engine
+                .add_integer_linear_constraint(
+                    LinearConstraint::new((0..4).map(|pigeon| (!p(pigeon, hole), 1)), 3),
+                    false,
+                )
+                .unwrap();
+        }
+        assert!(pb_engine.number_of_count_constraints() == 4);
+        assert!(pb_engine.number_of_integer_linear_constraints() == 3);
 
-        return self.state;
+        let mut analyze = Analyze::new(1e-10);
+        loop {
+            let PBState::Conflict {
+                index,
+                explain_keys,
+            } = pb_engine.propagate()
+            else {
+                pb_engine.decide();
+                continue;
+            };
+            if pb_engine.decision_level() == 0 {
+                break;
+            }
+            let AnalyzeResult::Backjumpable {
+                backjump_level,
+                learnt_constraint,
+                ..
+            } = analyze.call(index, explain_keys, &pb_engine)
+            else {
+                break;
+            };
+            let learnt_constraint =
+                LinearConstraint::new(learnt_constraint.iter_terms(), learnt_constraint.lower());
+            pb_engine.backjump(backjump_level);
+            pb_engine
+                .add_integer_linear_constraint(learnt_constraint, true)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_decision_order() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for index in 0..3 {
+            // x0 のアクティビティが最も小さい
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, index as f64);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        // x0 = False とすると x1 と ¬x1 がともに伝播して矛盾する
+        pb_engine
+            .add_count_constraint(CountConstraintView::new([x(0), x(1)].into_iter(), 1), false)
+            .unwrap();
+        pb_engine
+            .add_count_constraint(CountConstraintView::new([x(0), !x(1)].into_iter(), 1), false)
+            .unwrap();
+        pb_engine.set_decision_order(vec![0]);
+
+        for _ in 0..2 {
+            pb_engine.decide();
+            let state = pb_engine.propagate();
+            assert!(pb_engine.get_assignment(0) == !x(0));
+            assert!(matches!(state, PBState::Conflict { index: 1, .. }));
+            // backjump で取り消された x0 は再び最初に決定される
+            pb_engine.backjump(0);
+        }
+
+        // 順序の変数が割り当て済みであればアクティビティに基づいて選択する
+        pb_engine.add_monadic_clause(MonadicClause { literal: x(0) }, false).unwrap();
+        assert!(pb_engine.propagate().is_noconflict());
+        pb_engine.decide();
+        assert!(pb_engine.propagate().is_noconflict());
+        assert!(pb_engine.get_assignment(1).index() == 2);
+    }
+
+    #[test]
+    fn test_freeze_variable() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for index in 0..3 {
+            // x2 のアクティビティが最も大きい
+            pb_engine.add_variable_with_initial_value(Boolean::TRUE, index as f64);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        pb_engine
+            .add_count_constraint(CountConstraintView::new([!x(0), x(2)].into_iter(), 1), false)
+            .unwrap();
+        pb_engine.freeze_variable(2);
+        assert!(pb_engine.is_frozen(2));
+
+        // x2 は決定されず，x0 の決定による伝播で割り当てられる
+        let mut decisions = Vec::default();
+        while pb_engine.has_unassigned() {
+            pb_engine.decide();
+            assert!(pb_engine.propagate().is_noconflict());
+            let decision_order = pb_engine.order_range(pb_engine.decision_level()).start;
+            decisions.push(pb_engine.get_assignment(decision_order));
+        }
+        assert!(decisions == vec![x(1), x(0)]);
+        assert!(pb_engine.is_true(x(2)));
+
+        // 凍結された変数も，他の変数が全て割り当てられた後には決定される
+        pb_engine.backjump(0);
+        pb_engine.add_monadic_clause(MonadicClause { literal: !x(0) }, false).unwrap();
+        assert!(pb_engine.propagate().is_noconflict());
+        let mut decisions = Vec::default();
+        while pb_engine.has_unassigned() {
+            pb_engine.decide();
+            assert!(pb_engine.propagate().is_noconflict());
+            let decision_order = pb_engine.order_range(pb_engine.decision_level()).start;
+            decisions.push(pb_engine.get_assignment(decision_order));
+        }
+        assert!(decisions == vec![x(1), x(2)]);
+
+        // 解凍するとアクティビティに基づいて決定される
+        pb_engine.backjump(0);
+        pb_engine.thaw_variable(2);
+        pb_engine.decide();
+        assert!(pb_engine.propagate().is_noconflict());
+        assert!(pb_engine.get_assignment(pb_engine.order_range(1).start) == x(2));
+    }
+
+    #[test]
+    fn test_branching_heuristic() {
+        let new_engine = || {
+            let mut pb_engine = PBEngine::new(10.0);
+            for index in 0..4 {
+                let initial_value = if index % 2 == 0 {
+                    Boolean::TRUE
+                } else {
+                    Boolean::FALSE
+                };
+                pb_engine.add_variable_with_initial_value(initial_value, index as f64);
+            }
+            return pb_engine;
+        };
+
+        // 与えた順序で決定し，値は保存された値に従う
+        let mut pb_engine = new_engine();
+        pb_engine.set_branching_heuristic(Some(Box::new(StaticOrderBranching::new(vec![2, 1]))));
+        let mut decisions = Vec::default();
+        while pb_engine.has_unassigned() {
+            pb_engine.decide();
+            assert!(pb_engine.propagate().is_noconflict());
+            decisions.push(pb_engine.get_assignment(pb_engine.number_of_assignments() - 1));
+        }
+        // 順序に含まれない変数はアクティビティの大きい順に決定される
+        assert!(
+            decisions
+                == vec![
+                    Literal::new(2, Boolean::TRUE),
+                    Literal::new(1, Boolean::FALSE),
+                    Literal::new(3, Boolean::FALSE),
+                    Literal::new(0, Boolean::TRUE),
+                ]
+        );
+
+        // ランダムな選択でも未割り当ての変数のみが決定される
+        for seed in 0..10 {
+            let mut pb_engine = new_engine();
+            pb_engine.set_branching_heuristic(Some(Box::new(RandomBranching::new(seed))));
+            while pb_engine.has_unassigned() {
+                pb_engine.decide();
+                assert!(pb_engine.propagate().is_noconflict());
+            }
+            assert!(pb_engine.decision_level() == 4);
+        }
+    }
+
+    #[test]
+    fn test_current_slack() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..4 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        // 3 x0 + 2 x1 + 2 x2 + x3 >= 4
+        let constraint =
+            LinearConstraint::new([(x(0), 3), (x(1), 2), (x(2), 2), (x(3), 1)].into_iter(), 4);
+        assert!(pb_engine.current_sup(&constraint) == 8);
+        assert!(pb_engine.current_slack(&constraint) == 4);
+
+        // ¬x0 を固定すると sup = 5, slack = 1 となり，係数が 2 の x1, x2 が伝播する状態になる
+        pb_engine.add_monadic_clause(MonadicClause { literal: !x(0) }, false).unwrap();
+        assert!(pb_engine.propagate().is_noconflict());
+        assert!(pb_engine.current_sup(&constraint) == 5);
+        assert!(pb_engine.current_slack(&constraint) == 1);
+
+        // さらに ¬x1 を固定すると違反する
+        pb_engine.add_monadic_clause(MonadicClause { literal: !x(1) }, false).unwrap();
+        assert!(pb_engine.propagate().is_noconflict());
+        assert!(pb_engine.current_slack(&constraint) == -1);
+    }
+
+    #[test]
+    fn test_add_infeasible_constraint() {
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..3 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        pb_engine.add_monadic_clause(MonadicClause { literal: !x(0) }, false).unwrap();
+        assert!(pb_engine.propagate().is_noconflict());
+
+        // 決定レベル 0 で x0 = False のもとでは sup = 3 < 4 となり充足不能
+        let constraint = LinearConstraint::new([(x(0), 3), (x(1), 2), (x(2), 1)].into_iter(), 4);
+        assert!(
+            pb_engine.add_integer_linear_constraint(constraint, false)
+                == Err(ConstraintError::Infeasible)
+        );
+        assert!(
+            pb_engine.add_count_constraint(CountConstraintView::new([x(0)].into_iter(), 1), false)
+                == Err(ConstraintError::Infeasible)
+        );
+        assert!(
+            pb_engine.add_monadic_clause(MonadicClause { literal: x(0) }, false)
+                == Err(ConstraintError::Infeasible)
+        );
+        assert!(pb_engine.number_of_integer_linear_constraints() == 0);
+
+        // 充足可能な制約条件は追加される
+        let constraint = LinearConstraint::new([(x(0), 3), (x(1), 2), (x(2), 1)].into_iter(), 3);
+        assert!(pb_engine.add_integer_linear_constraint(constraint, false) == Ok(()));
+        assert!(pb_engine.propagate().is_noconflict());
+        assert!(pb_engine.is_true(x(1)) && pb_engine.is_true(x(2)));
     }
 }
@@ -2,12 +2,15 @@ use utility::HeapedMap;
 
 use crate::{Literal, collections::LiteralArray};
 
+#[derive(Clone)]
 pub struct Activities {
     time_constant: f64,
     assignment_probabilities: LiteralArray<f64>,
     activities: Vec<f64>,
     // activity_increase_value: f64,
     unassigned_variables: HeapedMap<f64, CompareUnassignedVariables>,
+    // 凍結された変数は unassigned_variables に入れない
+    is_frozen: Vec<bool>,
 }
 
 impl Activities {
@@ -19,6 +22,7 @@ impl Activities {
             activities: Vec::default(),
             // activity_increase_value: 1.0,
             unassigned_variables: HeapedMap::default(),
+            is_frozen: Vec::default(),
         }
     }
 
@@ -26,9 +30,27 @@ impl Activities {
         let index = self.assignment_probabilities.len();
         self.assignment_probabilities.push([0.0, 0.0]);
         self.activities.push(initial_activity);
+        self.is_frozen.push(false);
         self.unassigned_variables.insert(index, initial_activity);
     }
 
+    pub fn freeze_variable(&mut self, index: usize) {
+        self.is_frozen[index] = true;
+        self.unassigned_variables.remove(index);
+    }
+
+    /// 割り当て済みの変数を戻しても，決定の際に読み飛ばされる
+    pub fn thaw_variable(&mut self, index: usize) {
+        if self.is_frozen[index] {
+            self.is_frozen[index] = false;
+            self.unassigned_variables.insert(index, self.activities[index]);
+        }
+    }
+
+    pub fn is_frozen(&self, index: usize) -> bool {
+        return self.is_frozen[index];
+    }
+
     pub fn update_assignment_probabilities(&mut self, assignments: impl Iterator<Item = Literal>) {
         let r = 1.0 - 1.0 / self.time_constant;
         for [p, q] in self.assignment_probabilities.iter_mut() {
@@ -53,6 +75,13 @@ impl Activities {
         }
     }
 
+    pub fn set_activity(&mut self, index: usize, activity: f64) {
+        self.activities[index] = activity;
+        if self.unassigned_variables.contains_key(index) {
+            self.unassigned_variables.insert(index, activity);
+        }
+    }
+
     // pub fn update_conflict_probabilities(
     //     &mut self,
     //     conflict_assignments: impl Iterator<Item = Literal>,
@@ -93,6 +122,9 @@ impl Activities {
     }
 
     pub fn push_unassigned_variable(&mut self, index: usize) {
+        if self.is_frozen[index] {
+            return;
+        }
         self.unassigned_variables
             .insert(index, self.activities[index]);
     }
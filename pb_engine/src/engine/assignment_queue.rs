@@ -11,8 +11,8 @@ pub struct AssignmentQueue<ExplainKeyT>
 where
     ExplainKeyT: Copy,
 {
-    conflict_queue: HeapedMap<Conflict<ExplainKeyT, f64>, ConflictComparator>,
-    assignment_queue: HeapedMap<Assignment<ExplainKeyT, f64>, AssignmentComparator>,
+    conflict_queue: HeapedMap<Conflict<ExplainKeyT, Priority>, ConflictComparator>,
+    assignment_queue: HeapedMap<Assignment<ExplainKeyT, Priority>, AssignmentComparator>,
 }
 
 impl<ExplainKeyT> Default for AssignmentQueue<ExplainKeyT>
@@ -42,12 +42,14 @@ where
         priority: f64,
         plbd: usize,
     ) {
+        // 同じ割り当ての理由が複数あれば PLBD の小さいものを選択する
         if self.conflict_queue.contains_key(literal.index()) {
             let conflict = self.conflict_queue.get(literal.index()).unwrap();
             if plbd < conflict.plbds[literal.value()] {
                 let mut conflict = conflict.clone();
                 conflict.reasons[literal.value()] = reason;
                 conflict.plbds[literal.value()] = plbd;
+                conflict.priority.plbd = conflict.plbds[0] + conflict.plbds[1];
                 self.conflict_queue.insert(literal.index(), conflict);
             }
         } else {
@@ -57,22 +59,26 @@ where
                     Assignment {
                         value: literal.value(),
                         reason,
-                        priority,
-                        plbd,
+                        priority: Priority {
+                            activity: priority,
+                            plbd,
+                        },
                     },
                 );
             } else {
                 let assignment = self.assignment_queue.get(literal.index()).unwrap();
                 if assignment.value == literal.value() {
-                    debug_assert!(assignment.priority == priority);
-                    if plbd < assignment.plbd {
+                    debug_assert!(assignment.priority.activity == priority);
+                    if plbd < assignment.priority.plbd {
                         self.assignment_queue.insert(
                             literal.index(),
                             Assignment {
                                 value: literal.value(),
                                 reason,
-                                priority,
-                                plbd,
+                                priority: Priority {
+                                    activity: priority,
+                                    plbd,
+                                },
                             },
                         );
                     }
@@ -83,16 +89,19 @@ where
                         [assignment.reason, reason]
                     };
                     let plbds = if literal.value() == Boolean::FALSE {
-                        [plbd, assignment.plbd]
+                        [plbd, assignment.priority.plbd]
                     } else {
-                        [assignment.plbd, plbd]
+                        [assignment.priority.plbd, plbd]
                     };
                     self.conflict_queue.insert(
                         literal.index(),
                         Conflict {
                             reasons,
                             plbds,
-                            priority,
+                            priority: Priority {
+                                activity: priority,
+                                plbd: plbds[0] + plbds[1],
+                            },
                         },
                     );
                     self.assignment_queue.remove(literal.index());
@@ -101,11 +110,12 @@ where
         }
     }
 
-    pub fn pop_conflict(&mut self) -> Option<(usize, [Reason<ExplainKeyT>; 2])> {
+    /// 矛盾を取り出さずに参照する (矛盾が見つかった時点でキューは clear される)
+    pub fn peek_conflict(&self) -> Option<(usize, &[Reason<ExplainKeyT>; 2])> {
         return self
             .conflict_queue
-            .pop_first()
-            .map(|(index, conflict)| (index, conflict.reasons));
+            .first()
+            .map(|(&index, conflict)| (index, &conflict.reasons));
     }
 
     pub fn pop_assignment(&mut self) -> Option<(Literal, Reason<ExplainKeyT>)> {
@@ -116,14 +126,61 @@ where
             .map(|(index, assignment)| (Literal::new(index, assignment.value), assignment.reason));
     }
 
+    /// キューに入っている伝播と矛盾の理由を列挙する
+    pub fn iter_explain_keys(&self) -> impl Iterator<Item = ExplainKeyT> + '_ {
+        return self
+            .assignment_queue
+            .iter()
+            .map(|(_, assignment)| assignment.reason)
+            .chain(
+                self.conflict_queue
+                    .iter()
+                    .flat_map(|(_, conflict)| conflict.reasons),
+            )
+            .filter_map(|reason| match reason {
+                Reason::Propagation { explain_key } => Some(explain_key),
+                Reason::Decision => None,
+            });
+    }
+
+    /// キューに入っている変数 index の割り当てと矛盾の優先度を変更する
+    pub fn update_priority(&mut self, index: usize, priority: f64) {
+        if let Some(assignment) = self.assignment_queue.get(index) {
+            let mut assignment = assignment.clone();
+            assignment.priority.activity = priority;
+            self.assignment_queue.insert(index, assignment);
+        }
+        if let Some(conflict) = self.conflict_queue.get(index) {
+            let mut conflict = conflict.clone();
+            conflict.priority.activity = priority;
+            self.conflict_queue.insert(index, conflict);
+        }
+    }
+
     pub fn clear(&mut self) {
         self.conflict_queue.clear();
         self.assignment_queue.clear();
     }
 }
 
-// TODO: PriorityT は (activity, plbd) とする
-// 合流では plbd の小さいものを選択し，割り当て順では activity の大きいものを選択する
+/// 割り当てと矛盾の優先度
+///
+/// activity の大きいものを優先し， activity が等しければ plbd の小さいものを優先する
+/// (大きいほど優先度が高い)．矛盾の plbd は 2 つの理由の PLBD の和とする
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Priority {
+    activity: f64,
+    plbd: usize,
+}
+
+impl PartialOrd for Priority {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        return self
+            .activity
+            .partial_cmp(&other.activity)
+            .map(|ordering| ordering.then(other.plbd.cmp(&self.plbd)));
+    }
+}
 
 #[derive(Clone)]
 struct Assignment<ExplainKeyT, PriorityT>
@@ -133,7 +190,6 @@ where
 {
     value: Boolean,
     reason: Reason<ExplainKeyT>,
-    plbd: usize,
     priority: PriorityT,
 }
 
@@ -271,3 +327,50 @@ where
         rhs.1.priority.partial_cmp(&lhs.1.priority).unwrap()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::AssignmentQueue;
+    use crate::{Boolean, Literal, Reason};
+
+    fn explain_key(reason: Reason<usize>) -> usize {
+        let Reason::Propagation { explain_key } = reason else {
+            unreachable!()
+        };
+        return explain_key;
+    }
+
+    #[test]
+    fn test_priority() {
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        let propagation = |explain_key: usize| Reason::Propagation { explain_key };
+        let mut assignment_queue = AssignmentQueue::<usize>::default();
+
+        // 同じ割り当ての理由は PLBD の小さいものが選択される
+        assignment_queue.push(x(0), propagation(1), 1.0, 5);
+        assignment_queue.push(x(0), propagation(2), 1.0, 3);
+        assignment_queue.push(x(0), propagation(3), 1.0, 4);
+        // アクティビティが等しければ PLBD の小さい割り当てが先に取り出される
+        assignment_queue.push(x(1), propagation(4), 1.0, 2);
+        // アクティビティの大きい割り当てが最初に取り出される
+        assignment_queue.push(x(2), propagation(5), 2.0, 9);
+
+        let (literal, reason) = assignment_queue.pop_assignment().unwrap();
+        assert!(literal == x(2) && explain_key(reason) == 5);
+        let (literal, reason) = assignment_queue.pop_assignment().unwrap();
+        assert!(literal == x(1) && explain_key(reason) == 4);
+        let (literal, reason) = assignment_queue.pop_assignment().unwrap();
+        assert!(literal == x(0) && explain_key(reason) == 2);
+        assert!(assignment_queue.pop_assignment().is_none());
+
+        // 矛盾の理由も PLBD の小さいものが選択される
+        assignment_queue.push(x(0), propagation(1), 1.0, 5);
+        assignment_queue.push(!x(0), propagation(2), 1.0, 4);
+        assignment_queue.push(!x(0), propagation(3), 1.0, 2);
+        assignment_queue.push(x(0), propagation(4), 1.0, 6);
+        let (index, reasons) = assignment_queue.peek_conflict().unwrap();
+        assert!(index == 0);
+        assert!(explain_key(reasons[Boolean::FALSE]) == 3);
+        assert!(explain_key(reasons[Boolean::TRUE]) == 1);
+    }
+}
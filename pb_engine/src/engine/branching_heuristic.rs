@@ -0,0 +1,69 @@
+use super::PBEngine;
+use crate::types::Literal;
+
+/// 決定変数の選択方法
+///
+/// select が返したリテラルの変数に，保存された値 (phase) を割り当てる．リテラルの極性は用いない．
+/// None を返した場合はアクティビティに基づいて選択する
+pub trait BranchingHeuristic {
+    fn select(&mut self, engine: &PBEngine) -> Option<Literal>;
+}
+
+/// 未割り当ての変数から一様ランダムに選択する
+pub struct RandomBranching {
+    state: u64,
+}
+
+impl RandomBranching {
+    pub fn new(seed: u64) -> Self {
+        // xorshift の状態は 0 であってはならない
+        return Self {
+            state: seed | 1,
+        };
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        return self.state;
+    }
+}
+
+impl BranchingHeuristic for RandomBranching {
+    fn select(&mut self, engine: &PBEngine) -> Option<Literal> {
+        let number_of_unassigned = engine.number_of_variables() - engine.number_of_assignments();
+        if number_of_unassigned == 0 {
+            return None;
+        }
+        let k = (self.next() % number_of_unassigned as u64) as usize;
+        return engine
+            .iter_variables()
+            .filter(|&index| !engine.is_assigned(index))
+            .nth(k)
+            .map(|index| Literal::new(index, engine.get_value(index)));
+    }
+}
+
+/// 与えられた順序で最初の未割り当ての変数を選択する
+///
+/// order に含まれない変数はアクティビティに基づいて選択される
+pub struct StaticOrderBranching {
+    order: Vec<usize>,
+}
+
+impl StaticOrderBranching {
+    pub fn new(order: Vec<usize>) -> Self {
+        return Self { order };
+    }
+}
+
+impl BranchingHeuristic for StaticOrderBranching {
+    fn select(&mut self, engine: &PBEngine) -> Option<Literal> {
+        return self
+            .order
+            .iter()
+            .find(|&&index| !engine.is_assigned(index))
+            .map(|&index| Literal::new(index, engine.get_value(index)));
+    }
+}
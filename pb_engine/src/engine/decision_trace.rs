@@ -0,0 +1,188 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    CountConstraint, CountConstraintTrait, LinearConstraint, LinearConstraintTrait,
+    types::{Boolean, Coefficient, Literal},
+};
+
+/// PBEngine に対する操作
+///
+/// 割り当てと制約条件を変更する操作と，アクティビティを更新する操作を記録する
+#[derive(Clone, Debug)]
+pub enum DecisionEvent {
+    AddVariable {
+        initial_value: Boolean,
+        initial_activity: f64,
+    },
+    AddMonadicClause {
+        literal: Literal,
+        is_learnt: bool,
+    },
+    AddCountConstraint {
+        constraint: CountConstraint,
+        is_learnt: bool,
+    },
+    AddIntegerLinearConstraint {
+        constraint: LinearConstraint<Coefficient>,
+        is_learnt: bool,
+    },
+    Propagate,
+    PropagateStep,
+    Decide(Literal),
+    /// リスタートは決定レベル 0 への Backjump として記録される
+    Backjump(usize),
+    UpdateAssignmentProbabilities,
+    UpdateConflictProbabilities {
+        conflict_assignments: Vec<Literal>,
+        backjump_level: usize,
+    },
+}
+
+/// PBEngine::start_recording から PBEngine::take_recording までに行われた操作の列
+///
+/// 1 行に 1 つの操作を書く文字列形式で書き出し，読み込むことができる
+#[derive(Clone, Debug, Default)]
+pub struct DecisionTrace {
+    pub events: Vec<DecisionEvent>,
+}
+
+/// DecisionTrace の文字列表現が不正であることを表すエラー
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ParseDecisionTraceError {
+    /// 読み取れなかった行 (1 から始まる)
+    pub line_number: usize,
+}
+
+impl Display for DecisionTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for event in self.events.iter() {
+            match event {
+                DecisionEvent::AddVariable {
+                    initial_value,
+                    initial_activity,
+                } => write!(f, "v {} {}", *initial_value as usize, initial_activity)?,
+                DecisionEvent::AddMonadicClause { literal, is_learnt } => {
+                    write!(f, "m {} {}", *is_learnt as usize, literal)?
+                }
+                DecisionEvent::AddCountConstraint {
+                    constraint,
+                    is_learnt,
+                } => {
+                    write!(f, "c {} {}", *is_learnt as usize, constraint.lower())?;
+                    for literal in constraint.iter_terms() {
+                        write!(f, " {}", literal)?;
+                    }
+                }
+                DecisionEvent::AddIntegerLinearConstraint {
+                    constraint,
+                    is_learnt,
+                } => {
+                    write!(f, "l {} {}", *is_learnt as usize, constraint.lower())?;
+                    for (literal, coefficient) in constraint.iter_terms() {
+                        write!(f, " {} {}", coefficient, literal)?;
+                    }
+                }
+                DecisionEvent::Propagate => write!(f, "p")?,
+                DecisionEvent::PropagateStep => write!(f, "s")?,
+                DecisionEvent::Decide(literal) => write!(f, "d {}", literal)?,
+                DecisionEvent::Backjump(backjump_level) => write!(f, "b {}", backjump_level)?,
+                DecisionEvent::UpdateAssignmentProbabilities => write!(f, "a")?,
+                DecisionEvent::UpdateConflictProbabilities {
+                    conflict_assignments,
+                    backjump_level,
+                } => {
+                    write!(f, "u {}", backjump_level)?;
+                    for literal in conflict_assignments.iter() {
+                        write!(f, " {}", literal)?;
+                    }
+                }
+            }
+            writeln!(f)?;
+        }
+        return Ok(());
+    }
+}
+
+impl FromStr for DecisionTrace {
+    type Err = ParseDecisionTraceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut trace = DecisionTrace::default();
+        for (line_index, line) in s.lines().enumerate() {
+            let error = ParseDecisionTraceError {
+                line_number: line_index + 1,
+            };
+            let mut tokens = line.split_ascii_whitespace();
+            let Some(kind) = tokens.next() else {
+                continue;
+            };
+            let tokens: Vec<&str> = tokens.collect();
+            let event = match (kind, tokens.as_slice()) {
+                ("v", &[value, activity]) => DecisionEvent::AddVariable {
+                    initial_value: parse_boolean(value).ok_or(error)?,
+                    initial_activity: activity.parse().map_err(|_| error)?,
+                },
+                ("m", &[is_learnt, literal]) => DecisionEvent::AddMonadicClause {
+                    literal: literal.parse().map_err(|_| error)?,
+                    is_learnt: parse_boolean(is_learnt).ok_or(error)? == Boolean::TRUE,
+                },
+                ("c", &[is_learnt, lower, ref literals @ ..]) => {
+                    let literals = literals
+                        .iter()
+                        .map(|literal| literal.parse::<Literal>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|_| error)?;
+                    DecisionEvent::AddCountConstraint {
+                        constraint: CountConstraint::new(
+                            literals.into_iter(),
+                            lower.parse().map_err(|_| error)?,
+                        ),
+                        is_learnt: parse_boolean(is_learnt).ok_or(error)? == Boolean::TRUE,
+                    }
+                }
+                ("l", &[is_learnt, lower, ref terms @ ..]) if terms.len() % 2 == 0 => {
+                    let terms = terms
+                        .chunks(2)
+                        .map(|term| Some((term[1].parse().ok()?, term[0].parse().ok()?)))
+                        .collect::<Option<Vec<(Literal, Coefficient)>>>()
+                        .ok_or(error)?;
+                    DecisionEvent::AddIntegerLinearConstraint {
+                        constraint: LinearConstraint::new(
+                            terms.into_iter(),
+                            lower.parse().map_err(|_| error)?,
+                        ),
+                        is_learnt: parse_boolean(is_learnt).ok_or(error)? == Boolean::TRUE,
+                    }
+                }
+                ("p", &[]) => DecisionEvent::Propagate,
+                ("s", &[]) => DecisionEvent::PropagateStep,
+                ("d", &[literal]) => DecisionEvent::Decide(literal.parse().map_err(|_| error)?),
+                ("b", &[backjump_level]) => {
+                    DecisionEvent::Backjump(backjump_level.parse().map_err(|_| error)?)
+                }
+                ("a", &[]) => DecisionEvent::UpdateAssignmentProbabilities,
+                ("u", &[backjump_level, ref literals @ ..]) => {
+                    DecisionEvent::UpdateConflictProbabilities {
+                        conflict_assignments: literals
+                            .iter()
+                            .map(|literal| literal.parse::<Literal>())
+                            .collect::<Result<Vec<_>, _>>()
+                            .map_err(|_| error)?,
+                        backjump_level: backjump_level.parse().map_err(|_| error)?,
+                    }
+                }
+                _ => return Err(error),
+            };
+            trace.events.push(event);
+        }
+        return Ok(trace);
+    }
+}
+
+fn parse_boolean(s: &str) -> Option<Boolean> {
+    return match s {
+        "0" => Some(Boolean::FALSE),
+        "1" => Some(Boolean::TRUE),
+        _ => None,
+    };
+}
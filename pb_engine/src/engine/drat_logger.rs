@@ -0,0 +1,43 @@
+use std::io::{BufWriter, Write};
+
+use crate::types::{Boolean, Literal};
+
+/// 学習した節と削除した節を DRAT 形式で書き出す
+///
+/// 入力と学習制約が全て節である場合に限り，書き出した内容は入力の CNF に対する証明となる
+pub struct DratLogger {
+    writer: BufWriter<Box<dyn Write>>,
+}
+
+impl DratLogger {
+    pub fn new(writer: impl Write + 'static) -> Self {
+        return Self {
+            writer: BufWriter::new(Box::new(writer)),
+        };
+    }
+
+    pub fn add_clause(&mut self, literals: impl Iterator<Item = Literal>) {
+        self.write_clause(literals);
+    }
+
+    pub fn delete_clause(&mut self, literals: impl Iterator<Item = Literal>) {
+        write!(self.writer, "d ").unwrap();
+        self.write_clause(literals);
+    }
+
+    pub fn flush(&mut self) {
+        self.writer.flush().unwrap();
+    }
+
+    fn write_clause(&mut self, literals: impl Iterator<Item = Literal>) {
+        for literal in literals {
+            // DRAT の変数は 1 から始まり，負のリテラルは符号で表す
+            if literal.value() == Boolean::TRUE {
+                write!(self.writer, "{} ", literal.index() + 1).unwrap();
+            } else {
+                write!(self.writer, "-{} ", literal.index() + 1).unwrap();
+            }
+        }
+        writeln!(self.writer, "0").unwrap();
+    }
+}
@@ -0,0 +1,66 @@
+use super::PBExplainKey;
+use crate::types::Literal;
+use utility::Map;
+
+/// 含意グラフの頂点 (割り当てられたリテラル)
+#[derive(Clone, Debug)]
+pub struct ImplicationGraphNode {
+    pub literal: Literal,
+    pub decision_level: usize,
+    /// 伝播を引き起こした制約条件 (決定の場合は None)
+    pub explain_key: Option<PBExplainKey>,
+    /// 伝播の前提となった (真である) リテラル
+    pub antecedents: Vec<Literal>,
+}
+
+/// 矛盾に至った含意グラフ
+///
+/// 矛盾した変数の正負のリテラルそれぞれについて，それを導いた制約条件と前提を保持する
+#[derive(Clone, Debug)]
+pub struct ImplicationGraph {
+    /// 変数のインデックスから頂点への写像 (割り当て順)
+    pub nodes: Map<ImplicationGraphNode>,
+    pub conflict: [ImplicationGraphNode; 2],
+}
+
+impl std::fmt::Display for ImplicationGraph {
+    /// DOT 形式で出力する
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "digraph implication_graph {{")?;
+        for (_, node) in self.nodes.iter() {
+            writeln!(
+                f,
+                "  \"{}\" [label=\"{} @{}\", shape={}];",
+                node.literal,
+                node.literal,
+                node.decision_level,
+                if node.explain_key.is_none() {
+                    "box"
+                } else {
+                    "ellipse"
+                }
+            )?;
+            for antecedent in node.antecedents.iter() {
+                writeln!(
+                    f,
+                    "  \"{}\" -> \"{}\" [label=\"{:?}\"];",
+                    antecedent,
+                    node.literal,
+                    node.explain_key.unwrap()
+                )?;
+            }
+        }
+        writeln!(f, "  \"conflict\" [shape=doubleoctagon];")?;
+        for node in self.conflict.iter() {
+            for antecedent in node.antecedents.iter() {
+                writeln!(
+                    f,
+                    "  \"{}\" -> \"conflict\" [label=\"{:?}\"];",
+                    antecedent,
+                    node.explain_key.unwrap()
+                )?;
+            }
+        }
+        writeln!(f, "}}")
+    }
+}
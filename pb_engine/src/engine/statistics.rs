@@ -0,0 +1,33 @@
+/// PBEngine の探索の進み具合
+///
+/// PBEngine::statistics で取得するほか， PBEngine::on_restart で登録した関数にリスタートごとに渡される
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Statistics {
+    /// PBEngine::restart の呼び出し回数
+    pub number_of_restarts: u64,
+    /// 伝播で検出した矛盾の数
+    pub number_of_conflicts: u64,
+    /// 決定レベル 0 で割り当てられている変数の数
+    pub number_of_fixed: usize,
+    pub number_of_count_constraints: usize,
+    pub number_of_integer_linear_constraints: usize,
+    /// 個数制約と整数線形制約の学習制約の数の合計
+    pub number_of_learnt_constraints: usize,
+    /// これまでに追加した学習制約の項数の合計 (削除された学習制約も含む)
+    pub learnt_size_sum: u64,
+    /// これまでに追加した学習制約の数 (削除された学習制約も含む)
+    pub number_of_added_learnt_constraints: u64,
+}
+
+/// PBEngine::on_restart で登録する，リスタートごとに呼び出す関数
+pub(crate) type RestartCallback = Box<dyn FnMut(&Statistics)>;
+
+impl Statistics {
+    /// これまでに追加した学習制約の項数の平均 (学習制約がなければ 0)
+    pub fn avg_learnt_size(&self) -> f64 {
+        if self.number_of_added_learnt_constraints == 0 {
+            return 0.0;
+        }
+        return self.learnt_size_sum as f64 / self.number_of_added_learnt_constraints as f64;
+    }
+}
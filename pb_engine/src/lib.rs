@@ -14,8 +14,14 @@ mod types;
 pub use analyze::{Analyze, AnalyzeResult, strengthen_integer_linear_constraint};
 pub use calculate_plbd::CalculatePLBD;
 pub use constraints::{
-    CountConstraint, CountConstraintTrait, CountConstraintView, LinearConstraint,
+    ConstraintError, CountConstraint, CountConstraintTrait, CountConstraintView, LinearConstraint,
     LinearConstraintTrait, LinearConstraintView, MonadicClause,
 };
-pub use engine::{PBConstraint, PBEngine, PBExplainKey, PBState, Reason};
-pub use types::{Boolean, Literal};
+pub use engine::{
+    BranchingHeuristic, ConstraintOrigin, DecisionEvent, DecisionTrace, DratLogger,
+    EngineSnapshot, ImplicationGraph, ImplicationGraphNode, ParseDecisionTraceError,
+    PBConstraint, PBEngine, PBExplainKey, PBState, RandomBranching, Reason, StaticOrderBranching,
+    Statistics,
+};
+pub use theories::ReduceDbCriterion;
+pub use types::{Boolean, Coefficient, Literal, ParseLiteralError};
@@ -35,15 +35,49 @@ pub trait TheoryTrait {
 }
 
 pub trait TheoryAddConstraintTrait<ConstraintT>: TheoryTrait {
+    /// 現在の決定レベルより前に伝播が発生する制約条件は追加せず，伝播が発生する決定レベルを Err で返す
+    /// (追加に適した決定レベルは PBEngine::propagation_level_of で求められる)
+    ///
+    /// 追加した行の ID を返す．右辺値が 0 以下の自明な制約条件は追加せず None を返す
     fn add_constraint<ExplainKeyT: Copy>(
         &mut self,
         constraint: ConstraintT,
         is_learnt: bool,
         decision_stack: &DecisionStack<ExplainKeyT>,
         callback: impl FnMut(Propagation<Self::ExplainKey>),
-    ) -> Result<(), usize>;
+    ) -> Result<Option<usize>, usize>;
+}
+
+/// 学習制約の削除基準
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ReduceDbCriterion {
+    /// PLBD の小さい順（同じ PLBD ではアクティビティの大きい順）に残す
+    Plbd,
+    /// PLBD に関わらずアクティビティの大きい順に残す
+    Activity,
+    /// PLBD が 2 以下の制約条件は常に残し，それ以外をアクティビティの大きい順に残す
+    #[default]
+    Hybrid,
+}
 
-    // TODO: 制約条件を追加するための適切な決定レベルを算出する関数を追加
+impl ReduceDbCriterion {
+    /// 学習制約 (row_id, plbd, activity) から削除候補を選び，残す優先度の高い順に並べる
+    fn sort_candidates(self, candidates: &mut Vec<(usize, usize, f64)>) {
+        match self {
+            Self::Plbd => {
+                candidates.sort_unstable_by(|lhs, rhs| {
+                    lhs.1.cmp(&rhs.1).then(rhs.2.partial_cmp(&lhs.2).unwrap())
+                });
+            }
+            Self::Activity => {
+                candidates.sort_unstable_by(|lhs, rhs| rhs.2.partial_cmp(&lhs.2).unwrap());
+            }
+            Self::Hybrid => {
+                candidates.retain(|&(_, plbd, _)| plbd > 2);
+                candidates.sort_unstable_by(|lhs, rhs| rhs.2.partial_cmp(&lhs.2).unwrap());
+            }
+        }
+    }
 }
 
 pub use count_constraint_theory::{CountConstraintExplainKey, CountConstraintTheory};
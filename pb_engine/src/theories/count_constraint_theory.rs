@@ -1,17 +1,23 @@
 use std::cmp::{max, min};
 
 use crate::{
-    Literal, calculate_plbd::CalculatePLBD, collections::LiteralArray,
-    constraints::CountConstraintTrait, decision_stack::DecisionStack, theories::Propagation,
+    Coefficient, Literal, calculate_plbd::CalculatePLBD, collections::LiteralArray,
+    constraints::{CountConstraintTrait, CountConstraintView}, decision_stack::DecisionStack, theories::Propagation,
 };
 
-use super::{TheoryAddConstraintTrait, TheoryTrait};
+use super::{ReduceDbCriterion, TheoryAddConstraintTrait, TheoryTrait};
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct CountConstraintExplainKey {
     row_id: usize,
 }
 
+impl CountConstraintExplainKey {
+    pub fn row_id(&self) -> usize {
+        return self.row_id;
+    }
+}
+
 #[derive(Clone)]
 pub struct CountConstraintTheory {
     activity_time_constant: f64,
@@ -19,10 +25,16 @@ pub struct CountConstraintTheory {
     watching_rows: LiteralArray<Vec<Watch>>,
     rows: Vec<Row>,
     number_of_constraints: usize,
+    number_of_original_constraints: usize,
     number_of_evaluated_assignments: usize,
     activity_increase_value: f64,
     backjump_count: usize,
     reducing_backjump_count: usize,
+    reduce_db_criterion: ReduceDbCriterion,
+    // 削除した節 (lower = 1 の行) を記録する場合は Some
+    deleted_clauses: Option<Vec<Vec<Literal>>>,
+    // 学習した節が包含する節を削除するか
+    subsumption_checks: bool,
 }
 
 impl CountConstraintTheory {
@@ -33,16 +45,265 @@ impl CountConstraintTheory {
             watching_rows: LiteralArray::default(),
             rows: Vec::default(),
             number_of_constraints: 0,
+            number_of_original_constraints: 0,
             number_of_evaluated_assignments: 0,
             activity_increase_value: 1.0,
             backjump_count: 0,
             reducing_backjump_count: 10000,
+            reduce_db_criterion: ReduceDbCriterion::default(),
+            deleted_clauses: None,
+            subsumption_checks: false,
         }
     }
 
+    /// 学習制約の削除で取り除いた節を記録するかどうかを設定する
+    pub fn record_deleted_clauses(&mut self, record: bool) {
+        self.deleted_clauses = if record { Some(Vec::default()) } else { None };
+    }
+
+    /// 節を学習するたびに，その節が包含する学習した節を削除するかどうかを設定する (既定は無効)
+    pub fn enable_subsumption_checks(&mut self, subsumption_checks: bool) {
+        self.subsumption_checks = subsumption_checks;
+    }
+
+    /// 前回の呼び出し以降に削除した節を取り出す
+    pub fn take_deleted_clauses(&mut self) -> Vec<Vec<Literal>> {
+        return self
+            .deleted_clauses
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default();
+    }
+
     pub fn number_of_constraints(&self) -> usize {
         return self.number_of_constraints;
     }
+
+    /// 削除した行を含む行の数 (次に追加する行の row_id)
+    pub fn number_of_rows(&self) -> usize {
+        return self.rows.len();
+    }
+
+    /// 削除されていない学習制約の数
+    pub fn number_of_learnt_constraints(&self) -> usize {
+        return self.number_of_constraints - self.number_of_original_constraints;
+    }
+
+    /// 学習制約の数が max_number_of_learnt_constraints 以下となるように，削除基準での順位が低いものから削除する
+    ///
+    /// 割り当ての理由となっている学習制約と，削除基準が削除の候補としない学習制約は削除しないため，
+    /// max_number_of_learnt_constraints 以下とならない場合がある
+    pub fn reduce_learnt_constraints(&mut self, max_number_of_learnt_constraints: usize) {
+        let number_of_learnt_constraints = self.number_of_learnt_constraints();
+        if number_of_learnt_constraints <= max_number_of_learnt_constraints {
+            return;
+        }
+        let mut rows = Vec::default();
+        for (row_id, row) in self.rows.iter().enumerate() {
+            if row.state == RowState::Learnt && row.reference_count == 0 {
+                rows.push((row_id, row.plbd, row.activity));
+            }
+        }
+        self.reduce_db_criterion.sort_candidates(&mut rows);
+        let number_of_deleted_rows = min(
+            number_of_learnt_constraints - max_number_of_learnt_constraints,
+            rows.len(),
+        );
+        for &(row_id, _, _) in rows[rows.len() - number_of_deleted_rows..].iter() {
+            self.delete_learnt_row(row_id);
+        }
+    }
+
+    fn delete_learnt_row(&mut self, row_id: usize) {
+        let row = &mut self.rows[row_id];
+        debug_assert!(row.state == RowState::Learnt);
+        row.state = RowState::Deleted;
+        if let Some(deleted_clauses) = &mut self.deleted_clauses
+            && row.lower == 1
+        {
+            deleted_clauses.push(std::mem::take(&mut row.literals));
+        }
+        row.literals.clear();
+        self.number_of_constraints -= 1;
+    }
+
+    /// 削除されていない制約条件と，それが学習制約であるかを列挙する
+    pub fn iter_constraints(
+        &self,
+    ) -> impl Iterator<Item = (CountConstraintView<impl Iterator<Item = Literal> + Clone + '_>, bool)> + '_
+    {
+        return self
+            .rows
+            .iter()
+            .filter(|row| row.state != RowState::Deleted)
+            .map(|row| {
+                (
+                    CountConstraintView::new(row.literals.iter().copied(), row.lower),
+                    row.state != RowState::Original,
+                )
+            });
+    }
+
+    /// 削除されていない各行の余裕 (偽でないリテラルの数 - lower) を列挙する
+    ///
+    /// 監視リテラル方式のため上界を保持しておらず，行の長さに比例する時間がかかる
+    pub fn iter_slacks<'a, ExplainKeyT: Copy>(
+        &'a self,
+        decision_stack: &'a DecisionStack<ExplainKeyT>,
+    ) -> impl Iterator<Item = Coefficient> + 'a {
+        return self
+            .rows
+            .iter()
+            .filter(|row| row.state != RowState::Deleted)
+            .map(|row| {
+                let sup = row
+                    .literals
+                    .iter()
+                    .filter(|&&literal| !decision_stack.is_false(literal))
+                    .count() as Coefficient;
+                sup.saturating_sub(row.lower)
+            });
+    }
+
+    pub fn set_reduce_db_criterion(&mut self, reduce_db_criterion: ReduceDbCriterion) {
+        self.reduce_db_criterion = reduce_db_criterion;
+    }
+
+    pub fn explain_terms(
+        &self,
+        explain_key: CountConstraintExplainKey,
+    ) -> impl Iterator<Item = Literal> + Clone + '_ {
+        return self.rows[explain_key.row_id].literals.iter().cloned();
+    }
+
+    pub fn explain_lower(&self, explain_key: CountConstraintExplainKey) -> Coefficient {
+        return self.rows[explain_key.row_id].lower;
+    }
+
+    /// explain_key に対応する制約条件が割り当ての理由として記録されたことを通知する
+    ///
+    /// 参照されている間，その制約条件は削除されない
+    pub fn lock(&mut self, explain_key: CountConstraintExplainKey) {
+        self.rows[explain_key.row_id].reference_count += 1;
+    }
+
+    /// lock で通知した割り当てが取り消されたことを通知する
+    pub fn unlock(&mut self, explain_key: CountConstraintExplainKey) {
+        let row = &mut self.rows[explain_key.row_id];
+        debug_assert!(row.reference_count > 0);
+        row.reference_count -= 1;
+    }
+
+    /// 学習した節 row_id が包含する (全てのリテラルを含む) 節を削除する
+    ///
+    /// 候補は学習した節の偽でないリテラル (学習直後は伝播するリテラルのみ) を監視している節のうち，
+    /// 長さが学習した節以上のものに限る．包含される節も他のリテラルが偽であれば同じリテラルを監視している．
+    /// 元の制約条件は入力での由来を保つため削除しない
+    fn subsumption_check<ExplainKeyT: Copy>(
+        &mut self,
+        row_id: usize,
+        decision_stack: &DecisionStack<ExplainKeyT>,
+    ) {
+        let key = |literal: &Literal| (literal.index(), literal.value() as usize);
+        let mut literals = self.rows[row_id].literals.clone();
+        literals.sort_unstable_by_key(key);
+        for &literal in literals.iter() {
+            if decision_stack.is_false(literal) {
+                continue;
+            }
+            for k in 0..self.watching_rows[literal].len() {
+                let other_row_id = self.watching_rows[literal][k].row_id;
+                let other_row = &self.rows[other_row_id];
+                if other_row_id == row_id
+                    || other_row.state == RowState::Deleted
+                    || other_row.state == RowState::Original
+                    || other_row.lower != 1
+                    || other_row.literals.len() < literals.len()
+                    // 割り当ての理由となっている節は削除しない
+                    || other_row.reference_count > 0
+                {
+                    continue;
+                }
+                let number_of_common_literals = other_row
+                    .literals
+                    .iter()
+                    .filter(|literal| literals.binary_search_by_key(&key(literal), key).is_ok())
+                    .count();
+                if number_of_common_literals < literals.len() {
+                    continue;
+                }
+
+                let other_row = &mut self.rows[other_row_id];
+                let state = std::mem::replace(&mut other_row.state, RowState::Deleted);
+                if let Some(deleted_clauses) = &mut self.deleted_clauses {
+                    deleted_clauses.push(std::mem::take(&mut other_row.literals));
+                }
+                other_row.literals.clear();
+                self.number_of_constraints -= 1;
+                let row = &mut self.rows[row_id];
+                if state == RowState::Core && row.state == RowState::Learnt {
+                    row.state = RowState::Core;
+                }
+            }
+        }
+    }
+
+    /// 監視リテラルの整合性を検証する
+    ///
+    /// 各行で先頭の lower + 1 個のリテラルが監視され，それぞれの監視リストに
+    /// 行と位置を指す Watch がちょうど 1 つずつ登録されていることを確かめる
+    pub fn check_invariants<ExplainKeyT: Copy>(&self, decision_stack: &DecisionStack<ExplainKeyT>) {
+        assert!(self.number_of_evaluated_assignments == decision_stack.number_of_assignments());
+        for (row_id, row) in self.rows.iter().enumerate() {
+            if row.state == RowState::Deleted || row.number_of_watching_literals == 0 {
+                continue;
+            }
+            assert!(
+                row.number_of_watching_literals == row.lower as usize + 1,
+                "count constraint {}: {} literals are watched but lower is {}",
+                row_id,
+                row.number_of_watching_literals,
+                row.lower
+            );
+            for (position, &literal) in row.literals[..row.number_of_watching_literals]
+                .iter()
+                .enumerate()
+            {
+                assert!(
+                    self.watching_rows[literal]
+                        .iter()
+                        .filter(|watch| watch.row_id == row_id && watch.position == position)
+                        .count()
+                        == 1,
+                    "count constraint {}: position {} is not watched by {}",
+                    row_id,
+                    position,
+                    literal
+                );
+            }
+        }
+
+        // 監視リストの各 Watch は，記録された位置のリテラルを指す
+        for index in 0..self.watching_rows.len() {
+            for value in [crate::Boolean::FALSE, crate::Boolean::TRUE] {
+                let literal = Literal::new(index, value);
+                for watch in self.watching_rows[literal].iter() {
+                    let row = &self.rows[watch.row_id];
+                    if row.state == RowState::Deleted {
+                        continue;
+                    }
+                    assert!(
+                        watch.position < row.number_of_watching_literals
+                            && row.literals[watch.position] == literal,
+                        "count constraint {}: {} is watched at position {} but the literal is not there",
+                        watch.row_id,
+                        literal,
+                        watch.position
+                    );
+                }
+            }
+        }
+    }
 }
 
 impl TheoryTrait for CountConstraintTheory {
@@ -96,6 +357,13 @@ impl TheoryTrait for CountConstraintTheory {
                 decision_stack,
             );
             row.plbd = min(row.plbd, plbd);
+            // PLBD が 2 以下となった学習制約は削除しない
+            if row.state == RowState::Learnt
+                && row.plbd <= 2
+                && self.reduce_db_criterion != ReduceDbCriterion::Activity
+            {
+                row.state = RowState::Core;
+            }
             row.activity += self.activity_increase_value;
 
             for &literal in row.literals[..row.number_of_watching_literals].iter() {
@@ -130,18 +398,15 @@ impl TheoryTrait for CountConstraintTheory {
             let mut rows = Vec::default();
             for (row_id, row) in self.rows.iter_mut().enumerate() {
                 row.activity /= self.activity_increase_value;
-                if row.state == RowState::Learnt && row.plbd > 2 {
-                    rows.push((row_id, row.activity));
+                // 割り当ての理由となっている学習制約は削除しない
+                if row.state == RowState::Learnt && row.reference_count == 0 {
+                    rows.push((row_id, row.plbd, row.activity));
                 }
             }
             self.activity_increase_value = 1.0;
-            rows.sort_unstable_by(|lhs, rhs| rhs.1.partial_cmp(&lhs.1).unwrap());
-            for &(row_id, _) in rows.iter().skip(max(1000, rows.len() * 2)) {
-                let row = &mut self.rows[row_id];
-                debug_assert!(row.state == RowState::Learnt);
-                row.state = RowState::Deleted;
-                row.literals.clear();
-                self.number_of_constraints -= 1;
+            self.reduce_db_criterion.sort_candidates(&mut rows);
+            for &(row_id, _, _) in rows.iter().skip(max(1000, rows.len() * 2)) {
+                self.delete_learnt_row(row_id);
             }
         }
     }
@@ -161,9 +426,9 @@ where
         is_learnt: bool,
         decision_stack: &DecisionStack<ExplainKeyT>,
         mut callback: impl FnMut(Propagation<Self::ExplainKey>),
-    ) -> Result<(), usize> {
+    ) -> Result<Option<usize>, usize> {
         if constraint.lower() == 0 {
-            return Ok(());
+            return Ok(None);
         }
 
         if is_learnt {
@@ -225,9 +490,13 @@ where
                     },
                     activity: 0.0,
                     plbd,
+                    reference_count: 0,
                 });
             }
             self.number_of_constraints += 1;
+            if !is_learnt {
+                self.number_of_original_constraints += 1;
+            }
             let row = self.rows.last_mut().unwrap();
 
             // 監視を追加
@@ -260,6 +529,11 @@ where
                     }
                 }
             }
+
+            if is_learnt && lower == 1 && self.subsumption_checks {
+                self.subsumption_check(row_id, decision_stack);
+            }
+            return Ok(Some(row_id));
         } else {
             assert!((constraint.lower() as usize) == constraint.len());
             assert!(decision_stack.decision_level() == 0);
@@ -276,8 +550,12 @@ where
                 },
                 activity: 0.0,
                 plbd: 0,
+                reference_count: 0,
             });
             self.number_of_constraints += 1;
+            if !is_learnt {
+                self.number_of_original_constraints += 1;
+            }
             // let row = self.rows.last_mut().unwrap();
 
             for literal in constraint.iter_terms() {
@@ -290,8 +568,8 @@ where
                     });
                 }
             }
+            return Ok(Some(row_id));
         }
-        return Ok(());
     }
 }
 
@@ -299,17 +577,21 @@ where
 enum RowState {
     Original,
     Learnt,
+    /// PLBD が小さく，削除対象から外された学習制約
+    Core,
     Deleted,
 }
 
 #[derive(Clone, Debug)]
 struct Row {
     literals: Vec<Literal>,
-    lower: u64,
+    lower: Coefficient,
     number_of_watching_literals: usize,
     state: RowState,
     activity: f64,
     plbd: usize,
+    // この制約条件を理由とする割り当ての数
+    reference_count: usize,
 }
 
 impl CountConstraintTrait for Row {
@@ -317,7 +599,7 @@ impl CountConstraintTrait for Row {
         self.literals.iter().cloned()
     }
 
-    fn lower(&self) -> u64 {
+    fn lower(&self) -> Coefficient {
         self.lower
     }
 }
@@ -327,3 +609,267 @@ struct Watch {
     row_id: usize,
     position: usize,
 }
+
+#[cfg(test)]
+mod test {
+    use super::{CountConstraintTheory, RowState};
+    use crate::{
+        Boolean, CountConstraintView, Literal, Reason,
+        decision_stack::DecisionStack,
+        theories::{ReduceDbCriterion, TheoryAddConstraintTrait, TheoryTrait},
+    };
+
+    #[test]
+    fn test_core_promotion() {
+        let mut decision_stack = DecisionStack::<()>::default();
+        let mut theory = CountConstraintTheory::new(1e4);
+        for _ in 0..4 {
+            decision_stack.add_variable(Boolean::FALSE);
+            theory.add_variable();
+        }
+
+        // x0 + x1 + x2 + x3 >= 1 を学習制約として追加
+        theory
+            .add_constraint(
+                CountConstraintView::new((0..4).map(|index| Literal::new(index, Boolean::TRUE)), 1),
+                true,
+                &decision_stack,
+                |_| unreachable!(),
+            )
+            .unwrap();
+        assert!(theory.rows[0].state == RowState::Learnt);
+
+        // 決定レベル 1 で x0, x1 ，決定レベル 2 で x2 に False を割り当てると x3 が伝播する
+        let mut propagations = Vec::default();
+        for (index, reason) in [
+            (0, Reason::Decision),
+            (1, Reason::Propagation { explain_key: () }),
+            (2, Reason::Decision),
+        ] {
+            decision_stack.assign(Literal::new(index, Boolean::FALSE), reason);
+            theory.assign(&decision_stack, |propagation| {
+                propagations.push(propagation.literal)
+            });
+        }
+        assert!(propagations == vec![Literal::new(3, Boolean::TRUE)]);
+        theory.check_invariants(&decision_stack);
+
+        // 伝播時の PLBD は 2 なので削除対象から外される
+        assert!(theory.rows[0].plbd == 2);
+        assert!(theory.rows[0].state == RowState::Core);
+    }
+
+    #[test]
+    fn test_reduce_db_criterion() {
+        for reduce_db_criterion in [
+            ReduceDbCriterion::Plbd,
+            ReduceDbCriterion::Activity,
+            ReduceDbCriterion::Hybrid,
+        ] {
+            let mut decision_stack = DecisionStack::<()>::default();
+            let mut theory = CountConstraintTheory::new(1e4);
+            theory.set_reduce_db_criterion(reduce_db_criterion);
+            for _ in 0..8 {
+                decision_stack.add_variable(Boolean::FALSE);
+                theory.add_variable();
+            }
+
+            // row 0: x0 + x1 + x2 + x3 >= 1 ，row 1: x4 + x5 + x6 + x7 >= 1 を学習制約として追加
+            let x = |index: usize| Literal::new(index, Boolean::TRUE);
+            for literals in [vec![x(0), x(1), x(2), x(3)], vec![x(4), x(5), x(6), x(7)]] {
+                theory
+                    .add_constraint(
+                        CountConstraintView::new(literals.into_iter(), 1),
+                        true,
+                        &decision_stack,
+                        |_| unreachable!(),
+                    )
+                    .unwrap();
+            }
+
+            // row 0 のみが PLBD 2 で伝播に使われる
+            for (index, reason) in [
+                (0, Reason::Decision),
+                (1, Reason::Propagation { explain_key: () }),
+                (2, Reason::Decision),
+            ] {
+                decision_stack.assign(!x(index), reason);
+                theory.assign(&decision_stack, |_| {});
+            }
+            assert!(theory.rows[0].plbd == 2 && theory.rows[0].activity > 0.0);
+            assert!(theory.rows[1].activity == 0.0);
+            // Activity では PLBD が小さくても削除対象から外さない
+            assert!(
+                (theory.rows[0].state == RowState::Learnt)
+                    == (reduce_db_criterion == ReduceDbCriterion::Activity)
+            );
+
+            // 決定レベル 0 に戻ってから学習制約を 1 つに減らすと，一度も使われていない row 1 が削除される
+            decision_stack.backjump(0);
+            theory.backjump(0, &decision_stack);
+            theory.reduce_learnt_constraints(1);
+            assert!(theory.rows[0].state != RowState::Deleted);
+            assert!(theory.rows[1].state == RowState::Deleted);
+            assert!(theory.number_of_learnt_constraints() == 1);
+            theory.check_invariants(&decision_stack);
+        }
+    }
+
+    #[test]
+    fn test_subsumption_check() {
+        let mut decision_stack = DecisionStack::<()>::default();
+        let mut theory = CountConstraintTheory::new(1e4);
+        theory.record_deleted_clauses(true);
+        theory.enable_subsumption_checks(true);
+        for _ in 0..4 {
+            decision_stack.add_variable(Boolean::FALSE);
+            theory.add_variable();
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        let add_clause = |theory: &mut CountConstraintTheory, literals: Vec<Literal>, is_learnt| {
+            theory
+                .add_constraint(
+                    CountConstraintView::new(literals.into_iter(), 1),
+                    is_learnt,
+                    &decision_stack,
+                    |_| unreachable!(),
+                )
+                .unwrap();
+        };
+        add_clause(&mut theory, vec![x(0), x(1), x(2)], true);
+        add_clause(&mut theory, vec![x(1), x(0), !x(3)], true);
+        add_clause(&mut theory, vec![x(2), x(3)], false);
+        add_clause(&mut theory, vec![x(0), x(2)], false);
+
+        // x0 ∨ x1 は最初の 2 つの節を包含する
+        add_clause(&mut theory, vec![x(0), x(1)], true);
+        let deleted_clauses = theory.take_deleted_clauses();
+        assert!(deleted_clauses.len() == 2);
+        // 削除された節は学習した節が含意するため冗長
+        assert!(
+            deleted_clauses
+                .iter()
+                .all(|clause| clause.contains(&x(0)) && clause.contains(&x(1)))
+        );
+        assert!(theory.number_of_constraints() == 3);
+        theory.check_invariants(&decision_stack);
+
+        // 長さが短い節 x0 ∨ x2 は削除されない
+        add_clause(&mut theory, vec![x(0), x(2), x(3)], true);
+        assert!(theory.take_deleted_clauses().is_empty());
+        assert!(theory.number_of_constraints() == 4);
+    }
+
+    #[test]
+    fn test_subsumption_keeps_original_clauses() {
+        let mut decision_stack = DecisionStack::<()>::default();
+        let mut theory = CountConstraintTheory::new(1e4);
+        theory.enable_subsumption_checks(true);
+        for _ in 0..4 {
+            decision_stack.add_variable(Boolean::FALSE);
+            theory.add_variable();
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        for (literals, is_learnt) in [
+            (vec![x(0), x(1), x(2)], false),
+            (vec![x(0), x(1), x(3)], false),
+            (vec![x(0), x(1)], true),
+        ] {
+            theory
+                .add_constraint(
+                    CountConstraintView::new(literals.into_iter(), 1),
+                    is_learnt,
+                    &decision_stack,
+                    |_| unreachable!(),
+                )
+                .unwrap();
+        }
+
+        // x0 ∨ x1 が包含する元の節は，入力での由来を保つため削除しない
+        assert!(theory.number_of_constraints() == 3);
+        assert!(theory.number_of_learnt_constraints() == 1);
+        assert!(theory.rows[2].state == RowState::Learnt);
+    }
+
+    #[test]
+    #[should_panic(expected = "count constraint 0: position 0 is not watched by l_0")]
+    fn test_check_invariants() {
+        let mut decision_stack = DecisionStack::<()>::default();
+        let mut theory = CountConstraintTheory::new(1e4);
+        for _ in 0..3 {
+            decision_stack.add_variable(Boolean::FALSE);
+            theory.add_variable();
+        }
+        // x0 + x1 + x2 >= 1
+        theory
+            .add_constraint(
+                CountConstraintView::new((0..3).map(|index| Literal::new(index, Boolean::TRUE)), 1),
+                false,
+                &decision_stack,
+                |_| unreachable!(),
+            )
+            .unwrap();
+        theory.check_invariants(&decision_stack);
+
+        // 監視リストから取り除かれた監視リテラルがあれば panic する
+        theory.watching_rows[Literal::new(0, Boolean::TRUE)].clear();
+        theory.check_invariants(&decision_stack);
+    }
+
+    #[test]
+    fn test_watch_invariants() {
+        let mut decision_stack = DecisionStack::<()>::default();
+        let mut theory = CountConstraintTheory::new(1e4);
+        for _ in 0..6 {
+            decision_stack.add_variable(Boolean::FALSE);
+            theory.add_variable();
+        }
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+
+        // x0 + ... + x5 >= 2, x0 + x2 + x4 >= 1, ¬x1 + ¬x3 + x5 >= 2
+        for (literals, lower) in [
+            ((0..6).map(x).collect::<Vec<_>>(), 2),
+            (vec![x(0), x(2), x(4)], 1),
+            (vec![!x(1), !x(3), x(5)], 2),
+        ] {
+            theory
+                .add_constraint(
+                    CountConstraintView::new(literals.into_iter(), lower),
+                    false,
+                    &decision_stack,
+                    |_| unreachable!(),
+                )
+                .unwrap();
+        }
+        theory.check_invariants(&decision_stack);
+
+        // x0, x1, x2 に False を決定して伝播させ，バックジャンプする操作を繰り返す
+        for _ in 0..2 {
+            for index in [0, 1, 2] {
+                let mut assignments = vec![(!x(index), Reason::Decision)];
+                while let Some((literal, reason)) = assignments.pop() {
+                    if decision_stack.is_assigned(literal.index()) {
+                        assert!(decision_stack.is_true(literal));
+                        continue;
+                    }
+                    decision_stack.assign(literal, reason);
+                    theory.assign(&decision_stack, |propagation| {
+                        assignments.push((
+                            propagation.literal,
+                            Reason::Propagation { explain_key: () },
+                        ))
+                    });
+                    theory.check_invariants(&decision_stack);
+                }
+            }
+            // x0 + x2 + x4 >= 1 から x4 が伝播する
+            assert!(decision_stack.number_of_assignments() == 4);
+            assert!(decision_stack.is_true(x(4)));
+            for backjump_level in [1, 0] {
+                theory.backjump(backjump_level, &decision_stack);
+                decision_stack.backjump(backjump_level);
+                theory.check_invariants(&decision_stack);
+            }
+        }
+    }
+}
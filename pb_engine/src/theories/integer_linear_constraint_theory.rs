@@ -1,9 +1,13 @@
 use std::cmp::{max, min};
 
-use super::{Propagation, TheoryAddConstraintTrait, TheoryTrait};
+use super::{Propagation, ReduceDbCriterion, TheoryAddConstraintTrait, TheoryTrait};
 use crate::{
-    Literal, calculate_plbd::CalculatePLBD, collections::LiteralArray,
-    constraints::LinearConstraintTrait, decision_stack::DecisionStack, engine,
+    Coefficient, Literal,
+    analyze::CalculatePropagationLevel,
+    calculate_plbd::CalculatePLBD,
+    collections::LiteralArray,
+    constraints::{LinearConstraintTrait, LinearConstraintView},
+    decision_stack::DecisionStack,
 };
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -11,42 +15,202 @@ pub struct IntegerLinearConstraintExplainKey {
     row_id: usize,
 }
 
+impl IntegerLinearConstraintExplainKey {
+    pub fn row_id(&self) -> usize {
+        return self.row_id;
+    }
+}
+
 #[derive(Clone)]
 pub struct IntegerLinearConstraintTheory {
     calculate_plbd: CalculatePLBD,
+    calculate_propagation_level: CalculatePropagationLevel,
     rows: Vec<Row>,
     number_of_constraints: usize,
+    number_of_original_constraints: usize,
     columns: LiteralArray<Column>,
     number_of_evaluated_assignments: usize,
     activity_time_constant: f64,
     activity_increase_value: f64,
     backjump_count: usize,
     reducing_backjump_count: usize,
+    reduce_db_criterion: ReduceDbCriterion,
 }
 
 impl IntegerLinearConstraintTheory {
     pub fn new(activity_time_constant: f64) -> Self {
         Self {
             calculate_plbd: CalculatePLBD::default(),
+            calculate_propagation_level: CalculatePropagationLevel::new(),
             rows: Vec::default(),
             number_of_constraints: 0,
+            number_of_original_constraints: 0,
             columns: LiteralArray::default(),
             number_of_evaluated_assignments: 0,
             activity_time_constant,
             activity_increase_value: 1.0,
             backjump_count: 0,
             reducing_backjump_count: 3000,
+            reduce_db_criterion: ReduceDbCriterion::default(),
         }
     }
 
     pub fn number_of_constraints(&self) -> usize {
         return self.number_of_constraints;
     }
+
+    /// 削除した行を含む行の数 (次に追加する行の row_id)
+    pub fn number_of_rows(&self) -> usize {
+        return self.rows.len();
+    }
+
+    /// 削除されていない学習制約の数
+    pub fn number_of_learnt_constraints(&self) -> usize {
+        return self.number_of_constraints - self.number_of_original_constraints;
+    }
+
+    /// 学習制約の数が max_number_of_learnt_constraints 以下となるように，削除基準での順位が低いものから削除する
+    ///
+    /// 割り当ての理由となっている学習制約と，削除基準が削除の候補としない学習制約は削除しないため，
+    /// max_number_of_learnt_constraints 以下とならない場合がある
+    pub fn reduce_learnt_constraints(&mut self, max_number_of_learnt_constraints: usize) {
+        let number_of_learnt_constraints = self.number_of_learnt_constraints();
+        if number_of_learnt_constraints <= max_number_of_learnt_constraints {
+            return;
+        }
+        let mut rows = Vec::default();
+        for (row_id, row) in self.rows.iter().enumerate() {
+            if row.state == RowState::Learnt && row.reference_count == 0 {
+                rows.push((row_id, row.min_plbd, row.activity));
+            }
+        }
+        self.reduce_db_criterion.sort_candidates(&mut rows);
+        let number_of_deleted_rows = min(
+            number_of_learnt_constraints - max_number_of_learnt_constraints,
+            rows.len(),
+        );
+        for &(row_id, _, _) in rows[rows.len() - number_of_deleted_rows..].iter() {
+            self.delete_learnt_row(row_id);
+        }
+    }
+
+    /// 学習制約 row_id を削除の対象から外す
+    pub fn pin_row(&mut self, row_id: usize) {
+        let row = &mut self.rows[row_id];
+        debug_assert!(row.state == RowState::Learnt || row.state == RowState::Core);
+        row.state = RowState::Core;
+    }
+
+    fn delete_learnt_row(&mut self, row_id: usize) {
+        let row = &mut self.rows[row_id];
+        debug_assert!(row.state == RowState::Learnt);
+        row.state = RowState::Deleted;
+        row.terms.clear();
+        row.terms.shrink_to_fit();
+        self.number_of_constraints -= 1;
+    }
+
+    /// 削除されていない制約条件と，それが学習制約であるかを列挙する
+    pub fn iter_constraints(
+        &self,
+    ) -> impl Iterator<
+        Item = (
+            LinearConstraintView<Coefficient, impl Iterator<Item = (Literal, Coefficient)> + Clone + '_>,
+            bool,
+        ),
+    > + '_ {
+        return self
+            .rows
+            .iter()
+            .filter(|row| row.state != RowState::Deleted)
+            .map(|row| {
+                (
+                    LinearConstraintView::new(row.terms.iter().copied(), row.lower),
+                    row.state != RowState::Original,
+                )
+            });
+    }
+
+    /// 削除されていない各行の余裕 (左辺値の上界 - lower) を列挙する
+    pub fn iter_slacks(&self) -> impl Iterator<Item = Coefficient> + '_ {
+        return self
+            .rows
+            .iter()
+            .filter(|row| row.state != RowState::Deleted)
+            .map(|row| row.sup.saturating_sub(row.lower));
+    }
+
+    pub fn set_reduce_db_criterion(&mut self, reduce_db_criterion: ReduceDbCriterion) {
+        self.reduce_db_criterion = reduce_db_criterion;
+    }
+
+    /// 各行が保持している左辺値の上界と未割り当てリテラルの係数の最大値を再計算して検証する
+    pub fn check_invariants<ExplainKeyT: Copy>(&self, decision_stack: &DecisionStack<ExplainKeyT>) {
+        assert!(self.number_of_evaluated_assignments == decision_stack.number_of_assignments());
+        for (row_id, row) in self.rows.iter().enumerate() {
+            if row.state == RowState::Deleted {
+                continue;
+            }
+            let sup: Coefficient = row
+                .terms
+                .iter()
+                .filter(|&&(literal, _)| !decision_stack.is_false(literal))
+                .map(|&(_, coefficient)| coefficient)
+                .sum();
+            assert!(
+                row.sup == sup,
+                "integer linear constraint {}: cached sup {} differs from recomputed sup {}",
+                row_id,
+                row.sup,
+                sup
+            );
+            // max_unassigned_coefficient は遅延して更新されるため上界であればよい
+            let max_unassigned_coefficient = row
+                .terms
+                .iter()
+                .filter(|&&(literal, _)| !decision_stack.is_assigned(literal.index()))
+                .map(|&(_, coefficient)| coefficient)
+                .max()
+                .unwrap_or(0);
+            assert!(
+                row.max_unassigned_coefficient >= max_unassigned_coefficient,
+                "integer linear constraint {}: cached max_unassigned_coefficient {} is less than recomputed value {}",
+                row_id,
+                row.max_unassigned_coefficient,
+                max_unassigned_coefficient
+            );
+        }
+    }
+
+    pub fn explain_terms(
+        &self,
+        explain_key: IntegerLinearConstraintExplainKey,
+    ) -> impl Iterator<Item = (Literal, Coefficient)> + Clone + '_ {
+        return self.rows[explain_key.row_id].terms.iter().cloned();
+    }
+
+    pub fn explain_lower(&self, explain_key: IntegerLinearConstraintExplainKey) -> Coefficient {
+        return self.rows[explain_key.row_id].lower;
+    }
+
+    /// explain_key に対応する制約条件が割り当ての理由として記録されたことを通知する
+    ///
+    /// 参照されている間，その制約条件は削除されない
+    pub fn lock(&mut self, explain_key: IntegerLinearConstraintExplainKey) {
+        self.rows[explain_key.row_id].reference_count += 1;
+    }
+
+    /// lock で通知した割り当てが取り消されたことを通知する
+    pub fn unlock(&mut self, explain_key: IntegerLinearConstraintExplainKey) {
+        let row = &mut self.rows[explain_key.row_id];
+        debug_assert!(row.reference_count > 0);
+        row.reference_count -= 1;
+    }
 }
 
 impl TheoryTrait for IntegerLinearConstraintTheory {
     type ExplainKey = IntegerLinearConstraintExplainKey;
-    type ExplanationConstraint<'a> = impl LinearConstraintTrait<Value = u64> + 'a;
+    type ExplanationConstraint<'a> = impl LinearConstraintTrait<Value = Coefficient> + 'a;
 
     fn add_variable(&mut self) {
         self.columns.push([Column::default(), Column::default()]);
@@ -93,14 +257,17 @@ impl TheoryTrait for IntegerLinearConstraintTheory {
             if row.sup >= row.lower + row.max_unassigned_coefficient {
                 continue;
             }
-            let plbd = self.calculate_plbd.calculate(
-                row.terms
-                    .iter()
-                    .map(|&(literal, _)| !literal)
-                    .filter(|&literal| decision_stack.is_true(literal)),
-                decision_stack,
-            );
+            let plbd = self
+                .calculate_plbd
+                .calculate_for_constraint(&*row, decision_stack);
             row.min_plbd = min(row.min_plbd, plbd);
+            // PLBD が 2 以下となった学習制約は削除しない
+            if row.state == RowState::Learnt
+                && row.min_plbd <= 2
+                && self.reduce_db_criterion != ReduceDbCriterion::Activity
+            {
+                row.state = RowState::Core;
+            }
             row.activity += self.activity_increase_value;
 
             // 伝播
@@ -141,13 +308,13 @@ impl TheoryTrait for IntegerLinearConstraintTheory {
                 row.sup += coefficient;
                 assert!(row.sup >= row.lower);
                 row.max_unassigned_coefficient =
-                    u64::max(row.max_unassigned_coefficient, coefficient);
+                    Coefficient::max(row.max_unassigned_coefficient, coefficient);
             }
             // unassigned_literal を含む制約条件の未割り当てリテラルの係数の最大値を更新
             for &(row_id, coefficient) in self.columns[unassigned_literal].terms.iter() {
                 let row = &mut self.rows[row_id];
                 row.max_unassigned_coefficient =
-                    u64::max(row.max_unassigned_coefficient, coefficient);
+                    Coefficient::max(row.max_unassigned_coefficient, coefficient);
             }
         }
 
@@ -160,19 +327,15 @@ impl TheoryTrait for IntegerLinearConstraintTheory {
             let mut rows = Vec::default();
             for (row_id, row) in self.rows.iter_mut().enumerate() {
                 row.activity /= self.activity_increase_value;
-                if row.state == RowState::Learnt && row.min_plbd > 2 {
-                    rows.push((row_id, row.activity));
+                // 割り当ての理由となっている学習制約は削除しない
+                if row.state == RowState::Learnt && row.reference_count == 0 {
+                    rows.push((row_id, row.min_plbd, row.activity));
                 }
             }
             self.activity_increase_value = 1.0;
-            rows.sort_unstable_by(|lhs, rhs| rhs.1.partial_cmp(&lhs.1).unwrap());
-            for &(row_id, _) in rows.iter().skip(max(1000, rows.len() / 2)) {
-                let row = &mut self.rows[row_id];
-                debug_assert!(row.state == RowState::Learnt);
-                row.state = RowState::Deleted;
-                row.terms.clear();
-                row.terms.shrink_to_fit();
-                self.number_of_constraints -= 1;
+            self.reduce_db_criterion.sort_candidates(&mut rows);
+            for &(row_id, _, _) in rows.iter().skip(max(1000, rows.len() / 2)) {
+                self.delete_learnt_row(row_id);
             }
         }
     }
@@ -184,25 +347,57 @@ impl TheoryTrait for IntegerLinearConstraintTheory {
 
 impl<ConstraintT> TheoryAddConstraintTrait<ConstraintT> for IntegerLinearConstraintTheory
 where
-    ConstraintT: LinearConstraintTrait<Value = u64>,
+    ConstraintT: LinearConstraintTrait<Value = Coefficient>,
 {
     fn add_constraint<ExplainKeyT: Copy>(
         &mut self,
         constraint: ConstraintT,
         is_learnt: bool,
         decision_stack: &DecisionStack<ExplainKeyT>,
-        mut callback: impl FnMut(Propagation<Self::ExplainKey>),
-    ) -> Result<(), usize> {
-        if constraint.lower() <= 0 {
-            return Ok(());
+        callback: impl FnMut(Propagation<Self::ExplainKey>),
+    ) -> Result<Option<usize>, usize> {
+        if constraint.lower() == 0 {
+            return Ok(None);
         }
 
         let mut terms = Vec::from_iter(constraint.iter_terms());
-        let lower = constraint.lower();
 
         // 係数の降順にソート
         terms.sort_unstable_by(|l, r| r.1.partial_cmp(&l.1).unwrap());
 
+        return self.add_sorted_terms(terms, constraint.lower(), is_learnt, decision_stack, callback);
+    }
+}
+
+impl IntegerLinearConstraintTheory {
+    /// 係数の降順に並んだ制約条件を，ソートせずに追加する
+    ///
+    /// 呼び出し側で既に項を並べ替えている場合 (学習制約など) に用いる
+    pub fn add_constraint_presorted<ExplainKeyT: Copy>(
+        &mut self,
+        constraint: impl LinearConstraintTrait<Value = Coefficient>,
+        is_learnt: bool,
+        decision_stack: &DecisionStack<ExplainKeyT>,
+        callback: impl FnMut(Propagation<IntegerLinearConstraintExplainKey>),
+    ) -> Result<Option<usize>, usize> {
+        if constraint.lower() == 0 {
+            return Ok(None);
+        }
+
+        let terms = Vec::from_iter(constraint.iter_terms());
+        debug_assert!(terms.is_sorted_by(|l, r| l.1 >= r.1));
+
+        return self.add_sorted_terms(terms, constraint.lower(), is_learnt, decision_stack, callback);
+    }
+
+    fn add_sorted_terms<ExplainKeyT: Copy>(
+        &mut self,
+        terms: Vec<(Literal, Coefficient)>,
+        lower: Coefficient,
+        is_learnt: bool,
+        decision_stack: &DecisionStack<ExplainKeyT>,
+        mut callback: impl FnMut(Propagation<IntegerLinearConstraintExplainKey>),
+    ) -> Result<Option<usize>, usize> {
         // 左辺値の上界と未割り当てリテラルの係数の最大値を算出
         let mut sup = 0;
         let mut max_unassigned_coefficient = 0;
@@ -222,18 +417,21 @@ where
             }
         }
         assert!(sup >= lower);
-        // TODO Err を返す
-        assert!(
-            decision_stack.decision_level() == 0
-                || sup_at_previous_decision_level >= lower + max_unassigned_coefficient,
-            "{} {} {}",
-            sup_at_previous_decision_level,
-            lower,
-            max_unassigned_coefficient
-        );
+        // 現在の決定レベルより前に伝播が発生するならエラー
+        if decision_stack.decision_level() != 0
+            && sup_at_previous_decision_level < lower + max_unassigned_coefficient
+        {
+            let propagation_level = self
+                .calculate_propagation_level
+                .call(LinearConstraintView::new(terms.iter().copied(), lower), decision_stack, true)
+                .unwrap();
+            debug_assert!(propagation_level < decision_stack.decision_level());
+            return Err(propagation_level);
+        }
 
         // 制約条件を追加
         let row_id = self.rows.len();
+        let number_of_terms = terms.len();
         self.rows.push(Row {
             terms,
             lower,
@@ -242,12 +440,16 @@ where
             } else {
                 RowState::Original
             },
-            min_plbd: constraint.iter_terms().count(),
+            min_plbd: number_of_terms,
             activity: 0.0,
             sup,
             max_unassigned_coefficient,
+            reference_count: 0,
         });
         self.number_of_constraints += 1;
+        if !is_learnt {
+            self.number_of_original_constraints += 1;
+        }
         let row = self.rows.last_mut().unwrap();
 
         // 列方向の係数を追加
@@ -257,13 +459,9 @@ where
 
         // 追加した制約条件による伝播を実行
         if sup < lower + max_unassigned_coefficient {
-            let plbd = self.calculate_plbd.calculate(
-                row.terms
-                    .iter()
-                    .map(|&(literal, _)| !literal)
-                    .filter(|&literal| decision_stack.is_true(literal)),
-                decision_stack,
-            );
+            let plbd = self
+                .calculate_plbd
+                .calculate_for_constraint(&*row, decision_stack);
             row.min_plbd = plbd;
             row.activity += self.activity_increase_value;
 
@@ -283,7 +481,7 @@ where
             }
         }
 
-        return Ok(());
+        return Ok(Some(row_id));
     }
 }
 
@@ -291,32 +489,157 @@ where
 enum RowState {
     Original,
     Learnt,
+    /// PLBD が小さく，削除対象から外された学習制約
+    Core,
     Deleted,
 }
 
 #[derive(Clone, Debug)]
 struct Row {
-    terms: Vec<(Literal, u64)>,
-    lower: u64,
+    terms: Vec<(Literal, Coefficient)>,
+    lower: Coefficient,
     state: RowState,
     min_plbd: usize,
     activity: f64,
-    sup: u64,
-    max_unassigned_coefficient: u64,
+    sup: Coefficient,
+    max_unassigned_coefficient: Coefficient,
+    // この制約条件を理由とする割り当ての数
+    reference_count: usize,
 }
 
 impl LinearConstraintTrait for Row {
-    type Value = u64;
-    fn iter_terms(&self) -> impl Iterator<Item = (Literal, u64)> + Clone + '_ {
+    type Value = Coefficient;
+    fn iter_terms(&self) -> impl Iterator<Item = (Literal, Coefficient)> + Clone + '_ {
         self.terms.iter().cloned()
     }
 
-    fn lower(&self) -> u64 {
+    fn lower(&self) -> Coefficient {
         self.lower
     }
 }
 
 #[derive(Default, Clone, Debug)]
 struct Column {
-    terms: Vec<(usize, u64)>,
+    terms: Vec<(usize, Coefficient)>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::{IntegerLinearConstraintExplainKey, IntegerLinearConstraintTheory, RowState};
+    use crate::{
+        Boolean, LinearConstraint, Literal,
+        decision_stack::DecisionStack,
+        theories::{TheoryAddConstraintTrait, TheoryTrait},
+    };
+
+    #[test]
+    fn test_locked_row_is_not_reduced() {
+        let mut decision_stack = DecisionStack::<()>::default();
+        let mut theory = IntegerLinearConstraintTheory::new(1e4);
+        for _ in 0..3 {
+            decision_stack.add_variable(Boolean::FALSE);
+            theory.add_variable();
+        }
+
+        // x0 + x1 + 2 x2 >= 2 を学習制約として 1002 個追加し，後に追加したものほどアクティビティを小さくする
+        for k in 0..1002 {
+            theory
+                .add_constraint(
+                    LinearConstraint::new(
+                        [(0, 1), (1, 1), (2, 2)]
+                            .into_iter()
+                            .map(|(index, coefficient)| {
+                                (Literal::new(index, Boolean::TRUE), coefficient)
+                            }),
+                        2,
+                    ),
+                    true,
+                    &decision_stack,
+                    |_| unreachable!(),
+                )
+                .unwrap();
+            theory.rows[k].activity = (1002 - k) as f64;
+        }
+
+        // 最後の 2 個が削除対象となるが，参照されている row 1001 は削除されない
+        theory.lock(IntegerLinearConstraintExplainKey { row_id: 1001 });
+        theory.reducing_backjump_count = 0;
+        theory.backjump(0, &decision_stack);
+        assert!(theory.rows[1000].state == RowState::Deleted);
+        assert!(theory.rows[1001].state == RowState::Learnt);
+        assert!(theory.number_of_constraints() == 1001);
+
+        theory.unlock(IntegerLinearConstraintExplainKey { row_id: 1001 });
+        assert!(theory.rows[1001].reference_count == 0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "integer linear constraint 0: cached sup 5 differs from recomputed sup 4"
+    )]
+    fn test_check_invariants() {
+        let mut decision_stack = DecisionStack::<()>::default();
+        let mut theory = IntegerLinearConstraintTheory::new(1e4);
+        for _ in 0..3 {
+            decision_stack.add_variable(Boolean::FALSE);
+            theory.add_variable();
+        }
+        // x0 + x1 + 2 x2 >= 2
+        theory
+            .add_constraint(
+                LinearConstraint::new(
+                    [(0, 1), (1, 1), (2, 2)]
+                        .into_iter()
+                        .map(|(index, coefficient)| {
+                            (Literal::new(index, Boolean::TRUE), coefficient)
+                        }),
+                    2,
+                ),
+                false,
+                &decision_stack,
+                |_| unreachable!(),
+            )
+            .unwrap();
+        theory.check_invariants(&decision_stack);
+
+        // 保持している上界が再計算した値と異なれば panic する
+        theory.rows[0].sup += 1;
+        theory.check_invariants(&decision_stack);
+    }
+
+    #[test]
+    fn test_add_constraint_presorted() {
+        let mut decision_stack = DecisionStack::<()>::default();
+        let mut theory = IntegerLinearConstraintTheory::new(1e4);
+        for _ in 0..3 {
+            decision_stack.add_variable(Boolean::FALSE);
+            theory.add_variable();
+        }
+        let terms = [(2, 3), (1, 2), (0, 1)]
+            .map(|(index, coefficient)| (Literal::new(index, Boolean::TRUE), coefficient));
+
+        // 3 x2 + 2 x1 + x0 >= 4 から x2 が伝播する
+        let mut propagated = Vec::default();
+        theory
+            .add_constraint_presorted(
+                LinearConstraint::new(terms.into_iter(), 4),
+                false,
+                &decision_stack,
+                |propagation| propagated.push(propagation.literal),
+            )
+            .unwrap();
+        assert!(propagated == vec![Literal::new(2, Boolean::TRUE)]);
+
+        // ソートする場合と同じ行が追加される
+        theory
+            .add_constraint(
+                LinearConstraint::new(terms.into_iter().rev(), 4),
+                false,
+                &decision_stack,
+                |_| {},
+            )
+            .unwrap();
+        assert!(theory.rows[0].terms == theory.rows[1].terms);
+        assert!(theory.rows[0].sup == theory.rows[1].sup);
+    }
 }
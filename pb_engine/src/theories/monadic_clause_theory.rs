@@ -7,9 +7,16 @@ pub struct MonadicClauseExplainKey {
     monadic_clause: MonadicClause,
 }
 
+impl MonadicClauseExplainKey {
+    pub fn monadic_clause(&self) -> MonadicClause {
+        return self.monadic_clause;
+    }
+}
+
 #[derive(Clone)]
 pub struct MonadicClauseTheory {
-    monadic_clauses: Vec<MonadicClause>,
+    // 単位節と，学習制約であるか
+    monadic_clauses: Vec<(MonadicClause, bool)>,
     number_of_evaluated_assignments: usize,
 }
 
@@ -24,6 +31,11 @@ impl MonadicClauseTheory {
     pub fn number_of_monadic_clauses(&self) -> usize {
         return self.monadic_clauses.len();
     }
+
+    /// 保持している単位節と，それが学習制約であるかを列挙する
+    pub fn iter_constraints(&self) -> impl Iterator<Item = (MonadicClause, bool)> + '_ {
+        return self.monadic_clauses.iter().copied();
+    }
 }
 
 impl TheoryTrait for MonadicClauseTheory {
@@ -64,24 +76,27 @@ impl TheoryAddConstraintTrait<MonadicClause> for MonadicClauseTheory {
     fn add_constraint<ExplainKeyT: Copy>(
         &mut self,
         constraint: MonadicClause,
-        _is_learnt: bool,
+        is_learnt: bool,
         assignment_state: &DecisionStack<ExplainKeyT>,
         mut callback: impl FnMut(Propagation<Self::ExplainKey>),
-    ) -> Result<(), usize> {
+    ) -> Result<Option<usize>, usize> {
         assert!(assignment_state.number_of_assignments() == self.number_of_evaluated_assignments);
         if assignment_state.decision_level() != 0 {
             return Err(0);
         } else {
-            self.monadic_clauses.push(constraint);
+            self.monadic_clauses.push((constraint, is_learnt));
             assert!(!assignment_state.is_false(constraint.literal)); // TODO 後で考える
-            callback(Propagation {
-                literal: constraint.literal,
-                explain_key: MonadicClauseExplainKey {
-                    monadic_clause: constraint,
-                },
-                plbd: 0,
-            });
-            return Ok(());
+            // 既に True が割り当てられていれば伝播は不要
+            if !assignment_state.is_true(constraint.literal) {
+                callback(Propagation {
+                    literal: constraint.literal,
+                    explain_key: MonadicClauseExplainKey {
+                        monadic_clause: constraint,
+                    },
+                    plbd: 0,
+                });
+            }
+            return Ok(Some(self.monadic_clauses.len() - 1));
         }
     }
 }
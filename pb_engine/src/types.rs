@@ -2,4 +2,10 @@ mod boolean;
 mod literal;
 
 pub use boolean::Boolean;
-pub use literal::Literal;
+pub use literal::{Literal, ParseLiteralError};
+
+/// 係数と右辺値の型 (wide-coefficients フィーチャを有効にすると u128 となる)
+#[cfg(not(feature = "wide-coefficients"))]
+pub type Coefficient = u64;
+#[cfg(feature = "wide-coefficients")]
+pub type Coefficient = u128;
@@ -66,6 +66,14 @@ impl<T> IndexMut<Boolean> for [T; 2] {
 
 impl std::fmt::Display for Boolean {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", if *self == Boolean::FALSE { "F" } else { "T" })
+        write!(
+            f,
+            "{}",
+            if *self == Boolean::FALSE {
+                "false"
+            } else {
+                "true"
+            }
+        )
     }
 }
@@ -1,4 +1,4 @@
-use std::{hint::unreachable_unchecked, ops::Not};
+use std::{hint::unreachable_unchecked, ops::Not, str::FromStr};
 
 use super::boolean::Boolean;
 
@@ -33,10 +33,27 @@ impl Literal {
         };
     }
 
+    /// index と同じく，リテラルが表す変数のインデックスを返す
+    #[inline(always)]
+    pub fn variable(&self) -> usize {
+        return self.index();
+    }
+
+    #[inline(always)]
+    pub fn is_positive(&self) -> bool {
+        return self.value() == Boolean::TRUE;
+    }
+
     #[inline(always)]
     pub fn bits(&self) -> usize {
         self.bits
     }
+
+    /// DIMACS 形式の整数 (変数を 1 から数え，否定を負の数で表す) に変換する
+    pub fn to_dimacs(&self) -> i64 {
+        let variable = self.index() as i64 + 1;
+        return if self.is_positive() { variable } else { -variable };
+    }
 }
 
 impl Not for Literal {
@@ -69,3 +86,61 @@ impl std::fmt::Debug for Literal {
         <Self as std::fmt::Display>::fmt(self, f)
     }
 }
+
+/// Literal の文字列表現が不正であることを表すエラー
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ParseLiteralError;
+
+/// OPB 形式の変数 (x1 から始まる) または Display の出力 (l_0 から始まる) を読み取る
+///
+/// 否定は先頭の "-"，"~" または "!" で表す．例えば "x3"，"-x3"，"~x3" と "!l_2" はいずれも変数 2 のリテラルである
+impl FromStr for Literal {
+    type Err = ParseLiteralError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, s) = match s.strip_prefix(['-', '~', '!']) {
+            Some(s) => (Boolean::FALSE, s),
+            None => (Boolean::TRUE, s),
+        };
+        let index = if let Some(number) = s.strip_prefix('x') {
+            number
+                .parse::<usize>()
+                .ok()
+                .and_then(|number| number.checked_sub(1))
+        } else if let Some(number) = s.strip_prefix("l_") {
+            number.parse::<usize>().ok()
+        } else {
+            None
+        };
+        return index
+            .filter(|&index| ((index << 1) >> 1) == index)
+            .map(|index| Literal::new(index, value))
+            .ok_or(ParseLiteralError);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Literal, ParseLiteralError};
+    use crate::Boolean;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("x3".parse(), Ok(Literal::new(2, Boolean::TRUE)));
+        assert_eq!("-x3".parse(), Ok(Literal::new(2, Boolean::FALSE)));
+        assert_eq!("~x3".parse(), Ok(Literal::new(2, Boolean::FALSE)));
+        assert_eq!("x0".parse::<Literal>(), Err(ParseLiteralError));
+        assert_eq!("x".parse::<Literal>(), Err(ParseLiteralError));
+        assert_eq!("--x3".parse::<Literal>(), Err(ParseLiteralError));
+
+        // Display の出力から元のリテラルに戻る
+        for literal in [
+            Literal::new(0, Boolean::TRUE),
+            Literal::new(5, Boolean::FALSE),
+        ] {
+            assert_eq!(literal.to_string().parse(), Ok(literal));
+        }
+        assert!(Literal::new(5, Boolean::FALSE).variable() == 5);
+        assert!(!Literal::new(5, Boolean::FALSE).is_positive());
+    }
+}
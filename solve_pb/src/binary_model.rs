@@ -0,0 +1,39 @@
+use std::io::Write;
+
+use pb_engine::Boolean;
+
+/// 解を変数の数 (u64 リトルエンディアン) と，x1 から順に 1 ビットずつ詰めたビット列で書き出す
+///
+/// 各バイトの下位ビットから順に詰め，x(i+1) が True であれば i ビット目を 1 とする
+pub fn write_binary_model(output: &mut impl Write, solution: &[Boolean]) -> std::io::Result<()> {
+    output.write_all(&(solution.len() as u64).to_le_bytes())?;
+    for chunk in solution.chunks(8) {
+        let mut byte = 0u8;
+        for (bit, &value) in chunk.iter().enumerate() {
+            if value == Boolean::TRUE {
+                byte |= 1 << bit;
+            }
+        }
+        output.write_all(&[byte])?;
+    }
+    return Ok(());
+}
+
+/// write_binary_model で書き出した解を読み込む (テストで書き出した内容を確認するために用いる)
+#[cfg(test)]
+pub fn read_binary_model(input: &mut impl std::io::Read) -> std::io::Result<Vec<Boolean>> {
+    let mut header = [0u8; 8];
+    input.read_exact(&mut header)?;
+    let number_of_variables = u64::from_le_bytes(header) as usize;
+    let mut bytes = vec![0u8; number_of_variables.div_ceil(8)];
+    input.read_exact(&mut bytes)?;
+    return Ok((0..number_of_variables)
+        .map(|index| {
+            if bytes[index / 8] >> (index % 8) & 1 == 1 {
+                Boolean::TRUE
+            } else {
+                Boolean::FALSE
+            }
+        })
+        .collect());
+}
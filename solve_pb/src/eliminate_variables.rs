@@ -0,0 +1,293 @@
+use pb_engine::{Boolean, Literal};
+
+use crate::read_opb::{Constraint, RelationalOperator, Variable, WeightedTerm};
+
+/// 変数を消去する際に試す正負の出現数の積の上限
+const MAX_NUMBER_OF_RESOLUTIONS: usize = 400;
+/// 追加する導出節の長さの上限
+const MAX_RESOLVENT_LENGTH: usize = 20;
+
+/// 節にのみ現れる変数を消去した結果
+pub struct VariableElimination {
+    /// 消去されなかった制約条件と，消去によって追加された導出節
+    pub constraints: Vec<Constraint>,
+    /// 消去した変数のインデックス (0 から始まる) と，消去の際に取り除いた節 (消去した順)
+    eliminated: Vec<(usize, Vec<Vec<Literal>>)>,
+}
+
+impl VariableElimination {
+    pub fn number_of_eliminated_variables(&self) -> usize {
+        return self.eliminated.len();
+    }
+
+    /// 消去後の問題の解を元の問題の解に拡張する
+    ///
+    /// 消去した逆順に，取り除いた節のうち他のリテラルで充足されないものがあれば変数の値を決める
+    pub fn extend_solution(&self, solution: &mut [Boolean]) {
+        for (index, removed_clauses) in self.eliminated.iter().rev() {
+            solution[*index] = Boolean::FALSE;
+            let is_satisfied = |clause: &Vec<Literal>| {
+                clause
+                    .iter()
+                    .any(|&literal| solution[literal.index()] == literal.value())
+            };
+            if !removed_clauses.iter().all(is_satisfied) {
+                solution[*index] = Boolean::TRUE;
+            }
+        }
+    }
+}
+
+/// 有界変数消去 (bounded variable elimination)
+///
+/// 節にのみ現れ，frozen_variables (目的関数の変数など) に含まれない変数 x について，
+/// x を含む節と ¬x を含む節の全ての組の導出節が元の節の数を超えなければ，元の節を導出節で置き換える
+pub fn eliminate_variables<'a>(
+    constraints: &[Constraint],
+    frozen_variables: impl Iterator<Item = &'a Variable>,
+) -> VariableElimination {
+    let mut frozen = Vec::default();
+    let freeze = |frozen: &mut Vec<bool>, index: usize| {
+        if index >= frozen.len() {
+            frozen.resize(index + 1, false);
+        }
+        frozen[index] = true;
+    };
+    for variable in frozen_variables {
+        freeze(&mut frozen, variable.index - 1);
+    }
+
+    // 節とそれ以外の制約条件に分ける
+    let mut clauses: Vec<Option<Vec<Literal>>> = Vec::default();
+    let mut other_constraints = Vec::default();
+    for constraint in constraints {
+        if let Some(clause) = as_clause(constraint) {
+            clauses.push(Some(clause));
+        } else {
+            for weighted_term in constraint.sum.iter() {
+                freeze(&mut frozen, weighted_term.term.index - 1);
+            }
+            other_constraints.push(constraint.clone());
+        }
+    }
+
+    // 各リテラルを含む節の番号
+    let mut occurrences: Vec<[Vec<usize>; 2]> = Vec::default();
+    let add_occurrences =
+        |occurrences: &mut Vec<[Vec<usize>; 2]>, clause_index: usize, clause: &Vec<Literal>| {
+            for &literal in clause.iter() {
+                if literal.index() >= occurrences.len() {
+                    occurrences.resize(literal.index() + 1, Default::default());
+                }
+                occurrences[literal.index()][literal.value()].push(clause_index);
+            }
+        };
+    for (clause_index, clause) in clauses.iter().enumerate() {
+        add_occurrences(&mut occurrences, clause_index, clause.as_ref().unwrap());
+    }
+
+    let mut eliminated = Vec::default();
+    let mut is_changed = true;
+    while is_changed {
+        is_changed = false;
+        for index in 0..occurrences.len() {
+            if frozen.get(index).copied().unwrap_or(false) {
+                continue;
+            }
+            // 取り除かれた節の番号を除く
+            for occurrence in occurrences[index].iter_mut() {
+                occurrence.retain(|&clause_index| clauses[clause_index].is_some());
+            }
+            let [negative, positive] = &occurrences[index];
+            if negative.is_empty() && positive.is_empty() {
+                continue;
+            }
+            if negative.len() * positive.len() > MAX_NUMBER_OF_RESOLUTIONS {
+                continue;
+            }
+
+            let mut resolvents = Vec::default();
+            let mut is_bounded = true;
+            'resolve: for &positive_index in positive.iter() {
+                for &negative_index in negative.iter() {
+                    let Some(resolvent) = resolve(
+                        clauses[positive_index].as_ref().unwrap(),
+                        clauses[negative_index].as_ref().unwrap(),
+                        index,
+                    ) else {
+                        continue;
+                    };
+                    if resolvent.len() > MAX_RESOLVENT_LENGTH
+                        || resolvents.len() >= negative.len() + positive.len()
+                    {
+                        is_bounded = false;
+                        break 'resolve;
+                    }
+                    resolvents.push(resolvent);
+                }
+            }
+            if !is_bounded {
+                continue;
+            }
+
+            // 元の節を取り除き，導出節を追加する
+            let removed_clauses = Vec::from_iter(
+                negative
+                    .iter()
+                    .chain(positive.iter())
+                    .map(|&clause_index| clauses[clause_index].take().unwrap()),
+            );
+            occurrences[index] = Default::default();
+            for resolvent in resolvents {
+                add_occurrences(&mut occurrences, clauses.len(), &resolvent);
+                clauses.push(Some(resolvent));
+            }
+            eliminated.push((index, removed_clauses));
+            is_changed = true;
+        }
+    }
+
+    return VariableElimination {
+        constraints: other_constraints
+            .into_iter()
+            .chain(
+                clauses
+                    .into_iter()
+                    .flatten()
+                    .map(|clause| to_constraint(&clause)),
+            )
+            .collect(),
+        eliminated,
+    };
+}
+
+/// 係数が ±1 で変数が重複しない節 (sum(literals) >= 1) であればリテラルの列を返す
+fn as_clause(constraint: &Constraint) -> Option<Vec<Literal>> {
+    if constraint.relational_operator != RelationalOperator::GreaterOrEqual {
+        return None;
+    }
+    let mut clause = Vec::default();
+    let mut number_of_negative_literals = 0;
+    for weighted_term in constraint.sum.iter() {
+        let value = match weighted_term.weight {
+            1 => Boolean::TRUE,
+            -1 => Boolean::FALSE,
+            _ => return None,
+        };
+        if value == Boolean::FALSE {
+            number_of_negative_literals += 1;
+        }
+        let literal = Literal::new(weighted_term.term.index - 1, value);
+        if clause
+            .iter()
+            .any(|other: &Literal| other.index() == literal.index())
+        {
+            return None;
+        }
+        clause.push(literal);
+    }
+    if constraint.rhs + number_of_negative_literals != 1 {
+        return None;
+    }
+    return Some(clause);
+}
+
+fn to_constraint(clause: &[Literal]) -> Constraint {
+    let number_of_negative_literals = clause
+        .iter()
+        .filter(|literal| literal.value() == Boolean::FALSE)
+        .count() as i64;
+    return Constraint {
+        sum: clause
+            .iter()
+            .map(|literal| WeightedTerm {
+                weight: if literal.value() == Boolean::TRUE {
+                    1
+                } else {
+                    -1
+                },
+                term: Variable {
+                    index: literal.index() + 1,
+                },
+            })
+            .collect(),
+        relational_operator: RelationalOperator::GreaterOrEqual,
+        rhs: 1 - number_of_negative_literals,
+    };
+}
+
+/// 変数 index について positive と negative の導出節を求める (恒真であれば None)
+fn resolve(positive: &[Literal], negative: &[Literal], index: usize) -> Option<Vec<Literal>> {
+    let mut resolvent = Vec::from_iter(
+        positive
+            .iter()
+            .copied()
+            .filter(|literal| literal.index() != index),
+    );
+    for &literal in negative.iter().filter(|literal| literal.index() != index) {
+        if resolvent.contains(&!literal) {
+            return None;
+        }
+        if !resolvent.contains(&literal) {
+            resolvent.push(literal);
+        }
+    }
+    return Some(resolvent);
+}
+
+#[cfg(test)]
+mod test {
+    use super::eliminate_variables;
+    use crate::read_opb::{Constraint, RelationalOperator, Variable, WeightedTerm};
+    use pb_engine::Boolean;
+
+    fn clause(literals: &[i64]) -> Constraint {
+        return Constraint {
+            sum: literals
+                .iter()
+                .map(|&literal| WeightedTerm {
+                    weight: literal.signum(),
+                    term: Variable {
+                        index: literal.unsigned_abs() as usize,
+                    },
+                })
+                .collect(),
+            relational_operator: RelationalOperator::GreaterOrEqual,
+            rhs: 1 - literals.iter().filter(|&&literal| literal < 0).count() as i64,
+        };
+    }
+
+    #[test]
+    fn test_eliminate_chain() {
+        // x1, x1 → x2, x2 → x3, x3 → x4 は全ての変数が消去される
+        let constraints = [
+            clause(&[1]),
+            clause(&[-1, 2]),
+            clause(&[-2, 3]),
+            clause(&[-3, 4]),
+        ];
+        let elimination = eliminate_variables(&constraints, [].iter());
+        assert!(elimination.number_of_eliminated_variables() == 4);
+        assert!(elimination.constraints.is_empty());
+
+        let mut solution = vec![Boolean::FALSE; 4];
+        elimination.extend_solution(&mut solution);
+        assert!(solution == vec![Boolean::TRUE; 4]);
+
+        // 固定した変数と，節以外の制約条件に現れる変数は消去しない
+        let mut constraints = constraints.to_vec();
+        constraints.push(Constraint {
+            sum: vec![WeightedTerm {
+                weight: 2,
+                term: Variable { index: 2 },
+            }],
+            relational_operator: RelationalOperator::GreaterOrEqual,
+            rhs: 1,
+        });
+        let elimination = eliminate_variables(&constraints, [Variable { index: 4 }].iter());
+        assert!(elimination.number_of_eliminated_variables() == 2);
+        let mut solution = vec![Boolean::FALSE, Boolean::TRUE, Boolean::TRUE, Boolean::TRUE];
+        elimination.extend_solution(&mut solution);
+        assert!(solution == vec![Boolean::TRUE; 4]);
+    }
+}
@@ -0,0 +1,139 @@
+use std::cmp::max;
+
+use num::Integer;
+
+use crate::read_opb::{Constraint, RelationalOperator, WeightedTerm};
+
+/// 目的関数の最小値の下界
+///
+/// 各制約条件を 1 つずつ取り出し，その制約条件のみを課した連続緩和 (分数ナップサック問題) を貪欲に解いた最適値のうち
+/// 最大のものを返す．全ての制約条件を同時に課した LP 緩和より弱いが，常に元の問題の最適値以下となる
+pub fn lp_lower_bound(objective: &[WeightedTerm], constraints: &[Constraint]) -> i128 {
+    // 変数ごとの目的関数の係数 (0 から始まるインデックス)
+    let mut costs: Vec<i128> = Vec::default();
+    for weighted_term in objective {
+        let index = weighted_term.term.index - 1;
+        if index >= costs.len() {
+            costs.resize(index + 1, 0);
+        }
+        costs[index] += weighted_term.weight as i128;
+    }
+    let cost = |index: usize| costs.get(index).copied().unwrap_or(0);
+    // 制約条件がなければ係数が負の変数のみを True とするのが最適
+    let unconstrained = costs.iter().filter(|&&cost| cost < 0).sum::<i128>();
+
+    let mut lower_bound = unconstrained;
+    for constraint in constraints {
+        // >= と (= の場合は) <=
+        for sign in match constraint.relational_operator {
+            RelationalOperator::GreaterOrEqual => [1].as_slice(),
+            RelationalOperator::Equal => [1, -1].as_slice(),
+        } {
+            // 同じ変数の項をまとめる
+            let mut terms = Vec::from_iter(constraint.sum.iter().map(|weighted_term| {
+                (
+                    weighted_term.term.index - 1,
+                    sign * weighted_term.weight as i128,
+                )
+            }));
+            terms.sort_unstable_by_key(|&(index, _)| index);
+            terms.dedup_by(|(index, weight), (other_index, other_weight)| {
+                if index == other_index {
+                    *other_weight += *weight;
+                }
+                index == other_index
+            });
+
+            // 各変数を目的関数にとって最良の値とした上で，左辺値の不足分を費用対効果の高い順に補う
+            let mut deficit = sign * constraint.rhs as i128;
+            let mut items = Vec::default();
+            for &(index, weight) in terms.iter() {
+                let cost = cost(index);
+                let (delta, additional_cost) = if cost < 0 {
+                    deficit -= weight;
+                    (-weight, -cost)
+                } else {
+                    (weight, cost)
+                };
+                if delta > 0 {
+                    items.push((delta, additional_cost));
+                }
+            }
+            if deficit <= 0 {
+                continue;
+            }
+            items.sort_unstable_by(|&(delta, cost), &(other_delta, other_cost)| {
+                (cost * other_delta).cmp(&(other_cost * delta))
+            });
+            let mut bound = unconstrained;
+            for (delta, cost) in items {
+                if deficit <= delta {
+                    // 分数の割り当てで補い，目的関数値は整数であるため切り上げる
+                    bound += Integer::div_ceil(&(cost * deficit), &delta);
+                    deficit = 0;
+                    break;
+                }
+                bound += cost;
+                deficit -= delta;
+            }
+            // 充足不能な制約条件は下界の算出に用いない
+            if deficit > 0 {
+                continue;
+            }
+            lower_bound = max(lower_bound, bound);
+        }
+    }
+    return lower_bound;
+}
+
+#[cfg(test)]
+mod test {
+    use super::lp_lower_bound;
+    use crate::read_opb::{RelationalOperator, read_opb};
+
+    #[test]
+    fn test_lp_lower_bound() {
+        let input = "min: +2 x1 +3 x2 +1 x3 -1 x4 ;\n\
+                     +1 x1 +1 x2 >= 1 ;\n\
+                     +3 x1 +1 x2 +2 x3 >= 5 ;\n\
+                     +1 x4 = 0 ;\n";
+        let pb_problem = read_opb(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+        let objective = &pb_problem.objectives[0];
+        let lower_bound = lp_lower_bound(objective, &pb_problem.constraints);
+        // 2 番目の制約条件のみの連続緩和: x3 = 1, x1 = 1 で不足分が埋まり，x4 = 1 と合わせて 1 + 2 - 1 = 2
+        assert!(lower_bound == 2);
+
+        // 全ての割り当てを列挙して求めた最適値 (x1 = x3 = True で 3) を超えない
+        let optimum = (0..1 << 4)
+            .filter(|assignment| {
+                pb_problem.constraints.iter().all(|constraint| {
+                    let lhs = constraint
+                        .sum
+                        .iter()
+                        .filter(|weighted_term| {
+                            assignment >> (weighted_term.term.index - 1) & 1 == 1
+                        })
+                        .map(|weighted_term| weighted_term.weight)
+                        .sum::<i64>();
+                    match constraint.relational_operator {
+                        RelationalOperator::GreaterOrEqual => lhs >= constraint.rhs,
+                        RelationalOperator::Equal => lhs == constraint.rhs,
+                    }
+                })
+            })
+            .map(|assignment| {
+                objective
+                    .iter()
+                    .filter(|weighted_term| assignment >> (weighted_term.term.index - 1) & 1 == 1)
+                    .map(|weighted_term| weighted_term.weight as i128)
+                    .sum::<i128>()
+            })
+            .min()
+            .unwrap();
+        assert!(optimum == 3);
+        assert!(lower_bound <= optimum);
+
+        // 制約条件がなければ係数が負の変数の和
+        assert!(lp_lower_bound(objective, &[]) == -1);
+    }
+}
@@ -1,197 +1,941 @@
 #![feature(float_erf)]
 
+mod binary_model;
+mod eliminate_variables;
+mod lp_lower_bound;
+mod objective_reporter;
 mod plbd_watcher;
+mod read_dimacs;
 mod read_opb;
 
-use std::{io::BufReader, usize};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    sync::{Arc, atomic::AtomicBool},
+    time::{Duration, Instant},
+};
 
+use binary_model::write_binary_model;
+use eliminate_variables::eliminate_variables;
+use lp_lower_bound::lp_lower_bound;
+use objective_reporter::{
+    IgnoringObjectiveReporter, NegatedObjectiveReporter, ObjectiveReporter, StdoutObjectiveReporter,
+};
 use pb_engine::{
-    Analyze, AnalyzeResult, Boolean, CalculatePLBD, CountConstraintView, LinearConstraintTrait,
-    LinearConstraintView, Literal, MonadicClause, PBEngine, PBState,
-    strengthen_integer_linear_constraint,
+    Analyze, AnalyzeResult, Boolean, Coefficient, ConstraintError, CountConstraint,
+    CountConstraintTrait, CountConstraintView, DratLogger, LinearConstraint, LinearConstraintTrait,
+    Literal, MonadicClause, PBEngine, PBExplainKey, PBState, strengthen_integer_linear_constraint,
 };
 use plbd_watcher::PLBDWatcher;
-use read_opb::{PBProblem, RelationalOperator, read_opb};
+use read_dimacs::read_dimacs;
+use read_opb::{
+    Constraint, PBProblem, ParseError, RelationalOperator, Variable, WboProblem, WeightedTerm,
+    read_opb, read_opb_batch, read_wbo,
+};
+
+/// ソルバの動作を切り替えるオプション
+#[derive(Clone)]
+struct SolveOptions {
+    /// cutting-plane による学習の代わりに first-UIP 節を学習する (--clausal-learning)
+    clausal_learning: bool,
+    /// リスタートの判断に用いる PLBD の短期平均の窓の大きさ (--plbd-short-window=N)
+    plbd_short_window: usize,
+    /// リスタートの判断に用いる PLBD の長期の統計量の時定数 (--plbd-long-window=N)
+    plbd_long_window: usize,
+    /// 学習制約の項数の上限 (--max-learnt-terms=N)
+    max_learnt_terms: Option<usize>,
+    /// 学習制約の数の上限 (--max-learnt=N)
+    max_learnt: Option<usize>,
+    /// --clausal-learning で学習する節を最小化する (--minimize-clauses)
+    minimize_clauses: bool,
+    /// 学習制約を最後に False が割り当てられたリテラルを伝播するように weaken する (--target-weakening)
+    target_weakening: bool,
+    /// 節を学習するたびに，その節が包含する学習した節を削除する (--subsumption)
+    subsumption: bool,
+    /// 節のみからなる問題を --clausal-learning で解くときに DRAT 証明を書き出すファイル (--drat=PATH)
+    drat_path: Option<String>,
+    /// 右辺値と飽和しない係数の和が等しい整数線形制約を節に符号化する (--no-sat-encoding で無効)
+    sat_encoding: bool,
+    /// 節にのみ現れる変数を前処理で消去する (--eliminate-variables)
+    eliminate_variables: bool,
+    /// 変数の初期値として用いる部分割り当て (OPB 形式の "* hint:" 行)
+    hint: Vec<Literal>,
+    /// 変数のアクティビティの初期値を制約条件に現れる回数に比例させる (--seed-activities)
+    seed_activities: bool,
+    /// 連続緩和による目的関数の下界を求め，下界に達した解が得られた時点で最適とする (--lp-bound)
+    lp_bound: bool,
+    /// 問題ごとの探索の制限時間 (--timeout SECS)
+    timeout: Option<Duration>,
+    /// 探索を打ち切る時刻 (start_timer で timeout から求める)
+    deadline: Option<Instant>,
+    /// 矛盾の数がこの値に達したら探索を打ち切る (--max-conflicts=N)
+    max_conflicts: Option<usize>,
+    /// 他のスレッドから探索を打ち切るためのフラグ
+    cancellation: Option<Arc<AtomicBool>>,
+    /// 解の出力形式 (--model-format FORMAT)
+    model_format: ModelFormat,
+    /// --model-format binary で解を書き出すファイル (--model-file=PATH)
+    model_path: Option<String>,
+    /// 解く前に変数の次数 (現れる制約条件の数) の分布を出力する (--degree-histogram)
+    degree_histogram: bool,
+    /// 入力を BATCH_SEPARATOR の行で区切られた複数の OPB 形式の問題として順に解く (--batch)
+    batch: bool,
+    /// 入力ファイル (省略した場合と - の場合は標準入力)
+    input_path: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ModelFormat {
+    /// "v x1 -x2 ..." 行として標準出力に出力する
+    Text,
+    /// write_binary_model の形式でファイルに書き出す
+    Binary,
+}
+
+const USAGE: &str = "usage: solve_pb [OPTIONS] [FILE | -]
+
+options:
+    --timeout SECS            give up after SECS seconds (prints the best solution found so far)
+    --max-conflicts=N         give up after N conflicts (prints the best solution found so far)
+    --opt                     minimize the objective function if present (default)
+    --lp-bound                report a lower bound of the objective and stop when it is reached
+    --clausal-learning        learn first-UIP clauses instead of cutting planes
+    --minimize-clauses        minimize clauses learnt with --clausal-learning
+    --target-weakening        weaken learnt constraints to propagate the last falsified literal
+    --subsumption             delete learnt clauses subsumed by a newly learnt clause
+    --degree-histogram        print how many constraints each variable occurs in (OPB only)
+    --batch                   solve OPB instances separated by \"* ---\" lines one by one
+    --eliminate-variables     eliminate variables occurring only in clauses
+    --no-sat-encoding         keep every integer linear constraint as a single constraint
+    --seed-activities         branch first on variables occurring in many constraints
+    --plbd-short-window=N     short-term window of the PLBD restart criterion
+    --plbd-long-window=N      long-term window of the PLBD restart criterion
+    --max-learnt-terms=N      upper bound of the number of terms of learnt constraints
+    --max-learnt=N            upper bound of the number of learnt constraints
+    --drat=PATH               write a DRAT proof (CNF with --clausal-learning only)
+    --model-format FORMAT     print the model as text (default) or write it as a binary bitset
+    --model-file=PATH         file the binary model is written to";
+
+impl Default for SolveOptions {
+    fn default() -> Self {
+        Self {
+            clausal_learning: false,
+            plbd_short_window: 10,
+            plbd_long_window: 10000,
+            max_learnt_terms: None,
+            max_learnt: None,
+            minimize_clauses: false,
+            target_weakening: false,
+            subsumption: false,
+            drat_path: None,
+            sat_encoding: true,
+            eliminate_variables: false,
+            hint: Vec::default(),
+            seed_activities: false,
+            lp_bound: false,
+            timeout: None,
+            deadline: None,
+            max_conflicts: None,
+            cancellation: None,
+            model_format: ModelFormat::Text,
+            model_path: None,
+            degree_histogram: false,
+            batch: false,
+            input_path: None,
+        }
+    }
+}
+
+impl SolveOptions {
+    /// コマンドライン引数を解釈する (不明な引数があれば Err でその引数を返す)
+    fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self::default();
+        while let Some(arg) = args.next() {
+            if arg == "--timeout" || arg.starts_with("--timeout=") {
+                let value = match arg.strip_prefix("--timeout=") {
+                    Some(value) => Some(value.to_string()),
+                    None => args.next(),
+                };
+                let Some(Ok(seconds)) = value.as_deref().map(str::parse::<f64>) else {
+                    return Err(arg);
+                };
+                let Ok(timeout) = Duration::try_from_secs_f64(seconds) else {
+                    return Err(arg);
+                };
+                options.timeout = Some(timeout);
+            } else if arg == "--model-format" || arg.starts_with("--model-format=") {
+                let value = match arg.strip_prefix("--model-format=") {
+                    Some(value) => Some(value.to_string()),
+                    None => args.next(),
+                };
+                options.model_format = match value.as_deref() {
+                    Some("text") => ModelFormat::Text,
+                    Some("binary") => ModelFormat::Binary,
+                    _ => return Err(arg),
+                };
+            } else if let Some(model_path) = arg.strip_prefix("--model-file=") {
+                options.model_path = Some(model_path.to_string());
+            } else if arg == "--opt" {
+                // 目的関数があれば常に最小化する
+            } else if arg == "--seed-activities" {
+                options.seed_activities = true;
+            } else if arg == "--lp-bound" {
+                options.lp_bound = true;
+            } else if arg == "--clausal-learning" {
+                options.clausal_learning = true;
+            } else if arg == "--minimize-clauses" {
+                options.minimize_clauses = true;
+            } else if arg == "--target-weakening" {
+                options.target_weakening = true;
+            } else if arg == "--subsumption" {
+                options.subsumption = true;
+            } else if arg == "--no-sat-encoding" {
+                options.sat_encoding = false;
+            } else if arg == "--degree-histogram" {
+                options.degree_histogram = true;
+            } else if arg == "--batch" {
+                options.batch = true;
+            } else if arg == "--eliminate-variables" {
+                options.eliminate_variables = true;
+            } else if let Some(Ok(window @ 1..)) = arg
+                .strip_prefix("--plbd-short-window=")
+                .map(str::parse::<usize>)
+            {
+                options.plbd_short_window = window;
+            } else if let Some(Ok(window @ 1..)) = arg
+                .strip_prefix("--plbd-long-window=")
+                .map(str::parse::<usize>)
+            {
+                options.plbd_long_window = window;
+            } else if let Some(Ok(max_learnt_terms)) = arg
+                .strip_prefix("--max-learnt-terms=")
+                .map(str::parse)
+            {
+                options.max_learnt_terms = Some(max_learnt_terms);
+            } else if let Some(Ok(max_learnt)) =
+                arg.strip_prefix("--max-learnt=").map(str::parse)
+            {
+                options.max_learnt = Some(max_learnt);
+            } else if let Some(Ok(max_conflicts)) =
+                arg.strip_prefix("--max-conflicts=").map(str::parse)
+            {
+                options.max_conflicts = Some(max_conflicts);
+            } else if let Some(drat_path) = arg.strip_prefix("--drat=") {
+                options.drat_path = Some(drat_path.to_string());
+            } else if (arg == "-" || !arg.starts_with("-")) && options.input_path.is_none() {
+                options.input_path = Some(arg);
+            } else {
+                return Err(arg);
+            }
+        }
+        // バイナリ形式の解は書き出すファイルが必要
+        if options.model_format == ModelFormat::Binary && options.model_path.is_none() {
+            return Err("--model-format".to_string());
+        }
+        // 複数の問題の解を 1 つのファイルに書き出すことはできない
+        if options.model_format == ModelFormat::Binary && options.batch {
+            return Err("--batch".to_string());
+        }
+        // 複数の問題の DRAT 証明を 1 つのファイルに書き出すことはできない
+        if options.drat_path.is_some() && options.batch {
+            return Err("--batch".to_string());
+        }
+        return Ok(options);
+    }
+
+    /// timeout が指定されていれば，現在の時刻から timeout 後を deadline とする (問題ごとに呼び出す)
+    fn start_timer(&mut self) {
+        if let Some(timeout) = self.timeout {
+            self.deadline = Instant::now().checked_add(timeout);
+        }
+    }
+}
 
 enum Status {
-    Satisfiable{solution: Vec<Boolean>},
+    Satisfiable {
+        solution: Vec<Boolean>,
+    },
+    Optimum {
+        solution: Vec<Boolean>,
+        objective_value: i128,
+    },
+    /// 複数の目的関数を辞書式順序で最小化した最適解
+    LexicographicOptimum {
+        solution: Vec<Boolean>,
+        objective_values: Vec<i128>,
+    },
     Unsatisfiable,
     Indefinite,
 }
 
-fn main() {
-    if let Some(pb_problem) = read_opb(&mut BufReader::new(std::io::stdin())) {
-        let status = solve(&pb_problem);
-        match status {
-            Status::Satisfiable{solution} => {
-                println!("s SATISFIABLE");
-                print!("v");
-                for (index, &value) in solution.iter().enumerate() {
-                    match value {
-                        Boolean::TRUE => {
-                            print!(" x{}", index + 1);
-                        },
-                        Boolean::FALSE => {
-                            print!(" -x{}", index + 1);
-                        }
-                    }
+impl Status {
+    /// 解を持つ場合に f で書き換える
+    fn map_solution(self, f: impl FnOnce(&mut Vec<Boolean>)) -> Self {
+        return match self {
+            Status::Satisfiable { mut solution } => {
+                f(&mut solution);
+                Status::Satisfiable { solution }
+            }
+            Status::Optimum {
+                mut solution,
+                objective_value,
+            } => {
+                f(&mut solution);
+                Status::Optimum {
+                    solution,
+                    objective_value,
+                }
+            }
+            Status::LexicographicOptimum {
+                mut solution,
+                objective_values,
+            } => {
+                f(&mut solution);
+                Status::LexicographicOptimum {
+                    solution,
+                    objective_values,
                 }
-                println!("");
             }
-            Status::Unsatisfiable => {
-                println!("s UNSATISFIABLE");
+            status => status,
+        };
+    }
+
+    /// 目的関数値の符号を反転する (最大化問題を最小化問題として解いた結果を元に戻す)
+    fn negate_objective_values(self) -> Self {
+        return match self {
+            Status::Optimum {
+                solution,
+                objective_value,
+            } => Status::Optimum {
+                solution,
+                objective_value: -objective_value,
+            },
+            Status::LexicographicOptimum {
+                solution,
+                objective_values,
+            } => Status::LexicographicOptimum {
+                solution,
+                objective_values: objective_values.into_iter().map(|value| -value).collect(),
+            },
+            status => status,
+        };
+    }
+}
+
+fn main() {
+    let mut options = match SolveOptions::from_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(arg) => {
+            eprintln!("unknown or malformed argument: {}", arg);
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    let mut input: BufReader<Box<dyn Read>> = match options.input_path.as_deref() {
+        None | Some("-") => BufReader::new(Box::new(std::io::stdin())),
+        Some(path) => match std::fs::File::open(path) {
+            Ok(file) => BufReader::new(Box::new(file)),
+            Err(error) => {
+                println!("c cannot open {}: {}", path, error);
+                println!("s UNSUPPORTED");
+                return;
             }
-            Status::Indefinite => {
-                println!("s UNKNOWN");
+        },
+    };
+    if options.batch {
+        for (index, pb_problem) in read_opb_batch(&mut input).enumerate() {
+            println!("c instance {}", index + 1);
+            match pb_problem {
+                Ok(pb_problem) => print_status(
+                    solve_opb(pb_problem, &options, &mut StdoutObjectiveReporter),
+                    &options,
+                ),
+                Err(error) => print_parse_error(error),
             }
         }
+    } else if is_dimacs(&mut input) {
+        if let Some(pb_problem) = read_dimacs(&mut input) {
+            options.start_timer();
+            let number_of_variables = number_of_variables(pb_problem.constraints.iter());
+            let status = solve(
+                number_of_variables,
+                &pb_problem.constraints,
+                None,
+                &options,
+                &mut StdoutObjectiveReporter,
+            );
+            print_status(status, &options);
+        } else {
+            println!("s UNSUPPORTED");
+        }
+    } else if is_wbo(&mut input) {
+        if let Some(wbo_problem) = read_wbo(&mut input) {
+            options.start_timer();
+            let status = solve_wbo(&wbo_problem, &options, &mut StdoutObjectiveReporter);
+            print_status(status, &options);
+        } else {
+            println!("s UNSUPPORTED");
+        }
     } else {
-        println!("s UNSUPPORTED");
+        match read_opb(&mut input) {
+            Ok(pb_problem) => print_status(
+                solve_opb(pb_problem, &options, &mut StdoutObjectiveReporter),
+                &options,
+            ),
+            Err(error) => print_parse_error(error),
+        }
     }
 }
 
-fn solve(pb_problem: &PBProblem) -> Status {
-    let start_time = std::time::Instant::now();
+/// OPB 形式で読み取った問題を目的関数の数に応じて解く
+fn solve_opb(
+    pb_problem: PBProblem,
+    options: &SolveOptions,
+    objective_reporter: &mut impl ObjectiveReporter,
+) -> Status {
+    if options.degree_histogram {
+        print_degree_histogram(&pb_problem.variable_degrees());
+    }
+    // 目的関数にのみ現れる変数も数える
+    let number_of_variables = number_of_variables(pb_problem.constraints.iter()).max(
+        pb_problem
+            .objectives
+            .iter()
+            .flatten()
+            .map(|weighted_term| weighted_term.term.index)
+            .max()
+            .unwrap_or(0),
+    );
+    // 問題に現れない変数の hint は無視する
+    let mut options = options.clone();
+    options.start_timer();
+    for literal in pb_problem.hint.into_iter().flatten() {
+        if literal.index() < number_of_variables {
+            options.hint.push(literal);
+        } else {
+            println!("c hint: ignoring unknown variable x{}", literal.index() + 1);
+        }
+    }
+    // 最大化問題は符号を反転した目的関数を最小化し，報告する値と結果の目的関数値の符号を戻す
+    if pb_problem.objective_is_maximize {
+        return solve_minimization(
+            number_of_variables,
+            &pb_problem.constraints,
+            &pb_problem.objectives,
+            &options,
+            &mut NegatedObjectiveReporter {
+                inner: objective_reporter,
+            },
+        )
+        .negate_objective_values();
+    }
+    return solve_minimization(
+        number_of_variables,
+        &pb_problem.constraints,
+        &pb_problem.objectives,
+        &options,
+        objective_reporter,
+    );
+}
+
+fn solve_minimization(
+    number_of_variables: usize,
+    constraints: &[Constraint],
+    objectives: &[Vec<WeightedTerm>],
+    options: &SolveOptions,
+    objective_reporter: &mut impl ObjectiveReporter,
+) -> Status {
+    return match objectives {
+        [] => solve(
+            number_of_variables,
+            constraints,
+            None,
+            options,
+            objective_reporter,
+        ),
+        [objective] => solve(
+            number_of_variables,
+            constraints,
+            Some(objective),
+            options,
+            objective_reporter,
+        ),
+        objectives => solve_lexicographic(
+            number_of_variables,
+            constraints,
+            objectives,
+            options,
+            objective_reporter,
+        ),
+    };
+}
 
-    let mut pb_engine = PBEngine::new(10.0);
+/// 次数ごとの変数の数を "c degree D: N variables" 行として出力する
+fn print_degree_histogram(degrees: &[usize]) {
+    let mut histogram = vec![0; degrees.iter().max().map_or(0, |max_degree| max_degree + 1)];
+    for &degree in degrees {
+        histogram[degree] += 1;
+    }
+    for (degree, &number_of_variables) in histogram.iter().enumerate() {
+        if number_of_variables != 0 {
+            println!("c degree {}: {} variables", degree, number_of_variables);
+        }
+    }
+}
 
-    {
-        let max_index = pb_problem
-            .constraints
+fn print_parse_error(error: ParseError) {
+    match error {
+        ParseError::Io(error) => {
+            println!("c {}", error);
+        }
+        ParseError::Syntax { line_number } => {
+            println!("c cannot parse line {}", line_number);
+        }
+    }
+    println!("s UNSUPPORTED");
+}
+
+/// 入力が DIMACS CNF 形式であるか (コメント行を除いた最初の行が p で始まるか) を判定する
+fn is_dimacs(input: &mut BufReader<Box<dyn Read>>) -> bool {
+    return first_line_except_comments(input, b"c").starts_with(b"p");
+}
+
+/// 入力が WBO 形式であるか (コメント行と空行を除いた最初の行が soft ヘッダであるか) を判定する
+fn is_wbo(input: &mut BufReader<Box<dyn Read>>) -> bool {
+    return first_line_except_comments(input, b"*").starts_with(b"soft:");
+}
+
+/// comment_prefix で始まるコメント行と空行を除いた最初の行を返す
+///
+/// 先頭のコメント行がバッファに収まらない場合にも判定できるよう，行を読み進めて判定し，読んだ内容は入力の先頭に戻す
+fn first_line_except_comments(
+    input: &mut BufReader<Box<dyn Read>>,
+    comment_prefix: &[u8],
+) -> Vec<u8> {
+    let mut consumed = Vec::default();
+    let mut first_line = Vec::default();
+    loop {
+        let start = consumed.len();
+        let Ok(bytes) = input.read_until(b'\n', &mut consumed) else {
+            break;
+        };
+        if bytes == 0 {
+            break;
+        }
+        let line = consumed[start..].trim_ascii();
+        if line.is_empty() || line.starts_with(comment_prefix) {
+            continue;
+        }
+        first_line = line.to_vec();
+        break;
+    }
+    let rest = std::mem::replace(input, BufReader::new(Box::new(std::io::empty())));
+    *input = BufReader::new(Box::new(std::io::Cursor::new(consumed).chain(rest)));
+    return first_line;
+}
+
+fn print_status(status: Status, options: &SolveOptions) {
+    match status {
+        Status::Satisfiable { solution } => {
+            println!("s SATISFIABLE");
+            print_solution(&solution, options);
+        }
+        Status::Optimum { solution, .. } => {
+            println!("s OPTIMUM FOUND");
+            print_solution(&solution, options);
+        }
+        Status::LexicographicOptimum {
+            solution,
+            objective_values,
+        } => {
+            print!("c objective values:");
+            for objective_value in objective_values {
+                print!(" {}", objective_value);
+            }
+            println!();
+            println!("s OPTIMUM FOUND");
+            print_solution(&solution, options);
+        }
+        Status::Unsatisfiable => {
+            println!("s UNSATISFIABLE");
+        }
+        Status::Indefinite => {
+            println!("s UNKNOWN");
+        }
+    }
+}
+
+fn print_solution(solution: &[Boolean], options: &SolveOptions) {
+    if options.model_format == ModelFormat::Binary {
+        let path = options.model_path.as_deref().unwrap();
+        let result = std::fs::File::create(path).and_then(|file| {
+            let mut output = std::io::BufWriter::new(file);
+            write_binary_model(&mut output, solution)?;
+            return output.flush();
+        });
+        match result {
+            Ok(()) => println!("c model written to {}", path),
+            Err(error) => println!("c cannot write {}: {}", path, error),
+        }
+        return;
+    }
+
+    print!("v");
+    for (index, &value) in solution.iter().enumerate() {
+        match value {
+            Boolean::TRUE => {
+                print!(" x{}", index + 1);
+            }
+            Boolean::FALSE => {
+                print!(" -x{}", index + 1);
+            }
+        }
+    }
+    println!();
+}
+
+fn number_of_variables<'a>(constraints: impl Iterator<Item = &'a Constraint>) -> usize {
+    return constraints
+        .map(|constraint| {
+            constraint
+                .sum
+                .iter()
+                .map(|weighted_term| weighted_term.term.index)
+                .max()
+                .unwrap_or(0)
+        })
+        .max()
+        .unwrap_or(0);
+}
+
+/// 重み付き MaxSAT を解く
+///
+/// 各ソフト制約に緩和変数を導入し，緩和変数の重み付き和を目的関数として最小化する
+fn solve_wbo(
+    wbo_problem: &WboProblem,
+    options: &SolveOptions,
+    objective_reporter: &mut impl ObjectiveReporter,
+) -> Status {
+    let number_of_original_variables = number_of_variables(
+        wbo_problem
+            .hard
             .iter()
-            .map(|constraint| {
-                constraint
-                    .sum
-                    .iter()
-                    .map(|weighted_term| weighted_term.term.index)
-                    .max()
-                    .unwrap_or(0)
-            })
-            .max()
-            .unwrap_or(0);
+            .chain(wbo_problem.soft.iter().map(|(_, constraint)| constraint)),
+    );
 
-        // eprintln!("number_of_variables={}", max_index);
-
-        // let mut number_of_appearances = Vec::default();
-        // number_of_appearances.resize(max_index, 0usize);
-        // for constraint in pb_problem.constraints.iter() {
-        //     let n = match constraint.relational_operator {
-        //         RelationalOperator::GreaterOrEqual => 1,
-        //         RelationalOperator::Equal => 2,
-        //     };
-        //     for weighter_term in constraint.sum.iter() {
-        //         number_of_appearances[weighter_term.term.index - 1] += n;
-        //     }
-        // }
-        // let max_number_of_appearances = *number_of_appearances.iter().max().unwrap();
-        for i in 0..max_index {
-            pb_engine.add_variable_with_initial_value(
-                Boolean::FALSE,
-                // number_of_appearances[i] as f64 / max_number_of_appearances as f64,
-                0.0
-            );
+    let mut constraints = wbo_problem.hard.clone();
+    let mut objective = Vec::default();
+    for (k, (weight, soft_constraint)) in wbo_problem.soft.iter().enumerate() {
+        let relaxation_variable = Variable {
+            index: number_of_original_variables + k + 1,
+        };
+        // >= と (= の場合は) <= のそれぞれを緩和変数で緩和
+        let mut is_relaxed = false;
+        for sign in match soft_constraint.relational_operator {
+            RelationalOperator::GreaterOrEqual => [1].as_slice(),
+            RelationalOperator::Equal => [1, -1].as_slice(),
+        } {
+            let sum = Vec::from_iter(soft_constraint.sum.iter().map(|weighted_term| {
+                WeightedTerm {
+                    weight: sign * weighted_term.weight,
+                    term: weighted_term.term,
+                }
+            }));
+            let rhs = sign * soft_constraint.rhs;
+            // 緩和変数が True であれば自明に充足されるような係数
+            let relaxation_weight = rhs as i128
+                - sum
+                    .iter()
+                    .filter(|weighted_term| weighted_term.weight < 0)
+                    .map(|weighted_term| weighted_term.weight as i128)
+                    .sum::<i128>();
+            if relaxation_weight <= 0 {
+                continue;
+            }
+            // 緩和変数の係数が i64 に収まらない問題には対応しない
+            let Ok(relaxation_weight) = i64::try_from(relaxation_weight) else {
+                return Status::Indefinite;
+            };
+            is_relaxed = true;
+            constraints.push(Constraint {
+                sum: sum
+                    .into_iter()
+                    .chain([WeightedTerm {
+                        weight: relaxation_weight,
+                        term: relaxation_variable,
+                    }])
+                    .collect(),
+                relational_operator: RelationalOperator::GreaterOrEqual,
+                rhs,
+            });
+        }
+        if is_relaxed {
+            let Ok(weight) = i64::try_from(*weight) else {
+                return Status::Indefinite;
+            };
+            objective.push(WeightedTerm {
+                weight,
+                term: relaxation_variable,
+            });
         }
     }
+    // 目的関数値が top 以上となる解は許容しない
+    if let Some(top) = wbo_problem.top {
+        let Ok(top) = i64::try_from(top) else {
+            return Status::Indefinite;
+        };
+        constraints.push(Constraint {
+            sum: objective
+                .iter()
+                .map(|weighted_term| WeightedTerm {
+                    weight: -weighted_term.weight,
+                    term: weighted_term.term,
+                })
+                .collect(),
+            relational_operator: RelationalOperator::GreaterOrEqual,
+            rhs: 1 - top,
+        });
+    }
 
-    // pb_engine に制約条件を追加
-    {
-        fn add_constraint(
-            pb_engine: &mut PBEngine,
-            terms: impl Iterator<Item = (usize, i64)> + Clone,
-            lower: i64,
-        ) -> Result<(), ()> {
-            // 自明に充足される制約であれば何もしない
-            let sum_of_negative_coefficients: i128 = terms
-                .clone()
-                .filter(|&(_, coefficient)| coefficient < 0)
-                .map(|(_, coefficient)| coefficient as i128)
-                .sum();
-            if lower as i128 - sum_of_negative_coefficients <= 0 {
-                return Ok(());
+    let status = solve(
+        number_of_original_variables + wbo_problem.soft.len(),
+        &constraints,
+        Some(&objective),
+        options,
+        objective_reporter,
+    );
+
+    // 緩和変数を解から除く
+    return match status {
+        Status::Satisfiable { mut solution } => {
+            solution.truncate(number_of_original_variables);
+            Status::Satisfiable { solution }
+        }
+        Status::Optimum {
+            mut solution,
+            objective_value,
+        } => {
+            solution.truncate(number_of_original_variables);
+            Status::Optimum {
+                solution,
+                objective_value,
             }
+        }
+        status => status,
+    };
+}
 
-            // 項を (Literal, u64) に変換
-            let pb_lower = (lower as i128 - sum_of_negative_coefficients) as u128;
-            let pb_terms =
-                terms
-                    .filter(|&(_, coefficient)| coefficient != 0)
-                    .map(|(index, coefficient)| {
-                        if coefficient > 0 {
-                            (
-                                Literal::new(index, pb_engine::Boolean::TRUE),
-                                coefficient as u64,
-                            )
-                        } else {
-                            (
-                                Literal::new(index, pb_engine::Boolean::FALSE),
-                                (-coefficient) as u64,
-                            )
-                        }
-                    });
-
-            // TODO 以下の処理は，現状の PBEngine のラッパーを作ってそこで実装したほうが良い
-            // そもそも PBConstraint は外に見せない(explain の戻り値の実装だけに使う)ほうがいいかも
-
-            // 実行不可能か
-            let sup: u128 = pb_terms
-                .clone()
-                .filter(|&(literal, _)| !pb_engine.is_false(literal))
-                .map(|(_, coefficient)| coefficient as u128)
-                .sum();
-            if sup < pb_lower {
-                return Err(());
+/// 複数の目的関数を辞書式順序で最小化する
+///
+/// 先頭の目的関数から順に最適値を求め，その値に等しいという制約条件を加えて次の目的関数を最小化する
+fn solve_lexicographic(
+    number_of_variables: usize,
+    constraints: &[Constraint],
+    objectives: &[Vec<WeightedTerm>],
+    options: &SolveOptions,
+    objective_reporter: &mut impl ObjectiveReporter,
+) -> Status {
+    let mut constraints = constraints.to_vec();
+    let mut objective_values = Vec::default();
+    let mut last_solution = None;
+    for (stage, objective) in objectives.iter().enumerate() {
+        // o 行の値が単調に改善するよう，報告するのは 1 番目の目的関数の値のみとする
+        let status = if stage == 0 {
+            solve(
+                number_of_variables,
+                &constraints,
+                Some(objective),
+                options,
+                objective_reporter,
+            )
+        } else {
+            solve(
+                number_of_variables,
+                &constraints,
+                Some(objective),
+                options,
+                &mut IgnoringObjectiveReporter,
+            )
+        };
+        let (solution, objective_value) = match status {
+            Status::Optimum {
+                solution,
+                objective_value,
+            } => (solution, objective_value),
+            status => return status,
+        };
+
+        // 最適値を固定する
+        let Ok(rhs) = i64::try_from(objective_value) else {
+            return Status::Indefinite;
+        };
+        constraints.push(Constraint {
+            sum: objective.clone(),
+            relational_operator: RelationalOperator::Equal,
+            rhs,
+        });
+        objective_values.push(objective_value);
+        last_solution = Some(solution);
+    }
+    let Some(solution) = last_solution else {
+        return Status::Indefinite;
+    };
+    return Status::LexicographicOptimum {
+        solution,
+        objective_values,
+    };
+}
+
+fn solve(
+    number_of_variables: usize,
+    constraints: &[Constraint],
+    objective: Option<&[WeightedTerm]>,
+    options: &SolveOptions,
+    objective_reporter: &mut impl ObjectiveReporter,
+) -> Status {
+    // 変数消去は導出節を証明に記録しないため，DRAT 証明を書き出す場合は行わない
+    if options.eliminate_variables && options.drat_path.is_none() {
+        let elimination = eliminate_variables(
+            constraints,
+            objective
+                .into_iter()
+                .flatten()
+                .map(|weighted_term| &weighted_term.term),
+        );
+        println!(
+            "c eliminated {} variables",
+            elimination.number_of_eliminated_variables()
+        );
+        let status = solve(
+            number_of_variables,
+            &elimination.constraints,
+            objective,
+            &SolveOptions {
+                eliminate_variables: false,
+                ..options.clone()
+            },
+            objective_reporter,
+        );
+        return status.map_solution(|solution| elimination.extend_solution(solution));
+    }
+
+    let start_time = std::time::Instant::now();
+
+    // 節学習を用いる決定問題で全ての係数が ±1 であれば，整数線形制約が現れることはない
+    let is_cardinality_only = options.clausal_learning
+        && objective.is_none()
+        && constraints.iter().all(|constraint| {
+            constraint
+                .sum
+                .iter()
+                .all(|weighted_term| weighted_term.weight.abs() == 1)
+        });
+    let mut pb_engine = if is_cardinality_only {
+        PBEngine::new_cardinality_only(10.0)
+    } else {
+        PBEngine::new(10.0)
+    };
+    pb_engine.set_max_learnt(options.max_learnt);
+    pb_engine.enable_subsumption_checks(options.subsumption);
+    if let Some(cancellation) = &options.cancellation {
+        pb_engine.set_cancellation(cancellation.clone());
+    }
+
+    // DRAT 証明は全ての制約条件が節であり，節のみを学習する場合に限り有効
+    if let Some(drat_path) = &options.drat_path {
+        let is_clausal = is_cardinality_only
+            && constraints.iter().all(|constraint| {
+                constraint.relational_operator == RelationalOperator::GreaterOrEqual
+                    && constraint.rhs
+                        + constraint
+                            .sum
+                            .iter()
+                            .filter(|weighted_term| weighted_term.weight < 0)
+                            .count() as i64
+                        <= 1
+            });
+        if !is_clausal {
+            println!("c DRAT proof requires a CNF instance and --clausal-learning");
+        } else {
+            match std::fs::File::create(drat_path) {
+                Ok(file) => pb_engine.set_drat_logger(Some(DratLogger::new(file))),
+                Err(error) => println!("c cannot create {}: {}", drat_path, error),
             }
+        }
+    }
 
-            // 制約を追加
-            add_integer_linear_constraint(
-                pb_engine,
-                &LinearConstraintView::new(pb_terms, pb_lower as u64),
-                false,
-            );
+    {
+        // eprintln!("number_of_variables={}", number_of_variables);
 
-            return Ok(());
+        let mut initial_values = vec![Boolean::FALSE; number_of_variables];
+        for literal in options.hint.iter() {
+            initial_values[literal.index()] = literal.value();
+        }
+        for initial_value in initial_values {
+            pb_engine.add_variable_with_initial_value(initial_value, 0.0);
         }
+    }
 
-        for constraint in pb_problem.constraints.iter() {
-            // >=
-            let result = add_constraint(
-                &mut pb_engine,
+    // pb_engine に制約条件を追加
+    // 解の検証のために正規化した制約条件を保持しておく
+    let mut normalized_constraints = Vec::default();
+    for (k, constraint) in constraints.iter().enumerate() {
+        pb_engine.set_original_constraint_index(k);
+        // >= と (= の場合は) <=
+        for sign in match constraint.relational_operator {
+            RelationalOperator::GreaterOrEqual => [1].as_slice(),
+            RelationalOperator::Equal => [1, -1].as_slice(),
+        } {
+            let Some(normalized_constraint) = normalize_constraint(
                 constraint
                     .sum
                     .iter()
-                    .map(|weighted_term| (weighted_term.term.index - 1, weighted_term.weight)),
-                constraint.rhs,
-            );
-            if result.is_err() {
+                    .map(|weighted_term| {
+                        (weighted_term.term.index - 1, sign * weighted_term.weight)
+                    }),
+                sign * constraint.rhs,
+            ) else {
+                continue;
+            };
+            if add_constraint(&mut pb_engine, &normalized_constraint, options.sat_encoding)
+                .is_err()
+            {
+                log_empty_clause(&mut pb_engine);
                 return Status::Unsatisfiable;
             }
-            // <=
-            if matches!(constraint.relational_operator, RelationalOperator::Equal) {
-                let result = add_constraint(
-                    &mut pb_engine,
-                    constraint
-                        .sum
-                        .iter()
-                        .map(|weighted_term| (weighted_term.term.index - 1, -weighted_term.weight)),
-                    -constraint.rhs,
-                );
-                if result.is_err() {
-                    return Status::Unsatisfiable;
-                }
-            }
+            normalized_constraints.push(normalized_constraint);
         }
     }
 
+    // 目的関数値の上界の制約条件は constraints の後の番号とする
+    pb_engine.set_original_constraint_index(constraints.len());
+
+    if options.seed_activities {
+        pb_engine.seed_activities_by_occurrence();
+    }
+
     // eprintln!("   RESTART CONFLICT      PLBD     FIXED    #COUNT   #LINEAR      TIME");
 
-    let mut plbd_watcher = PLBDWatcher::new(10, 10000);
-    let mut analyzer = Analyze::new(1e-10);
-    let mut calculate_plbd = CalculatePLBD::default();
+    let mut plbd_watcher = PLBDWatcher::new(options.plbd_short_window, options.plbd_long_window);
+    let mut analyzer =
+        Analyze::new_with_limits(1e-10, u32::MAX as Coefficient, options.max_learnt_terms);
+    analyzer.set_minimization(options.minimize_clauses);
+    analyzer.set_target_weakening(options.target_weakening);
+    analyzer.enable_resolve_checks(cfg!(debug_assertions));
 
     let mut conflict_count: usize = 0;
     let mut restart_count: usize = 0;
     let mut previous_restart_timestamp = 0;
 
+    // これまでに得られた最良の解とその目的関数値
+    let mut incumbent: Option<(Vec<Boolean>, i128)> = None;
+
+    let lower_bound = objective
+        .filter(|_| options.lp_bound)
+        .map(|objective| lp_lower_bound(objective, constraints));
+    if let Some(lower_bound) = lower_bound {
+        objective_reporter.report_lower_bound(lower_bound);
+    }
+
     // eprintln!(
     //     "{:9} {:9} {:9} {:9} {:9} {:9} {:9}",
     //     restart_count,
@@ -211,6 +955,16 @@ fn solve(pb_problem: &PBProblem) -> Status {
         pb_engine.propagate();
         // eprintln!("{}", pb_engine.number_of_assignments());
 
+        // 打ち切られた場合は時間切れと同様にそれまでに得られた最良の解を返す
+        if pb_engine.state().is_interrupted() {
+            pb_engine.reset_to_root();
+            println!("c interrupted after {} conflicts", conflict_count);
+            return match incumbent {
+                Some((solution, _)) => Status::Satisfiable { solution },
+                None => Status::Indefinite,
+            };
+        }
+
         if let PBState::Conflict {
             index: conflict_variable,
             explain_keys: conflict_explain_keys,
@@ -219,41 +973,47 @@ fn solve(pb_problem: &PBProblem) -> Status {
             conflict_count += 1;
 
             if pb_engine.decision_level() == 0 {
-                return Status::Unsatisfiable;
+                break;
             }
 
-            pb_engine.update_assignment_probabilities();
-
-            let analyze_result =
-                analyzer.call(conflict_variable, conflict_explain_keys, &pb_engine);
-
-            let AnalyzeResult::Backjumpable {
-                backjump_level,
-                learnt_constraint,
-                conflicting_assignments,
-            } = analyze_result
-            else {
-                return Status::Unsatisfiable;
-            };
-
-            let plbd = calculate_plbd.calculate(
-                learnt_constraint
-                    .iter_terms()
-                    .map(|(literal, _)| !literal)
-                    .filter(|&literal| pb_engine.is_true(literal)),
-                &pb_engine,
-            );
-            plbd_watcher.add(plbd);
-            // plbd_watcher.add(pb_engine.decision_level());
-            // eprintln!("plbd={} long_term_mean={}, long_term_variance={}, short_term_mean={}, p={}", plbd, plbd_watcher.long_term_average.mean(), plbd_watcher.long_term_average.variance(), plbd_watcher.short_term_average.mean(), plbd_watcher.lower_tail_probability());
-
-            pb_engine.update_conflict_probabilities(conflicting_assignments, backjump_level);
+            // 時間切れの場合はそれまでに得られた最良の解を返す
+            if options
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                println!("c timeout after {} conflicts", conflict_count);
+                return match incumbent {
+                    Some((solution, _)) => Status::Satisfiable { solution },
+                    None => Status::Indefinite,
+                };
+            }
 
-            let conflict_level = pb_engine.decision_level();
+            // 矛盾の数が上限に達した場合も同様に打ち切る
+            if options
+                .max_conflicts
+                .is_some_and(|max_conflicts| conflict_count >= max_conflicts)
+            {
+                println!("c conflict limit reached after {} conflicts", conflict_count);
+                return match incumbent {
+                    Some((solution, _)) => Status::Satisfiable { solution },
+                    None => Status::Indefinite,
+                };
+            }
 
-            pb_engine.backjump(backjump_level);
+            pb_engine.update_assignment_probabilities();
 
-            add_integer_linear_constraint(&mut pb_engine, &learnt_constraint, true);
+            let is_learnt = if options.clausal_learning {
+                let analyze_result =
+                    analyzer.call_clausal(conflict_variable, conflict_explain_keys, &pb_engine);
+                learn(&mut pb_engine, &mut plbd_watcher, analyze_result, options.sat_encoding)
+            } else {
+                let analyze_result =
+                    analyzer.call(conflict_variable, conflict_explain_keys, &pb_engine);
+                learn(&mut pb_engine, &mut plbd_watcher, analyze_result, options.sat_encoding)
+            };
+            if !is_learnt {
+                break;
+            }
 
             // if conflict_count % 10000 == 0 {
             //     eprintln!(
@@ -267,25 +1027,52 @@ fn solve(pb_problem: &PBProblem) -> Status {
             //         start_time.elapsed().as_secs_f64()
             //     );
             // }
-        } else if pb_engine.number_of_assignments() == pb_engine.number_of_variables() {
-            for constraint in pb_problem.constraints.iter() {
-                let mut lhs = 0;
-                for term in constraint.sum.iter() {
-                    if pb_engine.get_value(term.term.index - 1) == Boolean::TRUE {
-                        lhs += term.weight;
-                    }
-                }
-                assert!(lhs >= constraint.rhs, "{} {}", lhs, constraint.rhs);
-                assert!(
-                    constraint.relational_operator == RelationalOperator::GreaterOrEqual
-                        || lhs <= constraint.rhs,
-                    "{} {}",
-                    lhs,
-                    constraint.rhs
-                );
+        } else if !pb_engine.has_unassigned() {
+            debug_assert!(pb_engine.is_propagation_complete());
+            assert!(pb_engine.verify_against(&normalized_constraints).is_ok());
+            // 符号化のために導入した補助変数は解に含めない
+            let solution: Vec<Boolean> = (0..number_of_variables).map(|index| pb_engine.get_value(index)).collect();
+
+            let Some(objective) = objective else {
+                return Status::Satisfiable{solution};
+            };
+
+            // 目的関数値を算出して解を更新
+            let objective_value = objective
+                .iter()
+                .filter(|weighted_term| solution[weighted_term.term.index - 1] == Boolean::TRUE)
+                .map(|weighted_term| weighted_term.weight as i128)
+                .sum::<i128>();
+            objective_reporter.report(objective_value);
+            incumbent.replace((solution, objective_value));
+
+            // 下界に達していれば最適
+            if lower_bound.is_some_and(|lower_bound| objective_value <= lower_bound) {
+                break;
+            }
+
+            // 目的関数値が objective_value 以上となる制約条件の否定を追加して探索を継続
+            pb_engine.reset_to_root();
+            // 目的関数値が i64 に収まらない場合は制約条件を追加できないため，得られた解を返す
+            let Ok(rhs) = i64::try_from(objective_value) else {
+                let (solution, _) = incumbent.unwrap();
+                return Status::Satisfiable { solution };
+            };
+            let Some(objective_constraint) = normalize_constraint(
+                objective
+                    .iter()
+                    .map(|weighted_term| (weighted_term.term.index - 1, weighted_term.weight)),
+                rhs,
+            ) else {
+                // 目的関数値は常に objective_value 以上であり，これより良い解は存在しない
+                break;
+            };
+            let negated_constraint = objective_constraint.negate();
+            let bound_constraint =
+                LinearConstraint::new(negated_constraint.iter_terms(), negated_constraint.lower());
+            if add_constraint(&mut pb_engine, &bound_constraint, options.sat_encoding).is_err() {
+                break;
             }
-            let solution = (0..pb_engine.number_of_variables()).map(|index| pb_engine.get_value(index)).collect();
-            return Status::Satisfiable{solution};
         } else if conflict_count >= previous_restart_timestamp + 10000
             || (conflict_count >= previous_restart_timestamp + 20
                 && plbd_watcher.lower_tail_probability() > 0.6)
@@ -293,23 +1080,131 @@ fn solve(pb_problem: &PBProblem) -> Status {
             restart_count += 1;
             previous_restart_timestamp = conflict_count;
 
-            if pb_engine.decision_level() != 0 {
-                pb_engine.backjump(0);
-            }
+            pb_engine.restart();
 
         } else {
             pb_engine.decide();
         }
     }
+
+    // より良い解が存在しないことが示された
+    return match incumbent {
+        Some((solution, objective_value)) => Status::Optimum {
+            solution,
+            objective_value,
+        },
+        None => {
+            log_empty_clause(&mut pb_engine);
+            Status::Unsatisfiable
+        }
+    };
+}
+
+/// 充足不能であることを示す空節を DRAT 証明に書き出す
+fn log_empty_clause(pb_engine: &mut PBEngine) {
+    if let Some(drat_logger) = pb_engine.drat_logger_mut() {
+        drat_logger.add_clause(std::iter::empty());
+        drat_logger.flush();
+    }
+}
+
+/// 矛盾の解析結果に従ってバックジャンプし，学習制約を追加する
+///
+/// 充足不能であることが示された場合は false を返す
+fn learn(
+    pb_engine: &mut PBEngine,
+    plbd_watcher: &mut PLBDWatcher,
+    analyze_result: AnalyzeResult<
+        impl LinearConstraintTrait<Value = Coefficient>,
+        impl Iterator<Item = Literal>,
+        impl Iterator<Item = PBExplainKey>,
+    >,
+    sat_encoding: bool,
+) -> bool {
+    let AnalyzeResult::Backjumpable {
+        backjump_level,
+        learnt_constraint,
+        conflicting_assignments,
+        plbd,
+        ..
+    } = analyze_result
+    else {
+        return false;
+    };
+
+    plbd_watcher.add(plbd);
+    // plbd_watcher.add(pb_engine.decision_level());
+    // eprintln!("plbd={} long_term_mean={}, long_term_variance={}, short_term_mean={}, p={}", plbd, plbd_watcher.long_term_average.mean(), plbd_watcher.long_term_average.variance(), plbd_watcher.short_term_average.mean(), plbd_watcher.lower_tail_probability());
+
+    pb_engine.update_conflict_probabilities(conflicting_assignments, backjump_level);
+
+    pb_engine.backjump(backjump_level);
+
+    // 学習制約は backjump 後の割り当てのもとで違反しない
+    add_integer_linear_constraint(pb_engine, &learnt_constraint, true, sat_encoding).unwrap();
+
+    return true;
+}
+
+/// 整数係数の制約条件 sum(terms) >= lower を正の係数の制約条件に変換する
+///
+/// 自明に充足される制約条件であれば None を返す
+fn normalize_constraint(
+    terms: impl Iterator<Item = (usize, i64)> + Clone,
+    lower: i64,
+) -> Option<LinearConstraint<Coefficient>> {
+    let sum_of_negative_coefficients: i128 = terms
+        .clone()
+        .filter(|&(_, coefficient)| coefficient < 0)
+        .map(|(_, coefficient)| coefficient as i128)
+        .sum();
+    if lower as i128 - sum_of_negative_coefficients <= 0 {
+        return None;
+    }
+
+    // 項を (Literal, Coefficient) に変換
+    let pb_lower = (lower as i128 - sum_of_negative_coefficients) as Coefficient;
+    let pb_terms =
+        terms
+            .filter(|&(_, coefficient)| coefficient != 0)
+            .map(|(index, coefficient)| {
+                if coefficient > 0 {
+                    (
+                        Literal::new(index, pb_engine::Boolean::TRUE),
+                        coefficient as Coefficient,
+                    )
+                } else {
+                    (
+                        Literal::new(index, pb_engine::Boolean::FALSE),
+                        (-coefficient) as Coefficient,
+                    )
+                }
+            });
+    return Some(LinearConstraint::new(pb_terms, pb_lower));
 }
 
+fn add_constraint(
+    pb_engine: &mut PBEngine,
+    linear_constraint: &LinearConstraint<Coefficient>,
+    sat_encoding: bool,
+) -> Result<(), ()> {
+    // TODO 以下の処理は，現状の PBEngine のラッパーを作ってそこで実装したほうが良い
+    // そもそも PBConstraint は外に見せない(explain の戻り値の実装だけに使う)ほうがいいかも
+
+    // 実行不可能であれば (項を持たず lower が正のものを含む) エンジンが Err を返す
+    return add_integer_linear_constraint(pb_engine, linear_constraint, false, sat_encoding)
+        .map_err(|_| ());
+}
+
+/// sat_encoding が false であれば，節と基数制約に変換できない制約条件は常に整数線形制約として追加する
 fn add_integer_linear_constraint(
     pb_engine: &mut PBEngine,
-    integer_linear_constraint: &impl LinearConstraintTrait<Value = u64>,
+    integer_linear_constraint: &impl LinearConstraintTrait<Value = Coefficient>,
     is_learnt: bool,
-) {
+    sat_encoding: bool,
+) -> Result<(), ConstraintError> {
     if integer_linear_constraint.lower() == 0 {
-        return;
+        return Ok(());
     }
     let integer_linear_constraint = strengthen_integer_linear_constraint(integer_linear_constraint);
 
@@ -317,20 +1212,20 @@ fn add_integer_linear_constraint(
         .iter_terms()
         .all(|(_, coefficient)| coefficient == 1)
     {
-        if integer_linear_constraint.len() == integer_linear_constraint.lower() as usize {
-            for (literal, _) in integer_linear_constraint.iter_terms() {
-                pb_engine.add_monadic_clause(MonadicClause { literal }, is_learnt);
+        let count_constraint = CountConstraintView::new(
+            integer_linear_constraint
+                .iter_terms()
+                .map(|(literal, _)| literal),
+            integer_linear_constraint.lower(),
+        );
+        if count_constraint.len() == count_constraint.lower() as usize {
+            // 全てのリテラルが True でなければならない (単位節を含む)
+            for literal in count_constraint.iter_terms() {
+                pb_engine.add_monadic_clause(MonadicClause { literal }, is_learnt)?;
             }
         } else {
-            pb_engine.add_count_constraint(
-                CountConstraintView::new(
-                    integer_linear_constraint
-                        .iter_terms()
-                        .map(|(literal, _)| literal),
-                    integer_linear_constraint.lower(),
-                ),
-                is_learnt,
-            );
+            // 節と基数制約はどちらも基数制約の理論で扱う
+            pb_engine.add_count_constraint(count_constraint, is_learnt)?;
         }
     } else {
         let mut sum_of_unsaturating_coefficients = 0;
@@ -339,7 +1234,7 @@ fn add_integer_linear_constraint(
                 sum_of_unsaturating_coefficients += coefficient;
             }
         }
-        if sum_of_unsaturating_coefficients == integer_linear_constraint.lower() {
+        if sat_encoding && sum_of_unsaturating_coefficients == integer_linear_constraint.lower() {
             // SAT 符号化して追加
             let saturating_literals = integer_linear_constraint
                 .iter_terms()
@@ -349,19 +1244,919 @@ fn add_integer_linear_constraint(
                 .iter_terms()
                 .filter(|&(_, coefficient)| coefficient < integer_linear_constraint.lower())
                 .map(|(literal, _)| literal);
-            for unsaturating_literal in unsaturating_literals {
-                pb_engine.add_count_constraint(
-                    CountConstraintView::new(
-                        saturating_literals
-                            .clone()
-                            .chain([unsaturating_literal].into_iter()),
-                        1,
-                    ),
-                    is_learnt,
+            add_disjunction_of_saturating_literals_and_conjunction(
+                pb_engine,
+                saturating_literals,
+                unsaturating_literals,
+                is_learnt,
+            )?;
+        } else if integer_linear_constraint
+            .iter_terms()
+            .is_sorted_by(|l, r| l.1 >= r.1)
+        {
+            // 既に係数の降順に並んでいればソートを省略する
+            pb_engine.add_integer_linear_constraint_presorted(integer_linear_constraint, is_learnt)?;
+        } else {
+            pb_engine.add_integer_linear_constraint(integer_linear_constraint, is_learnt)?;
+        }
+    }
+    return Ok(());
+}
+
+/// (saturating_literals のいずれかが True) ∨ (unsaturating_literals が全て True) を追加する
+///
+/// 各 unsaturating_literal について saturating_literals ∨ unsaturating_literal を追加すると
+/// unsaturating_literals の数だけ行が必要となるため，決定レベル 0 で元の制約条件を追加する場合には，
+/// 補助変数 y を導入して基数制約 saturating_literals ∨ y と
+/// y → (unsaturating_literals が全て True) を表す整数線形制約 n ¬y + Σ unsaturating_literals >= n の 2 行に符号化する
+fn add_disjunction_of_saturating_literals_and_conjunction(
+    pb_engine: &mut PBEngine,
+    saturating_literals: impl Iterator<Item = Literal> + Clone,
+    unsaturating_literals: impl Iterator<Item = Literal> + Clone,
+    is_learnt: bool,
+) -> Result<(), ConstraintError> {
+    let number_of_unsaturating_literals = unsaturating_literals.clone().count();
+    if !is_learnt
+        && pb_engine.decision_level() == 0
+        && !pb_engine.is_cardinality_only()
+        && number_of_unsaturating_literals > 2
+    {
+        let auxiliary_literal = Literal::new(pb_engine.number_of_variables(), Boolean::TRUE);
+        pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        pb_engine.add_count_constraint(
+            CountConstraint::from_clause(saturating_literals.chain([auxiliary_literal])),
+            is_learnt,
+        )?;
+        let number_of_unsaturating_literals = number_of_unsaturating_literals as Coefficient;
+        pb_engine.add_integer_linear_constraint(
+            LinearConstraint::new(
+                [(!auxiliary_literal, number_of_unsaturating_literals)]
+                    .into_iter()
+                    .chain(unsaturating_literals.map(|literal| (literal, 1))),
+                number_of_unsaturating_literals,
+            ),
+            is_learnt,
+        )?;
+    } else {
+        for unsaturating_literal in unsaturating_literals {
+            pb_engine.add_count_constraint(
+                CountConstraint::from_clause(
+                    saturating_literals.clone().chain([unsaturating_literal]),
+                ),
+                is_learnt,
+            )?;
+        }
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        ObjectiveReporter, SolveOptions, Status, add_integer_linear_constraint, is_dimacs, is_wbo,
+        number_of_variables, solve, solve_lexicographic, solve_opb, solve_wbo,
+    };
+    use pb_engine::{Boolean, LinearConstraint, Literal, PBEngine};
+    use std::{
+        io::{BufReader, Read},
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        time::Duration,
+    };
+    use crate::binary_model::{read_binary_model, write_binary_model};
+    use crate::read_opb::{
+        Constraint, PBProblem, RelationalOperator, Variable, WeightedTerm, read_opb, read_wbo,
+    };
+
+    struct CapturingObjectiveReporter {
+        values: Vec<i128>,
+    }
+
+    impl ObjectiveReporter for CapturingObjectiveReporter {
+        fn report(&mut self, value: i128) {
+            self.values.push(value);
+        }
+    }
+
+    #[test]
+    fn test_from_args() {
+        let args = |args: &[&str]| SolveOptions::from_args(args.iter().map(|arg| arg.to_string()));
+        let options = args(&["--timeout", "1.5", "--opt", "input.opb"]).unwrap();
+        assert!(options.input_path.as_deref() == Some("input.opb"));
+        assert!(options.timeout == Some(Duration::from_secs_f64(1.5)));
+        assert!(options.deadline.is_none());
+        let options = args(&["--clausal-learning", "-"]).unwrap();
+        assert!(options.input_path.as_deref() == Some("-"));
+        assert!(options.clausal_learning && options.timeout.is_none());
+        assert!(args(&[]).unwrap().input_path.is_none());
+
+        assert!(args(&["--unknown"]).err().as_deref() == Some("--unknown"));
+        assert!(args(&["--timeout"]).is_err());
+        assert!(args(&["--max-conflicts=100"]).unwrap().max_conflicts == Some(100));
+        assert!(args(&["--max-conflicts=x"]).is_err());
+        assert!(args(&["--timeout=-1"]).is_err());
+        assert!(args(&["a.opb", "b.opb"]).is_err());
+        assert!(args(&["--batch", "batch.opb"]).unwrap().batch);
+        assert!(args(&["--degree-histogram"]).unwrap().degree_histogram);
+        assert!(args(&["--subsumption"]).unwrap().subsumption);
+        assert!(args(&["--plbd-short-window=5"]).unwrap().plbd_short_window == 5);
+        assert!(args(&["--plbd-long-window=0"]).is_err());
+        assert!(args(&["--batch", "--model-format=binary", "--model-file=model.bin"]).is_err());
+        assert!(args(&["--batch", "--drat=proof.drat"]).is_err());
+    }
+
+    #[test]
+    fn test_is_wbo() {
+        // 先頭のコメント行がバッファより長くても判定でき，読んだ内容は入力に戻される
+        let input = format!("* {}\n\nsoft: 2 ;\n[1] +1 x1 >= 1 ;\n", "c".repeat(100000));
+        let mut reader: BufReader<Box<dyn Read>> =
+            BufReader::new(Box::new(std::io::Cursor::new(input.clone())));
+        assert!(is_wbo(&mut reader));
+        let mut content = String::default();
+        reader.read_to_string(&mut content).unwrap();
+        assert!(content == input);
+        let wbo_problem = read_wbo(&mut BufReader::new(content.as_bytes())).unwrap();
+        assert!(wbo_problem.top == Some(2));
+
+        // 制約条件の後に soft ヘッダが現れても WBO 形式とはみなさない
+        let input = "+1 x1 >= 1 ;\nsoft: 2 ;\n";
+        let mut reader: BufReader<Box<dyn Read>> = BufReader::new(Box::new(input.as_bytes()));
+        assert!(!is_wbo(&mut reader));
+    }
+
+    #[test]
+    fn test_is_dimacs() {
+        // 先頭のコメント行がバッファより長くても判定でき，読んだ内容は入力に戻される
+        let input = format!("c {}\nc\np cnf 1 1\n1 0\n", "x".repeat(100000));
+        let mut reader: BufReader<Box<dyn Read>> =
+            BufReader::new(Box::new(std::io::Cursor::new(input.clone())));
+        assert!(is_dimacs(&mut reader));
+        let mut content = String::default();
+        reader.read_to_string(&mut content).unwrap();
+        assert!(content == input);
+
+        // OPB 形式の入力は DIMACS CNF 形式とはみなさない
+        let input = "* #variable= 1 #constraint= 1\n+1 x1 >= 1 ;\n";
+        let mut reader: BufReader<Box<dyn Read>> = BufReader::new(Box::new(input.as_bytes()));
+        assert!(!is_dimacs(&mut reader));
+    }
+
+    #[test]
+    fn test_empty_input() {
+        // 空の入力，空白のみの入力とコメントのみの入力は変数を持たない充足可能な問題となる
+        for input in ["", "  \n\n", "* #variable= 0 #constraint= 0\n* comment\n"] {
+            let pb_problem = read_opb(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+            assert!(pb_problem.constraints.is_empty() && pb_problem.objectives.is_empty());
+            let number_of_variables = number_of_variables(pb_problem.constraints.iter());
+            assert!(number_of_variables == 0);
+            let status = solve(
+                number_of_variables,
+                &pb_problem.constraints,
+                None,
+                &SolveOptions::default(),
+                &mut CapturingObjectiveReporter {
+                    values: Vec::default(),
+                },
+            );
+            assert!(matches!(status, Status::Satisfiable { solution } if solution.is_empty()));
+        }
+    }
+
+    #[test]
+    fn test_binary_model() {
+        let input = "* #variable= 10 #constraint= 2
++1 x1 +1 x3 +1 x10 >= 2 ;
+-1 x1 +1 x2 -1 x9 >= 0 ;
+";
+        let pb_problem = read_opb(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+        let Status::Satisfiable { solution } = solve(
+            10,
+            &pb_problem.constraints,
+            None,
+            &SolveOptions::default(),
+            &mut CapturingObjectiveReporter {
+                values: Vec::default(),
+            },
+        ) else {
+            unreachable!()
+        };
+
+        // 書き出した解を読み込むと元の解に戻る
+        let mut bytes = Vec::default();
+        write_binary_model(&mut bytes, &solution).unwrap();
+        assert!(bytes.len() == 8 + 2);
+        assert!(read_binary_model(&mut bytes.as_slice()).unwrap() == solution);
+
+        // 出力先のファイルがなければバイナリ形式は指定できない
+        let args = |args: &[&str]| SolveOptions::from_args(args.iter().map(|arg| arg.to_string()));
+        assert!(args(&["--model-format", "binary"]).is_err());
+        assert!(args(&["--model-format=binary", "--model-file=model.bin"]).is_ok());
+        assert!(args(&["--model-format", "csv"]).is_err());
+    }
+
+    /// 鳩の巣原理 (pigeons 羽， holes 巣) を表す充足不能な制約条件
+    fn pigeonhole(pigeons: usize, holes: usize) -> Vec<Constraint> {
+        let mut constraints = Vec::default();
+        let x = |i: usize, j: usize| Variable {
+            index: i * holes + j + 1,
+        };
+        for i in 0..pigeons {
+            constraints.push(Constraint {
+                sum: (0..holes)
+                    .map(|j| WeightedTerm {
+                        weight: 1,
+                        term: x(i, j),
+                    })
+                    .collect(),
+                relational_operator: RelationalOperator::GreaterOrEqual,
+                rhs: 1,
+            });
+        }
+        for j in 0..holes {
+            constraints.push(Constraint {
+                sum: (0..pigeons)
+                    .map(|i| WeightedTerm {
+                        weight: -1,
+                        term: x(i, j),
+                    })
+                    .collect(),
+                relational_operator: RelationalOperator::GreaterOrEqual,
+                rhs: -1,
+            });
+        }
+        return constraints;
+    }
+
+    #[test]
+    fn test_timeout() {
+        // 鳩の巣原理 (10 羽，9 巣) は時間切れとなる
+        let constraints = pigeonhole(10, 9);
+        let options = SolveOptions {
+            clausal_learning: true,
+            deadline: Some(std::time::Instant::now()),
+            ..SolveOptions::default()
+        };
+        let status = solve(
+            90,
+            &constraints,
+            None,
+            &options,
+            &mut CapturingObjectiveReporter {
+                values: Vec::default(),
+            },
+        );
+        assert!(matches!(status, Status::Indefinite));
+
+        // solve_opb は問題ごとに timeout から deadline を求め直す
+        let pb_problem = PBProblem {
+            objectives: Vec::default(),
+            objective_is_maximize: false,
+            constraints: pigeonhole(4, 3),
+            hint: None,
+        };
+        let options = SolveOptions {
+            timeout: Some(Duration::from_secs(60)),
+            deadline: Some(std::time::Instant::now()),
+            ..SolveOptions::default()
+        };
+        let status = solve_opb(
+            pb_problem,
+            &options,
+            &mut CapturingObjectiveReporter {
+                values: Vec::default(),
+            },
+        );
+        assert!(matches!(status, Status::Unsatisfiable));
+    }
+
+    #[test]
+    fn test_max_conflicts() {
+        // 鳩の巣原理 (7 羽，6 巣) は少ない矛盾の数では打ち切られ，上限を上げれば解ける
+        let constraints = pigeonhole(7, 6);
+        let solve_with_max_conflicts = |max_conflicts: usize| {
+            let options = SolveOptions {
+                clausal_learning: true,
+                max_conflicts: Some(max_conflicts),
+                ..SolveOptions::default()
+            };
+            return solve(
+                42,
+                &constraints,
+                None,
+                &options,
+                &mut CapturingObjectiveReporter {
+                    values: Vec::default(),
+                },
+            );
+        };
+        assert!(matches!(solve_with_max_conflicts(10), Status::Indefinite));
+        assert!(matches!(solve_with_max_conflicts(1_000_000), Status::Unsatisfiable));
+    }
+
+    #[test]
+    fn test_cancellation() {
+        // 鳩の巣原理 (12 羽，11 巣) を解いている途中で他のスレッドから打ち切る
+        let constraints = pigeonhole(12, 11);
+        let cancellation = Arc::new(AtomicBool::new(false));
+        let options = SolveOptions {
+            clausal_learning: true,
+            cancellation: Some(cancellation.clone()),
+            ..SolveOptions::default()
+        };
+        let start_time = std::time::Instant::now();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            cancellation.store(true, Ordering::Relaxed);
+        });
+        let status = solve(
+            132,
+            &constraints,
+            None,
+            &options,
+            &mut CapturingObjectiveReporter {
+                values: Vec::default(),
+            },
+        );
+        canceller.join().unwrap();
+        assert!(matches!(status, Status::Indefinite));
+        assert!(start_time.elapsed() < std::time::Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_objective_reporter() {
+        let input = "* #variable= 3 #constraint= 4
+soft: 10 ;
+[3] +1 x1 >= 1 ;
+[2] +1 x2 >= 1 ;
+[1] +1 x3 >= 1 ;
+-1 x1 -1 x2 -1 x3 >= -1 ;
+";
+        let wbo_problem = read_wbo(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+        let mut objective_reporter = CapturingObjectiveReporter {
+            values: Vec::default(),
+        };
+        let status = solve_wbo(&wbo_problem, &SolveOptions::default(), &mut objective_reporter);
+        assert!(matches!(
+            status,
+            Status::Optimum {
+                objective_value: 3,
+                ..
+            }
+        ));
+        assert!(!objective_reporter.values.is_empty());
+        assert!(
+            objective_reporter
+                .values
+                .windows(2)
+                .all(|window| window[0] > window[1])
+        );
+        assert_eq!(*objective_reporter.values.last().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_wbo_relaxation_weight_overflow() {
+        // 緩和変数の係数が i64 に収まらない
+        let input = "* #variable= 1 #constraint= 1
+soft: ;
+[1] -9223372036854775807 x1 >= 9223372036854775807 ;
+";
+        let wbo_problem = read_wbo(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+        let mut objective_reporter = CapturingObjectiveReporter {
+            values: Vec::default(),
+        };
+        let status = solve_wbo(&wbo_problem, &SolveOptions::default(), &mut objective_reporter);
+        assert!(matches!(status, Status::Indefinite));
+    }
+
+    #[test]
+    fn test_target_weakening() {
+        // target より係数の大きな項がより前の決定レベルで伝播する学習制約が導出される問題
+        let input = "+17 x104 +13 x61 +11 x57 +19 x84 +1 x116 +9 x103 >= 61 ;
++9 x104 +30 x18 +16 x89 +29 x80 >= 84 ;
++29 x85 +21 x50 +22 x46 +30 x9 +8 x2 >= 78 ;
+-22 x46 -18 x113 -8 x80 -30 x9 -8 x2 -12 x12 >= -96 ;
++6 x130 +22 x88 +24 x12 >= 47 ;
+-23 x82 -12 x103 -29 x80 -19 x27 -30 x125 -14 x112 >= -103 ;
++14 x32 +18 x86 +22 x61 +27 x109 >= 77 ;
++9 x51 +19 x2 +13 x50 +5 x57 +23 x4 +25 x129 >= 68 ;
++30 x49 +25 x33 +26 x94 +15 x111 +16 x116 >= 83 ;
++27 x127 +4 x52 +5 x1 +30 x111 >= 63 ;
++21 x27 +19 x71 +29 x105 +6 x32 +22 x100 +28 x112 >= 88 ;
+-17 x61 -15 x127 -19 x71 -29 x105 -6 x32 -22 x100 -28 x112 >= -108 ;
++16 x73 +9 x113 +20 x55 +5 x123 >= 46 ;
++24 x82 +12 x102 +12 x21 +18 x42 >= 64 ;
+-24 x82 -24 x85 -18 x120 -18 x42 >= -79 ;
++15 x120 +21 x113 +27 x125 +21 x33 +19 x105 +30 x77 >= 96 ;
+";
+        let pb_problem = read_opb(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+        let constraints = pb_problem.constraints.clone();
+        let options = SolveOptions {
+            target_weakening: true,
+            ..SolveOptions::default()
+        };
+        let status = solve_opb(
+            pb_problem,
+            &options,
+            &mut CapturingObjectiveReporter {
+                values: Vec::default(),
+            },
+        );
+        let Status::Satisfiable { solution } = status else {
+            panic!("expected a satisfiable status");
+        };
+        assert!(constraints.iter().all(|constraint| is_satisfied(constraint, &solution)));
+    }
+
+    #[test]
+    fn test_maximize_objective() {
+        let input = "max: +3 x1 +2 x2 ;
+-1 x1 -1 x2 >= -1 ;
+";
+        let pb_problem = read_opb(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+        let mut objective_reporter = CapturingObjectiveReporter {
+            values: Vec::default(),
+        };
+        let status = solve_opb(pb_problem, &SolveOptions::default(), &mut objective_reporter);
+        assert!(matches!(
+            status,
+            Status::Optimum {
+                objective_value: 3,
+                ..
+            }
+        ));
+        assert!(!objective_reporter.values.is_empty());
+        assert!(*objective_reporter.values.last().unwrap() == 3);
+    }
+
+    #[test]
+    fn test_objective_value_overflow() {
+        // 目的関数値が i64 に収まらない解が見つかった場合は最適性を示さずにその解を返す
+        let input = "min: +9223372036854775807 x1 +9223372036854775807 x2 ;
++1 x1 +1 x2 >= 2 ;
+";
+        let pb_problem = read_opb(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+        let mut objective_reporter = CapturingObjectiveReporter {
+            values: Vec::default(),
+        };
+        let status = solve_opb(pb_problem, &SolveOptions::default(), &mut objective_reporter);
+        assert!(matches!(status, Status::Satisfiable { .. }));
+        assert!(objective_reporter.values == [2 * i64::MAX as i128]);
+    }
+
+    #[test]
+    fn test_disjunction_of_saturating_literals_and_conjunction() {
+        // 3 x0 + 3 x1 + x2 + x3 + x4 >= 3 は (x0 ∨ x1 ∨ y) と 3 ¬y + x2 + x3 + x4 >= 3 の 2 行になる
+        let x = |index: usize| Literal::new(index, Boolean::TRUE);
+        let mut pb_engine = PBEngine::new(10.0);
+        for _ in 0..5 {
+            pb_engine.add_variable_with_initial_value(Boolean::FALSE, 0.0);
+        }
+        add_integer_linear_constraint(
+            &mut pb_engine,
+            &LinearConstraint::new(
+                [(x(0), 3), (x(1), 3), (x(2), 1), (x(3), 1), (x(4), 1)].into_iter(),
+                3,
+            ),
+            false,
+            true,
+        )
+        .unwrap();
+        assert!(pb_engine.number_of_variables() == 6);
+        assert!(pb_engine.number_of_count_constraints() == 1);
+        assert!(pb_engine.number_of_integer_linear_constraints() == 1);
+
+        // 符号化しても充足可能性は変わらない
+        let solve_opb = |input: &str| {
+            let pb_problem = read_opb(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+            return solve(
+                5,
+                &pb_problem.constraints,
+                None,
+                &SolveOptions::default(),
+                &mut CapturingObjectiveReporter {
+                    values: Vec::default(),
+                },
+            );
+        };
+        let input = "+3 x1 +3 x2 +1 x3 +1 x4 +1 x5 >= 3 ;\n-1 x1 >= 0 ;\n-1 x2 >= 0 ;\n";
+        let Status::Satisfiable { solution } = solve_opb(input) else {
+            panic!();
+        };
+        assert!(solution.len() == 5);
+        assert!(solution[2..].iter().all(|&value| value == Boolean::TRUE));
+        let input = format!("{}-1 x4 >= 0 ;\n", input);
+        assert!(matches!(solve_opb(&input), Status::Unsatisfiable));
+    }
+
+    #[test]
+    fn test_constant_constraint() {
+        let solve_opb = |input: &str| {
+            let pb_problem = read_opb(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+            return solve(
+                1,
+                &pb_problem.constraints,
+                None,
+                &SolveOptions::default(),
+                &mut CapturingObjectiveReporter {
+                    values: Vec::default(),
+                },
+            );
+        };
+
+        // 係数が全て 0 の制約条件は定数の比較になる
+        assert!(matches!(solve_opb("0 x1 >= 1 ;\n"), Status::Unsatisfiable));
+        assert!(matches!(solve_opb("0 x1 = 1 ;\n"), Status::Unsatisfiable));
+        assert!(matches!(
+            solve_opb("0 x1 >= 0 ;\n+1 x1 >= 1 ;\n"),
+            Status::Satisfiable { .. }
+        ));
+
+        // 項を持たない制約条件 (DIMACS の空節など)
+        let constraints = vec![Constraint {
+            sum: Vec::default(),
+            relational_operator: RelationalOperator::GreaterOrEqual,
+            rhs: 1,
+        }];
+        let status = solve(
+            0,
+            &constraints,
+            None,
+            &SolveOptions::default(),
+            &mut CapturingObjectiveReporter {
+                values: Vec::default(),
+            },
+        );
+        assert!(matches!(status, Status::Unsatisfiable));
+    }
+
+    #[test]
+    fn test_minimize_clauses() {
+        // 4 羽の鳩を 3 つの巣に入れる鳩の巣原理 (充足不能) と，巣を 4 つにしたもの (充足可能)
+        let pigeonhole = |number_of_holes: usize| {
+            let mut input = String::default();
+            let variable = |pigeon: usize, hole: usize| pigeon * number_of_holes + hole + 1;
+            for pigeon in 0..4 {
+                for hole in 0..number_of_holes {
+                    input += &format!("+1 x{} ", variable(pigeon, hole));
+                }
+                input += ">= 1 ;\n";
+            }
+            for hole in 0..number_of_holes {
+                for pigeon in 0..4 {
+                    input += &format!("-1 x{} ", variable(pigeon, hole));
+                }
+                input += ">= -1 ;\n";
+            }
+            return read_opb(&mut std::io::BufReader::new(input.as_bytes()))
+                .unwrap()
+                .constraints;
+        };
+        let options = SolveOptions {
+            clausal_learning: true,
+            minimize_clauses: true,
+            ..SolveOptions::default()
+        };
+        let mut objective_reporter = CapturingObjectiveReporter {
+            values: Vec::default(),
+        };
+        let status = solve(12, &pigeonhole(3), None, &options, &mut objective_reporter);
+        assert!(matches!(status, Status::Unsatisfiable));
+        let status = solve(16, &pigeonhole(4), None, &options, &mut objective_reporter);
+        assert!(matches!(status, Status::Satisfiable { .. }));
+    }
+
+    #[test]
+    fn test_lexicographic_optimization() {
+        let input = "min: +1 x1 +1 x2 ;\nmin: -2 x1 -1 x2 ;\n+1 x1 +1 x2 +1 x3 >= 2 ;\n";
+        let pb_problem = read_opb(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+        assert!(pb_problem.objectives.len() == 2);
+        let mut objective_reporter = CapturingObjectiveReporter {
+            values: Vec::default(),
+        };
+        let status = solve_lexicographic(
+            3,
+            &pb_problem.constraints,
+            &pb_problem.objectives,
+            &SolveOptions::default(),
+            &mut objective_reporter,
+        );
+        // 報告されるのは 1 番目の目的関数の値のみ
+        assert!(objective_reporter.values.last() == Some(&1));
+        assert!(objective_reporter.values.is_sorted_by(|a, b| a > b));
+
+        // 2 番目の目的関数だけなら x1 = x2 = 1 (-3) が最適だが，1 番目の最適値 1 を保つ必要がある
+        let Status::LexicographicOptimum {
+            solution,
+            objective_values,
+        } = status
+        else {
+            panic!();
+        };
+        assert!(objective_values == vec![1, -2]);
+        assert!(solution == vec![Boolean::TRUE, Boolean::FALSE, Boolean::TRUE]);
+    }
+
+    #[test]
+    fn test_drat_proof() {
+        // 4 羽の鳩を 3 つの巣に入れる鳩の巣原理を節で表したもの
+        let mut input = String::default();
+        let variable = |pigeon: usize, hole: usize| pigeon * 3 + hole + 1;
+        for pigeon in 0..4 {
+            for hole in 0..3 {
+                input += &format!("+1 x{} ", variable(pigeon, hole));
+            }
+            input += ">= 1 ;\n";
+        }
+        for hole in 0..3 {
+            for pigeon in 0..4 {
+                for other in (pigeon + 1)..4 {
+                    input += &format!(
+                        "-1 x{} -1 x{} >= -1 ;\n",
+                        variable(pigeon, hole),
+                        variable(other, hole)
+                    );
+                }
+            }
+        }
+        let constraints = read_opb(&mut std::io::BufReader::new(input.as_bytes()))
+            .unwrap()
+            .constraints;
+
+        let drat_path =
+            std::env::temp_dir().join(format!("solve_pb_test_{}.drat", std::process::id()));
+        let options = SolveOptions {
+            clausal_learning: true,
+            drat_path: Some(drat_path.to_str().unwrap().to_string()),
+            ..SolveOptions::default()
+        };
+        let status = solve(
+            12,
+            &constraints,
+            None,
+            &options,
+            &mut CapturingObjectiveReporter {
+                values: Vec::default(),
+            },
+        );
+        assert!(matches!(status, Status::Unsatisfiable));
+
+        // 学習した節が 1 行ずつ書き出され，最後に空節が書き出される
+        let proof = std::fs::read_to_string(&drat_path).unwrap();
+        std::fs::remove_file(&drat_path).unwrap();
+        let lines: Vec<&str> = proof.lines().collect();
+        assert!(lines.len() >= 2);
+        assert!(*lines.last().unwrap() == "0");
+        for line in lines.iter() {
+            let mut tokens = line.split_whitespace();
+            assert!(tokens.next_back() == Some("0"));
+            assert!(tokens.all(|token| {
+                token
+                    .parse::<i64>()
+                    .is_ok_and(|literal| literal != 0 && literal.abs() <= 12)
+            }));
+        }
+    }
+
+    /// 制約条件 constraint が割り当て solution のもとで充足されるか
+    fn is_satisfied(constraint: &Constraint, solution: &[Boolean]) -> bool {
+        let lhs: i64 = constraint
+            .sum
+            .iter()
+            .filter(|weighted_term| solution[weighted_term.term.index - 1] == Boolean::TRUE)
+            .map(|weighted_term| weighted_term.weight)
+            .sum();
+        return match constraint.relational_operator {
+            RelationalOperator::GreaterOrEqual => lhs >= constraint.rhs,
+            RelationalOperator::Equal => lhs == constraint.rhs,
+        };
+    }
+
+    /// 全ての割り当てを列挙して problem を解く (変数の数が少ない場合に限る)
+    ///
+    /// 目的関数が 1 つの場合はその最適解を，複数の場合は辞書式順序での最適解を返す
+    fn solve_bruteforce(problem: &PBProblem) -> Status {
+        let number_of_variables = problem
+            .constraints
+            .iter()
+            .flat_map(|constraint| constraint.sum.iter())
+            .chain(problem.objectives.iter().flatten())
+            .map(|weighted_term| weighted_term.term.index)
+            .max()
+            .unwrap_or(0);
+        assert!(number_of_variables <= 20);
+
+        let mut best: Option<(Vec<Boolean>, Vec<i128>)> = None;
+        for bits in 0..1usize << number_of_variables {
+            let solution: Vec<Boolean> = (0..number_of_variables)
+                .map(|index| {
+                    if bits >> index & 1 == 1 {
+                        Boolean::TRUE
+                    } else {
+                        Boolean::FALSE
+                    }
+                })
+                .collect();
+            if !problem
+                .constraints
+                .iter()
+                .all(|constraint| is_satisfied(constraint, &solution))
+            {
+                continue;
+            }
+            let objective_values: Vec<i128> = problem
+                .objectives
+                .iter()
+                .map(|objective| {
+                    objective
+                        .iter()
+                        .filter(|weighted_term| {
+                            solution[weighted_term.term.index - 1] == Boolean::TRUE
+                        })
+                        .map(|weighted_term| weighted_term.weight as i128)
+                        .sum()
+                })
+                .collect();
+            if best
+                .as_ref()
+                .is_none_or(|(_, best_values)| objective_values < *best_values)
+            {
+                best = Some((solution, objective_values));
+            }
+        }
+
+        let Some((solution, mut objective_values)) = best else {
+            return Status::Unsatisfiable;
+        };
+        return match objective_values.len() {
+            0 => Status::Satisfiable { solution },
+            1 => Status::Optimum {
+                solution,
+                objective_value: objective_values.pop().unwrap(),
+            },
+            _ => Status::LexicographicOptimum {
+                solution,
+                objective_values,
+            },
+        };
+    }
+
+    #[test]
+    fn test_mixed_sign_objective() {
+        // 係数の符号が混在する目的関数と，全ての係数が負の目的関数 (最大化に相当する)
+        let inputs = [
+            "* #variable= 4 #constraint= 2
+min: -3 x1 +2 x2 -1 x3 +4 x4 ;
++1 x1 +1 x2 >= 1 ;
+-1 x1 -1 x3 +1 x4 >= -1 ;
+",
+            "* #variable= 3 #constraint= 1
+min: -2 x1 -1 x2 -3 x3 ;
+-1 x1 -1 x2 -1 x3 >= -2 ;
+",
+        ];
+        for input in inputs {
+            let pb_problem = read_opb(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+            let Status::Optimum {
+                objective_value: expected_objective_value,
+                ..
+            } = solve_bruteforce(&pb_problem)
+            else {
+                unreachable!()
+            };
+            let mut objective_reporter = CapturingObjectiveReporter {
+                values: Vec::default(),
+            };
+            let Status::Optimum {
+                solution,
+                objective_value,
+            } = solve(
+                number_of_variables(pb_problem.constraints.iter()),
+                &pb_problem.constraints,
+                Some(&pb_problem.objectives[0]),
+                &SolveOptions::default(),
+                &mut objective_reporter,
+            )
+            else {
+                unreachable!()
+            };
+            assert!(objective_value == expected_objective_value);
+            assert!(
+                pb_problem
+                    .constraints
+                    .iter()
+                    .all(|constraint| is_satisfied(constraint, &solution))
+            );
+            // 報告された値は解の目的関数値そのもので，最後の値が最適値となる
+            assert!(objective_reporter.values.is_sorted_by(|l, r| l > r));
+            assert!(objective_reporter.values.last() == Some(&objective_value));
+        }
+    }
+
+    #[test]
+    fn test_solve_agrees_with_bruteforce() {
+        // xorshift による再現可能な乱数
+        let mut state: u64 = 0x2545f4914f6cdd1d;
+        let mut random = |upper: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            return state % upper;
+        };
+
+        for _ in 0..300 {
+            let number_of_variables = 1 + random(12) as usize;
+            let mut constraints = Vec::default();
+            for _ in 0..1 + random(number_of_variables as u64) {
+                let mut sum: Vec<WeightedTerm> = Vec::default();
+                for index in 1..=number_of_variables {
+                    if random(3) == 0 {
+                        let weight = random(11) as i64 - 5;
+                        if weight != 0 {
+                            sum.push(WeightedTerm {
+                                weight,
+                                term: Variable { index },
+                            });
+                        }
+                    }
+                }
+                constraints.push(Constraint {
+                    sum,
+                    relational_operator: if random(8) == 0 {
+                        RelationalOperator::Equal
+                    } else {
+                        RelationalOperator::GreaterOrEqual
+                    },
+                    rhs: random(7) as i64 - 4,
+                });
+            }
+            let objectives = if random(2) == 0 {
+                Vec::default()
+            } else {
+                vec![
+                    (1..=number_of_variables)
+                        .map(|index| WeightedTerm {
+                            weight: random(7) as i64 - 3,
+                            term: Variable { index },
+                        })
+                        .collect(),
+                ]
+            };
+            let problem = PBProblem {
+                objectives,
+                objective_is_maximize: false,
+                constraints,
+                hint: None,
+            };
+            let expected = solve_bruteforce(&problem);
+
+            for (clausal_learning, sat_encoding) in [(false, true), (true, true), (false, false)] {
+                let options = SolveOptions {
+                    clausal_learning,
+                    sat_encoding,
+                    ..SolveOptions::default()
+                };
+                let status = solve(
+                    number_of_variables,
+                    &problem.constraints,
+                    problem.objectives.first().map(Vec::as_slice),
+                    &options,
+                    &mut CapturingObjectiveReporter {
+                        values: Vec::default(),
+                    },
                 );
+                match (&status, &expected) {
+                    (Status::Unsatisfiable, Status::Unsatisfiable) => {}
+                    (Status::Satisfiable { solution }, Status::Satisfiable { .. }) => {
+                        assert!(
+                            problem
+                                .constraints
+                                .iter()
+                                .all(|constraint| is_satisfied(constraint, solution))
+                        );
+                    }
+                    (
+                        Status::Optimum {
+                            solution,
+                            objective_value,
+                        },
+                        Status::Optimum {
+                            objective_value: expected_objective_value,
+                            ..
+                        },
+                    ) => {
+                        assert!(objective_value == expected_objective_value);
+                        assert!(
+                            problem
+                                .constraints
+                                .iter()
+                                .all(|constraint| is_satisfied(constraint, solution))
+                        );
+                    }
+                    _ => panic!("{:?}", problem),
+                }
             }
-        } else {
-            pb_engine.add_integer_linear_constraint(integer_linear_constraint, is_learnt);
         }
     }
 }
@@ -0,0 +1,60 @@
+/// 最適化の途中で得られた目的関数値の報告先
+pub trait ObjectiveReporter {
+    /// 目的関数値が改善された解が得られるたびに呼ばれる
+    fn report(&mut self, value: i128);
+
+    /// 目的関数値の下界が得られたときに呼ばれる
+    fn report_lower_bound(&mut self, _value: i128) {}
+
+    /// 目的関数値の上界が得られたときに呼ばれる (最大化問題を最小化問題として解く場合)
+    fn report_upper_bound(&mut self, _value: i128) {}
+}
+
+/// 目的関数値を "o <value>" の形式で標準出力に出力する
+pub struct StdoutObjectiveReporter;
+
+impl ObjectiveReporter for StdoutObjectiveReporter {
+    fn report(&mut self, value: i128) {
+        println!("o {}", value);
+    }
+
+    /// "c o-lb <value>" の形式で出力する (o 行と区別するためコメント行とする)
+    fn report_lower_bound(&mut self, value: i128) {
+        println!("c o-lb {}", value);
+    }
+
+    /// "c o-ub <value>" の形式で出力する
+    fn report_upper_bound(&mut self, value: i128) {
+        println!("c o-ub {}", value);
+    }
+}
+
+/// 報告された値を出力せずに捨てる
+pub struct IgnoringObjectiveReporter;
+
+impl ObjectiveReporter for IgnoringObjectiveReporter {
+    fn report(&mut self, _value: i128) {}
+}
+
+/// 符号を反転した目的関数を最小化する際に，元の目的関数の値に戻して inner に報告する
+///
+/// 最小化する目的関数の下界は元の目的関数の上界となる
+pub struct NegatedObjectiveReporter<'a, ObjectiveReporterT: ObjectiveReporter> {
+    pub inner: &'a mut ObjectiveReporterT,
+}
+
+impl<ObjectiveReporterT: ObjectiveReporter> ObjectiveReporter
+    for NegatedObjectiveReporter<'_, ObjectiveReporterT>
+{
+    fn report(&mut self, value: i128) {
+        self.inner.report(-value);
+    }
+
+    fn report_lower_bound(&mut self, value: i128) {
+        self.inner.report_upper_bound(-value);
+    }
+
+    fn report_upper_bound(&mut self, value: i128) {
+        self.inner.report_lower_bound(-value);
+    }
+}
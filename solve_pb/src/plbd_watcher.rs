@@ -1,12 +1,19 @@
 use std::f64::INFINITY;
 
+/// 学習制約の PLBD の推移を監視し，リスタートの判断に用いる統計量を提供する
+///
+/// 直近 short_window 個の PLBD の単純移動平均 (短期平均) を求め，
+/// さらに短期平均の指数移動平均と指数移動分散 (時定数 long_window) を長期の統計量とする．
+/// 短期平均が長期の分布に比べて大きい (探索が難航している) ほど lower_tail_probability は 1 に近づく
 pub struct PLBDWatcher {
     pub short_term_average: ShortTermAverage,
     pub long_term_average: LongTermAverage,
 }
 
 impl PLBDWatcher {
+    /// 窓の大きさはいずれも 1 以上でなければならない
     pub fn new(short_time_constant: usize, long_time_constant: usize) -> Self {
+        assert!(short_time_constant > 0 && long_time_constant > 0);
         Self {
             short_term_average: ShortTermAverage::new(short_time_constant),
             long_term_average: LongTermAverage::new(long_time_constant),
@@ -18,10 +25,26 @@ impl PLBDWatcher {
         self.long_term_average.add(self.short_term_average.mean());
     }
 
+    /// 直近 short_window 個の PLBD の平均
+    pub fn short_term_mean(&self) -> f64 {
+        return self.short_term_average.mean();
+    }
+
+    /// 短期平均の指数移動平均
+    pub fn long_term_mean(&self) -> f64 {
+        return self.long_term_average.mean();
+    }
+
+    /// 短期平均の指数移動分散
+    pub fn long_term_variance(&self) -> f64 {
+        return self.long_term_average.variance();
+    }
+
+    /// 短期平均が平均 long_term_mean ，分散 long_term_variance の正規分布に従うとしたときの累積分布関数の値
     pub fn lower_tail_probability(&self) -> f64 {
-        let value = self.short_term_average.mean();
-        let mean = self.long_term_average.mean();
-        let variance = self.long_term_average.variance();
+        let value = self.short_term_mean();
+        let mean = self.long_term_mean();
+        let variance = self.long_term_variance();
         if variance > 0.0 {
             return 0.5 * (1.0 + ((value - mean) / (2.0 * variance).sqrt()).erf());
         } else {
@@ -108,3 +131,19 @@ impl LongTermAverage {
         return self.variance;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::PLBDWatcher;
+
+    #[test]
+    fn test_plbd_watcher() {
+        let mut plbd_watcher = PLBDWatcher::new(2, 100);
+        plbd_watcher.add(4);
+        plbd_watcher.add(6);
+        assert!(plbd_watcher.short_term_mean() == 5.0);
+        assert!(plbd_watcher.long_term_mean() == 4.5);
+        plbd_watcher.add(8);
+        assert!(plbd_watcher.short_term_mean() == 7.0);
+    }
+}
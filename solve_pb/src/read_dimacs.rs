@@ -0,0 +1,137 @@
+use std::io::BufRead;
+
+use crate::read_opb::{Constraint, PBProblem, RelationalOperator, Variable, WeightedTerm};
+
+/// DIMACS CNF 形式の入力を読み込み，各節を線形不等式制約に変換する
+///
+/// 節 x_1 ∨ ... ∨ ¬x_k ∨ ... は x_1 + ... + (1 - x_k) + ... >= 1 ，すなわち
+/// 正リテラルの係数を 1 ，負リテラルの係数を -1 とし，右辺を 1 - (負リテラルの個数) とした制約になる
+pub fn read_dimacs(input: &mut impl BufRead) -> Option<PBProblem> {
+    let mut constraints = Vec::default();
+    let mut number_of_variables: Option<usize> = None;
+    let mut clause: Vec<i64> = Vec::default();
+
+    let mut line = String::default();
+    loop {
+        line.clear();
+        let Ok(bytes) = input.read_line(&mut line) else {
+            return None;
+        };
+        if bytes == 0 {
+            break;
+        }
+        let trimmed_line = line.trim_start();
+        if trimmed_line.starts_with('c') || trimmed_line.is_empty() {
+            continue;
+        }
+        // SATLIB のベンチマークに見られる終端記号
+        if trimmed_line.starts_with('%') {
+            break;
+        }
+        if trimmed_line.starts_with('p') {
+            // p cnf <変数の数> <節の数>
+            let tokens = Vec::from_iter(trimmed_line.split_whitespace());
+            if number_of_variables.is_some() || tokens.len() != 4 || tokens[1] != "cnf" {
+                return None;
+            }
+            number_of_variables = Some(tokens[2].parse().ok()?);
+            let _: usize = tokens[3].parse().ok()?;
+            continue;
+        }
+        // ヘッダより前に節が現れてはならない
+        let number_of_variables = number_of_variables?;
+        for token in trimmed_line.split_whitespace() {
+            let literal: i64 = token.parse().ok()?;
+            if literal == 0 {
+                if let Some(constraint) = clause_to_constraint(&mut clause) {
+                    constraints.push(constraint);
+                }
+                clause.clear();
+            } else {
+                if literal.unsigned_abs() as usize > number_of_variables {
+                    return None;
+                }
+                clause.push(literal);
+            }
+        }
+    }
+
+    // 0 で終端されていない最後の節
+    if !clause.is_empty()
+        && let Some(constraint) = clause_to_constraint(&mut clause)
+    {
+        constraints.push(constraint);
+    }
+
+    number_of_variables?;
+    return Some(PBProblem {
+        objectives: Vec::default(),
+        objective_is_maximize: false,
+        constraints,
+        hint: None,
+    });
+}
+
+/// 節を制約条件に変換する (恒真な節であれば None を返す)
+fn clause_to_constraint(clause: &mut Vec<i64>) -> Option<Constraint> {
+    clause.sort_unstable_by_key(|literal| (literal.unsigned_abs(), *literal));
+    clause.dedup();
+    if clause.windows(2).any(|window| window[0] == -window[1]) {
+        return None;
+    }
+    let number_of_negative_literals = clause.iter().filter(|&&literal| literal < 0).count();
+    return Some(Constraint {
+        sum: clause
+            .iter()
+            .map(|&literal| WeightedTerm {
+                weight: literal.signum(),
+                term: Variable {
+                    index: literal.unsigned_abs() as usize,
+                },
+            })
+            .collect(),
+        relational_operator: RelationalOperator::GreaterOrEqual,
+        rhs: 1 - number_of_negative_literals as i64,
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::read_dimacs;
+
+    #[test]
+    fn test_read_dimacs() {
+        let input = "c 3-SAT
+p cnf 4 3
+1 -2 3 0
+-1 2 -4 0
+2 3
+4 0
+1 -1 2 0
+";
+        let pb_problem = read_dimacs(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+        // 恒真な節 1 -1 2 は除かれる
+        assert!(pb_problem.constraints.len() == 3);
+        let constraint = &pb_problem.constraints[1];
+        assert!(
+            Vec::from_iter(
+                constraint
+                    .sum
+                    .iter()
+                    .map(|weighted_term| (weighted_term.weight, weighted_term.term.index))
+            ) == vec![(-1, 1), (1, 2), (-1, 4)]
+        );
+        assert!(constraint.rhs == -1);
+        assert!(pb_problem.constraints[2].sum.len() == 3);
+        assert!(pb_problem.constraints[2].rhs == 1);
+
+        // ヘッダのない入力や範囲外の変数は受け付けない
+        assert!(read_dimacs(&mut std::io::BufReader::new("1 2 0\n".as_bytes())).is_none());
+        assert!(
+            read_dimacs(&mut std::io::BufReader::new(
+                "p cnf 1 1\n1 2 0\n".as_bytes()
+            ))
+            .is_none()
+        );
+    }
+}
@@ -4,24 +4,88 @@ use nom::{
     IResult, Parser,
     branch::alt,
     bytes::complete::tag,
-    character::{
-        complete::{digit1, newline, not_line_ending, space1},
-        streaming::space0,
-    },
-    combinator::{map, map_res, opt, recognize},
-    multi::many1,
+    character::complete::{digit1, newline, not_line_ending, space0, space1},
+    combinator::{eof, map, map_res, opt, recognize, value},
+    multi::{many0, many1},
 };
 use num::{Integer, Signed};
+use pb_engine::{Boolean, Literal};
 
 #[derive(Clone, Debug)]
 pub struct PBProblem {
+    /// min: 行の目的関数 (複数ある場合は現れた順に辞書式順序で最小化する)
+    ///
+    /// max: 行の目的関数は係数の符号を反転して最小化する目的関数とする
+    pub objectives: Vec<Vec<WeightedTerm>>,
+    /// 目的関数が max: 行で与えられたか (目的関数値を出力する際に符号を戻す)
+    pub objective_is_maximize: bool,
     pub constraints: Vec<Constraint>,
+    /// "* hint: x1 -x2" 行で与えられた探索の初期値 (リテラルのインデックスは 0 から始まる)
+    pub hint: Option<Vec<Literal>>,
+}
+
+impl PBProblem {
+    /// 各変数が現れる制約条件の数 (次数) を，0 から始まるインデックスで並べて返す
+    ///
+    /// 1 つの制約条件に同じ変数が複数回現れても 1 と数える．目的関数にのみ現れる変数の次数は 0 とする
+    pub fn variable_degrees(&self) -> Vec<usize> {
+        let number_of_variables = self
+            .constraints
+            .iter()
+            .flat_map(|constraint| constraint.sum.iter())
+            .chain(self.objectives.iter().flatten())
+            .map(|weighted_term| weighted_term.term.index)
+            .max()
+            .unwrap_or(0);
+        let mut degrees = vec![0; number_of_variables];
+        let mut indices = Vec::default();
+        for constraint in self.constraints.iter() {
+            indices.clear();
+            indices.extend(constraint.sum.iter().map(|weighted_term| weighted_term.term.index - 1));
+            indices.sort_unstable();
+            indices.dedup();
+            for &index in indices.iter() {
+                degrees[index] += 1;
+            }
+        }
+        return degrees;
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum CommentOrConstraint {
     Comment(String),
+    Hint(Vec<Literal>),
+    Objective(Vec<WeightedTerm>),
+    /// max: 行の目的関数 (係数の符号を反転済み)
+    MaximizeObjective(Vec<WeightedTerm>),
+    Constraint(Constraint),
+    BlankLine,
+}
+
+#[derive(Clone, Debug)]
+pub enum ObjectiveOrConstraint {
+    Objective(Vec<WeightedTerm>),
+    /// max: 行の目的関数 (係数の符号を反転済み)
+    MaximizeObjective(Vec<WeightedTerm>),
     Constraint(Constraint),
+    Hint(Vec<Literal>),
+}
+
+#[derive(Clone, Debug)]
+pub struct WboProblem {
+    pub hard: Vec<Constraint>,
+    pub soft: Vec<(u64, Constraint)>,
+    pub top: Option<u64>,
+}
+
+#[derive(Clone, Debug)]
+pub enum WboStatement {
+    Comment,
+    BlankLine,
+    SoftHeader(Option<u64>),
+    HardConstraint(Constraint),
+    SoftConstraint(u64, Constraint),
 }
 
 #[derive(Clone, Debug)]
@@ -37,8 +101,18 @@ pub enum RelationalOperator {
     GreaterOrEqual,
 }
 
+/// 入力に現れる関係演算子 (厳密不等号は constraint で非厳密な不等号に正規化される)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ParsedRelationalOperator {
+    Equal,
+    GreaterOrEqual,
+    Greater,
+    Less,
+}
+
 #[derive(Clone, Debug)]
 pub struct WeightedTerm {
+    /// 係数 (pb_engine の wide-coefficients を有効にしても i64 に収まらない係数は受理しない)
     pub weight: i64,
     pub term: Term,
 }
@@ -50,10 +124,166 @@ pub struct Variable {
     pub index: usize,
 }
 
+/// read_opb_streaming で入力を読み取れなかった理由
+#[derive(Debug)]
+pub enum ParseError {
+    Io(std::io::Error),
+    /// line_number 行目 (1 から始まる) が OPB 形式の行として解釈できない
+    Syntax { line_number: usize },
+}
+
 pub fn read_opb(
     input: &mut std::io::BufReader<impl std::io::Read>,
-) -> Option<PBProblem> {
+) -> Result<PBProblem, ParseError> {
+    let mut objectives = Vec::default();
+    let mut objective_is_maximize = false;
     let mut constraints = Vec::default();
+    let mut hint: Option<Vec<Literal>> = None;
+    for objective_or_constraint in read_opb_streaming(input) {
+        match objective_or_constraint? {
+            ObjectiveOrConstraint::Objective(objective) => objectives.push(objective),
+            ObjectiveOrConstraint::MaximizeObjective(objective) => {
+                objective_is_maximize = true;
+                objectives.push(objective);
+            }
+            ObjectiveOrConstraint::Constraint(constraint) => constraints.push(constraint),
+            ObjectiveOrConstraint::Hint(literals) => {
+                hint.get_or_insert_default().extend(literals);
+            }
+        }
+    }
+
+    // PBProblem を構築して返す
+    return Ok(PBProblem {
+        objectives,
+        objective_is_maximize,
+        constraints,
+        hint,
+    });
+}
+
+/// 目的関数と制約条件を 1 行ずつ読み取って，入力に現れた順に返す (hint 以外のコメント行と空行は読み飛ばす)
+///
+/// 全ての制約条件を保持せずに処理できる．エラーを返した後は None を返す．min: 行と max: 行の両方を含む
+/// 入力は，最初の目的関数と向きが異なる目的関数の行を構文エラーとする
+pub fn read_opb_streaming(
+    input: &mut std::io::BufReader<impl std::io::Read>,
+) -> impl Iterator<Item = Result<ObjectiveOrConstraint, ParseError>> + '_ {
+    let mut line = String::default();
+    let mut line_number = 0;
+    let mut is_terminated = false;
+    // 最初の目的関数が max: 行であったか
+    let mut is_maximize = None;
+    return std::iter::from_fn(move || {
+        while !is_terminated {
+            line.clear();
+            line_number += 1;
+            let bytes = match input.read_line(&mut line) {
+                Ok(bytes) => bytes,
+                Err(error) => {
+                    is_terminated = true;
+                    return Some(Err(ParseError::Io(error)));
+                }
+            };
+            if bytes == 0 {
+                is_terminated = true;
+                break;
+            }
+            let is_last_line = input.fill_buf().is_ok_and(|buffer| buffer.is_empty());
+            let Some(comment_or_constraint) =
+                parse_line(line.as_str(), is_last_line, comment_or_constraint)
+            else {
+                is_terminated = true;
+                return Some(Err(ParseError::Syntax { line_number }));
+            };
+
+            match comment_or_constraint {
+                CommentOrConstraint::Objective(objective) => {
+                    if *is_maximize.get_or_insert(false) {
+                        is_terminated = true;
+                        return Some(Err(ParseError::Syntax { line_number }));
+                    }
+                    return Some(Ok(ObjectiveOrConstraint::Objective(objective)));
+                }
+                CommentOrConstraint::MaximizeObjective(objective) => {
+                    if !*is_maximize.get_or_insert(true) {
+                        is_terminated = true;
+                        return Some(Err(ParseError::Syntax { line_number }));
+                    }
+                    return Some(Ok(ObjectiveOrConstraint::MaximizeObjective(objective)));
+                }
+                CommentOrConstraint::Constraint(constraint) => {
+                    return Some(Ok(ObjectiveOrConstraint::Constraint(constraint)));
+                }
+                CommentOrConstraint::Hint(literals) => {
+                    return Some(Ok(ObjectiveOrConstraint::Hint(literals)));
+                }
+                CommentOrConstraint::Comment(_) | CommentOrConstraint::BlankLine => {}
+            }
+        }
+        return None;
+    });
+}
+
+/// read_opb_batch で問題を区切る行
+pub const BATCH_SEPARATOR: &str = "* ---";
+
+/// BATCH_SEPARATOR の行で区切られた複数の問題を順に読み取る
+///
+/// 区切りの間をそれぞれ read_opb で読み取る．入力の最後が区切りの行であれば空の問題は返さない．
+/// 構文エラーの行番号は入力全体での行番号とし，構文エラーの後も次の問題から読み取りを続ける
+pub fn read_opb_batch(
+    input: &mut std::io::BufReader<impl std::io::Read>,
+) -> impl Iterator<Item = Result<PBProblem, ParseError>> + '_ {
+    let mut line = String::default();
+    let mut line_number = 0;
+    let mut is_terminated = false;
+    return std::iter::from_fn(move || {
+        if is_terminated {
+            return None;
+        }
+        let first_line_number = line_number;
+        let mut segment = String::default();
+        loop {
+            line.clear();
+            match input.read_line(&mut line) {
+                Ok(0) => {
+                    is_terminated = true;
+                    if line_number == first_line_number {
+                        return None;
+                    }
+                    break;
+                }
+                Ok(_) => {}
+                Err(error) => {
+                    is_terminated = true;
+                    return Some(Err(ParseError::Io(error)));
+                }
+            }
+            line_number += 1;
+            if line.trim() == BATCH_SEPARATOR {
+                break;
+            }
+            segment.push_str(&line);
+        }
+        return Some(
+            read_opb(&mut std::io::BufReader::new(segment.as_bytes())).map_err(|error| match error {
+                ParseError::Syntax { line_number } => ParseError::Syntax {
+                    line_number: first_line_number + line_number,
+                },
+                error => error,
+            }),
+        );
+    });
+}
+
+pub fn read_wbo(
+    input: &mut std::io::BufReader<impl std::io::Read>,
+) -> Option<WboProblem> {
+    let mut hard = Vec::default();
+    let mut soft = Vec::default();
+    let mut top = None;
+    let mut has_soft_header = false;
 
     let mut line = String::default();
     loop {
@@ -64,20 +294,63 @@ pub fn read_opb(
         if bytes == 0 {
             break;
         }
-        let Ok((residual, comment_or_constraint)) = comment_or_constraint(line.as_str()) else {
-            return None;
-        };
-        if residual != "" {
-            return None;
+        let is_last_line = input.fill_buf().is_ok_and(|buffer| buffer.is_empty());
+        let wbo_statement = parse_line(line.as_str(), is_last_line, wbo_statement)?;
+
+        match wbo_statement {
+            WboStatement::Comment | WboStatement::BlankLine => {}
+            WboStatement::SoftHeader(soft_header) => {
+                // soft ヘッダは制約条件より前にちょうど 1 回だけ現れる
+                if has_soft_header || !hard.is_empty() || !soft.is_empty() {
+                    return None;
+                }
+                // 目的関数の係数として i64 で扱えない値は受理しない
+                if soft_header.is_some_and(|top| i64::try_from(top).is_err()) {
+                    return None;
+                }
+                has_soft_header = true;
+                top = soft_header;
+            }
+            WboStatement::HardConstraint(constraint) => {
+                if !has_soft_header {
+                    return None;
+                }
+                hard.push(constraint);
+            }
+            WboStatement::SoftConstraint(weight, constraint) => {
+                if !has_soft_header || i64::try_from(weight).is_err() {
+                    return None;
+                }
+                soft.push((weight, constraint));
+            }
         }
+    }
 
-        if let CommentOrConstraint::Constraint(constraint) = comment_or_constraint {
-            constraints.push(constraint);
-        }
+    if !has_soft_header {
+        return None;
     }
 
-    // PBProblem を構築して返す
-    return Some(PBProblem { constraints });
+    // WboProblem を構築して返す
+    return Some(WboProblem { hard, soft, top });
+}
+
+// 1 行全体を parser で読み取る．入力の最後の行に限り，行末の ";" が省略されていても受理する
+fn parse_line<T>(
+    line: &str,
+    is_last_line: bool,
+    parser: impl Fn(&str) -> IResult<&str, T>,
+) -> Option<T> {
+    if let Ok(("", output)) = parser(line) {
+        return Some(output);
+    }
+    if !is_last_line {
+        return None;
+    }
+    let terminated_line = format!("{} ;", line.trim_end());
+    return match parser(terminated_line.as_str()) {
+        Ok(("", output)) => Some(output),
+        _ => None,
+    };
 }
 
 fn sequence_of_comment_or_constraint(input: &str) -> IResult<&str, Vec<CommentOrConstraint>> {
@@ -86,14 +359,109 @@ fn sequence_of_comment_or_constraint(input: &str) -> IResult<&str, Vec<CommentOr
 }
 
 fn comment_or_constraint(input: &str) -> IResult<&str, CommentOrConstraint> {
-    // <comment_or_constraint> ::= <comment> | <constraint>
+    // <comment_or_constraint> ::= <hint> | <comment> | <objective> | <constraint> | <blank_line>
     alt((
+        map(hint, CommentOrConstraint::Hint),
         map(comment, CommentOrConstraint::Comment),
+        map(objective, CommentOrConstraint::Objective),
+        map(maximize_objective, CommentOrConstraint::MaximizeObjective),
         map(constraint, CommentOrConstraint::Constraint),
+        value(CommentOrConstraint::BlankLine, blank_line),
+    ))
+    .parse(input)
+}
+
+fn wbo_statement(input: &str) -> IResult<&str, WboStatement> {
+    // <wbo_statement> ::= <comment> | <soft_header> | <soft_constraint> | <constraint> | <blank_line>
+    alt((
+        value(WboStatement::Comment, comment),
+        value(WboStatement::BlankLine, blank_line),
+        map(soft_header, WboStatement::SoftHeader),
+        map(soft_constraint, |(weight, constraint)| {
+            WboStatement::SoftConstraint(weight, constraint)
+        }),
+        map(constraint, WboStatement::HardConstraint),
     ))
     .parse(input)
 }
 
+fn soft_header(input: &str) -> IResult<&str, Option<u64>> {
+    // <soft_header> ::= "soft:" [<unsigned_integer>] ";"
+    map(
+        (
+            tag("soft:"),
+            space0,
+            opt(unsigined_integer),
+            space0,
+            tag(";"),
+            opt(newline),
+        ),
+        |(_, _, top, _, _, _)| top,
+    )
+    .parse(input)
+}
+
+fn soft_constraint(input: &str) -> IResult<&str, (u64, Constraint)> {
+    // <soft_constraint> ::= "[" <zeroOrMoreSpace> <unsigned_integer> <zeroOrMoreSpace> "]" <constraint>
+    map(
+        (
+            tag("["),
+            space0,
+            unsigined_integer,
+            space0,
+            tag("]"),
+            space0,
+            constraint,
+        ),
+        |(_, _, weight, _, _, _, constraint)| (weight, constraint),
+    )
+    .parse(input)
+}
+
+fn objective(input: &str) -> IResult<&str, Vec<WeightedTerm>> {
+    // <objective> ::= "min:" <zeroOrMoreSpace> <sum> ";"
+    // 空の目的関数 "min: ;" も受理する
+    objective_with_direction("min:", input)
+}
+
+fn maximize_objective(input: &str) -> IResult<&str, Vec<WeightedTerm>> {
+    // <maximize_objective> ::= "max:" <zeroOrMoreSpace> <sum> ";"
+    // 係数の符号を反転して返す (符号を反転できない係数は受理しない)
+    map_res(
+        |input| objective_with_direction("max:", input),
+        |sum: Vec<WeightedTerm>| {
+            sum.into_iter()
+                .map(|weighted_term| {
+                    weighted_term
+                        .weight
+                        .checked_neg()
+                        .map(|weight| WeightedTerm { weight, ..weighted_term })
+                        .ok_or(())
+                })
+                .collect::<Result<Vec<_>, ()>>()
+        },
+    )
+    .parse(input)
+}
+
+fn objective_with_direction<'a>(
+    direction: &'static str,
+    input: &'a str,
+) -> IResult<&'a str, Vec<WeightedTerm>> {
+    map(
+        (
+            tag(direction),
+            space0,
+            opt(sum),
+            space0,
+            tag(";"),
+            opt(newline),
+        ),
+        |(_, _, sum, _, _, _)| sum.unwrap_or_default(),
+    )
+    .parse(input)
+}
+
 fn comment(input: &str) -> IResult<&str, String> {
     // <comment> ::= "*" <any_sequence_of_characters_other_than_EOL> <EOL>
     map((tag("*"), not_line_ending, newline), |(_, comment, _)| {
@@ -102,10 +470,52 @@ fn comment(input: &str) -> IResult<&str, String> {
     .parse(input)
 }
 
+fn hint(input: &str) -> IResult<&str, Vec<Literal>> {
+    // <hint> ::= "*" <zeroOrMoreSpace> "hint:" { <oneOrMoreSpace> <literal> } <zeroOrMoreSpace> <EOL>
+    // <literal> ::= <variableName> | "-" <variableName>
+    map(
+        (
+            tag("*"),
+            space0,
+            tag("hint:"),
+            many0(map((space1, hint_literal), |(_, literal)| literal)),
+            space0,
+            newline,
+        ),
+        |(_, _, _, literals, _, _)| literals,
+    )
+    .parse(input)
+}
+
+fn hint_literal(input: &str) -> IResult<&str, Literal> {
+    map_res(
+        (opt(tag("-")), variable_name),
+        |(negation, variable)| -> Result<Literal, ()> {
+            if variable.index == 0 {
+                return Err(());
+            }
+            let value = if negation.is_some() {
+                Boolean::FALSE
+            } else {
+                Boolean::TRUE
+            };
+            return Ok(Literal::new(variable.index - 1, value));
+        },
+    )
+    .parse(input)
+}
+
+fn blank_line(input: &str) -> IResult<&str, ()> {
+    // <blank_line> ::= <zeroOrMoreSpace> <EOL>
+    value((), (space0, alt((value((), newline), value((), eof))))).parse(input)
+}
+
 fn constraint(input: &str) -> IResult<&str, Constraint> {
     // <constraint>::= <sum> <relational_operator> <zeroOrMoreSpace> <integer> <zeroOrMoreSpace> ";"
     // ↑おそらく定義のミスで，実際のデータでは";"の後に改行がある
-    map(
+    // 厳密不等号は，係数と変数がいずれも整数であることから
+    // sum > rhs を sum >= rhs + 1 に， sum < rhs を -sum >= 1 - rhs に正確に置き換えられる
+    map_res(
         (
             sum,
             relational_operator,
@@ -115,20 +525,50 @@ fn constraint(input: &str) -> IResult<&str, Constraint> {
             tag(";"),
             opt(newline),
         ),
-        |(sum, relational_operator, _, rhs, _, _, _)| Constraint {
-            sum,
-            relational_operator,
-            rhs,
+        |(sum, relational_operator, _, rhs, _, _, _)| -> Result<Constraint, ()> {
+            return match relational_operator {
+                ParsedRelationalOperator::Equal => Ok(Constraint {
+                    sum,
+                    relational_operator: RelationalOperator::Equal,
+                    rhs,
+                }),
+                ParsedRelationalOperator::GreaterOrEqual => Ok(Constraint {
+                    sum,
+                    relational_operator: RelationalOperator::GreaterOrEqual,
+                    rhs,
+                }),
+                ParsedRelationalOperator::Greater => Ok(Constraint {
+                    sum,
+                    relational_operator: RelationalOperator::GreaterOrEqual,
+                    rhs: rhs.checked_add(1).ok_or(())?,
+                }),
+                ParsedRelationalOperator::Less => Ok(Constraint {
+                    sum: sum
+                        .into_iter()
+                        .map(|weighted_term| {
+                            Some(WeightedTerm {
+                                weight: weighted_term.weight.checked_neg()?,
+                                term: weighted_term.term,
+                            })
+                        })
+                        .collect::<Option<_>>()
+                        .ok_or(())?,
+                    relational_operator: RelationalOperator::GreaterOrEqual,
+                    rhs: 1i64.checked_sub(rhs).ok_or(())?,
+                }),
+            };
         },
     )
     .parse(input)
 }
 
-fn relational_operator(input: &str) -> IResult<&str, RelationalOperator> {
-    // <relational_operator> ::= "=" | ">="
+fn relational_operator(input: &str) -> IResult<&str, ParsedRelationalOperator> {
+    // <relational_operator> ::= "=" | ">=" | ">" | "<"
     alt((
-        map(tag("="), |_| RelationalOperator::Equal),
-        map(tag(">="), |_| RelationalOperator::GreaterOrEqual),
+        map(tag("="), |_| ParsedRelationalOperator::Equal),
+        map(tag(">="), |_| ParsedRelationalOperator::GreaterOrEqual),
+        map(tag(">"), |_| ParsedRelationalOperator::Greater),
+        map(tag("<"), |_| ParsedRelationalOperator::Less),
     ))
     .parse(input)
 }
@@ -141,8 +581,9 @@ fn sum(input: &str) -> IResult<&str, Vec<WeightedTerm>> {
 fn weighted_term(input: &str) -> IResult<&str, WeightedTerm> {
     // <weightedterm> ::= <integer> <oneOrMoreSpace> <term> <oneOrMoreSpace>
     // <term>::=<variableName>  # for linear instances
+    // 係数と変数名の間に空白を置かない出力 (+1x1) もあるため，係数の後の空白は省略できる
     map(
-        (integer, space1, variable_name, space1),
+        (integer, space0, variable_name, space1),
         |(weight, _, term, _)| WeightedTerm { weight, term },
     )
     .parse(input)
@@ -209,7 +650,10 @@ mod test {
 
     use crate::read_opb::{integer1, unsigined_integer};
 
-    use super::{Variable, integer, integer2, read_opb};
+    use super::{
+        Constraint, ObjectiveOrConstraint, ParseError, Variable, integer, integer2, read_opb,
+        read_opb_batch, read_opb_streaming, read_wbo,
+    };
 
     #[test]
     fn test_unsigined_integer() {
@@ -295,12 +739,256 @@ mod test {
         assert!(t.weight == -3);
         assert!(t.term.index == 1);
 
+        // 係数と変数名の間の空白は省略できる
+        for input in ["3x1 ", "+3x1 ", "+3 x1 ", "3  x1 "] {
+            let (s, t) = weighted_term(input).unwrap();
+            assert!(s.is_empty());
+            assert!(t.weight == 3);
+            assert!(t.term.index == 1);
+        }
+        let (s, t) = weighted_term("-3x1 ").unwrap();
+        assert!(s.is_empty());
+        assert!(t.weight == -3);
+
         assert!(weighted_term("3 x1").is_err());
         assert!(weighted_term("3x1").is_err());
+        assert!(weighted_term("3x1x2 ").is_err());
+        assert!(weighted_term("+ 3 x1 ").is_err());
         assert!(weighted_term("x1 ").is_err());
         assert!(weighted_term("x1").is_err());
     }
 
+    #[test]
+    fn test_constraint() {
+        use super::constraint;
+
+        let (s, c) = constraint("+1 x1 +2 x2 >= 1 ;\n").unwrap();
+        assert!(s.is_empty());
+        assert!(c.sum.len() == 2);
+        assert!(c.rhs == 1);
+
+        // ; の省略は入力の最後の行でのみ read_opb が受理する
+        assert!(constraint("+1 x1 +2 x2 >= 1").is_err());
+        assert!(constraint("+1 x1 +2 x2 >= 1 \n").is_err());
+
+        // 係数と変数名の間に空白がない項
+        let (s, c) = constraint("+1x1 2x2 -1 x3 >= 1 ;\n").unwrap();
+        assert!(s.is_empty());
+        assert!(c.sum.len() == 3);
+        assert!(c.sum[1].weight == 2 && c.sum[1].term.index == 2);
+
+        assert!(constraint("+1 x1 >=").is_err());
+        assert!(constraint("+1 x1 >= \n").is_err());
+        assert!(constraint("+1 x1 >= 1 ; 2").is_ok_and(|(s, _)| s == " 2"));
+    }
+
+    #[test]
+    fn test_strict_inequality() {
+        use super::{RelationalOperator, constraint};
+
+        // 3 x1 > 2 は 3 x1 >= 3
+        let (s, c) = constraint("3 x1 > 2 ;\n").unwrap();
+        assert!(s.is_empty());
+        assert!(c.relational_operator == RelationalOperator::GreaterOrEqual);
+        assert!(c.sum.len() == 1 && c.sum[0].weight == 3 && c.sum[0].term == Variable { index: 1 });
+        assert!(c.rhs == 3);
+
+        // +1 x1 -2 x2 < 1 は -1 x1 +2 x2 >= 0
+        let (s, c) = constraint("+1 x1 -2 x2 < 1 ;\n").unwrap();
+        assert!(s.is_empty());
+        assert!(c.relational_operator == RelationalOperator::GreaterOrEqual);
+        assert!(c.sum[0].weight == -1 && c.sum[1].weight == 2);
+        assert!(c.rhs == 0);
+
+        // 右辺の調整で桁あふれする場合は受理しない
+        assert!(constraint("+1 x1 > 9223372036854775807 ;\n").is_err());
+    }
+
+    #[test]
+    fn test_read_opb() {
+        // 最後の制約条件に ; がない
+        let input = "* #variable= 2 #constraint= 2\n+1 x1 +1 x2 >= 1 ;\n-1 x1 -1 x2 >= -1";
+        let pb_problem = read_opb(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+        assert!(pb_problem.constraints.len() == 2);
+        assert!(pb_problem.constraints[1].rhs == -1);
+        let input = "+1 x1 +1 x2 >= 1 ;\nmin: +1 x1\n";
+        let pb_problem = read_opb(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+        assert!(pb_problem.objectives.len() == 1);
+
+        // 最後の行以外の ; は省略できない
+        let input = "+1 x1 +1 x2 >= 1\n-1 x1 -1 x2 >= -1 ;\n";
+        assert!(read_opb(&mut std::io::BufReader::new(input.as_bytes())).is_err());
+
+        // 空行を含む
+        let input = "* #variable= 2 #constraint= 2\n\n+1 x1 +1 x2 >= 1 ;\n  \n-1 x1 -1 x2 >= -1 ;\n\n";
+        let pb_problem = read_opb(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+        assert!(pb_problem.constraints.len() == 2);
+
+        // 複数の目的関数と空の目的関数
+        let input = "min: +1 x1 +1 x2 ;\nmin: -2 x1 ;\nmin: ;\n+1 x1 +1 x2 >= 1 ;\n";
+        let pb_problem = read_opb(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+        assert!(pb_problem.objectives.len() == 3);
+        assert!(pb_problem.objectives[0].len() == 2);
+        assert!(pb_problem.objectives[1][0].weight == -2);
+        assert!(pb_problem.objectives[2].is_empty());
+        assert!(pb_problem.constraints.len() == 1);
+
+        // 不正な行
+        let input = "+1 x1 +1 x2 >= 1 ;\n1 x1 >=\n";
+        assert!(read_opb(&mut std::io::BufReader::new(input.as_bytes())).is_err());
+    }
+
+    #[test]
+    fn test_variable_degrees() {
+        // x1 は 3 つ， x2 は 2 つ， x3 は 1 つの制約条件に現れ， x4 は目的関数にのみ現れる
+        let input = "min: +1 x4 ;
++1 x1 +1 x2 >= 1 ;
++1 x1 -1 x3 >= 0 ;
++1 x1 +1 x2 +2 x1 >= 2 ;
+";
+        let pb_problem = read_opb(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+        assert!(pb_problem.variable_degrees() == vec![3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_maximize_objective() {
+        let input = "max: +3 x1 -2 x2 ;\n+1 x1 +1 x2 >= 1 ;\n";
+        let pb_problem = read_opb(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+        assert!(pb_problem.objective_is_maximize);
+        assert!(pb_problem.objectives[0][0].weight == -3);
+        assert!(pb_problem.objectives[0][1].weight == 2);
+
+        let input = "min: +1 x1 ;\n+1 x1 +1 x2 >= 1 ;\n";
+        let pb_problem = read_opb(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+        assert!(!pb_problem.objective_is_maximize);
+
+        // min: と max: の混在は構文エラー
+        let input = "min: +1 x1 ;\nmax: +1 x2 ;\n";
+        assert!(read_opb(&mut std::io::BufReader::new(input.as_bytes())).is_err());
+    }
+
+    #[test]
+    fn test_read_opb_streaming() {
+        let input = "* #variable= 2 #constraint= 2\n+1 x1 +1 x2 >= 1 ;\n1 x1 >=\n-1 x1 >= -1 ;\n";
+        let mut input = std::io::BufReader::new(input.as_bytes());
+        let mut constraints = read_opb_streaming(&mut input);
+        assert!(matches!(
+            constraints.next(),
+            Some(Ok(ObjectiveOrConstraint::Constraint(Constraint { rhs: 1, .. })))
+        ));
+        assert!(matches!(
+            constraints.next(),
+            Some(Err(ParseError::Syntax { line_number: 3 }))
+        ));
+        assert!(constraints.next().is_none());
+    }
+
+    #[test]
+    fn test_read_opb_batch() {
+        let input = "* #variable= 2 #constraint= 1\nmin: +1 x1 ;\n+1 x1 +1 x2 >= 1 ;\n* ---\n\
+                     * #variable= 1 #constraint= 2\n+1 x1 >= 1 ;\n-1 x1 >= 0 ;\n* ---\n";
+        let mut input = std::io::BufReader::new(input.as_bytes());
+        let pb_problems = read_opb_batch(&mut input).collect::<Result<Vec<_>, _>>().unwrap();
+        assert!(pb_problems.len() == 2);
+        assert!(pb_problems[0].objectives.len() == 1 && pb_problems[0].constraints.len() == 1);
+        assert!(pb_problems[1].objectives.is_empty() && pb_problems[1].constraints.len() == 2);
+
+        // 構文エラーは入力全体での行番号で報告し，次の問題から読み取りを続ける
+        let input = "+1 x1 >= 1 ;\n* ---\n+1 x1 >= 1 ;\n1 x1 >=\n* ---\n-1 x1 >= 0 ;";
+        let mut input = std::io::BufReader::new(input.as_bytes());
+        let mut pb_problems = read_opb_batch(&mut input);
+        assert!(pb_problems.next().is_some_and(|pb_problem| pb_problem.is_ok()));
+        assert!(matches!(
+            pb_problems.next(),
+            Some(Err(ParseError::Syntax { line_number: 4 }))
+        ));
+        assert!(pb_problems.next().is_some_and(|pb_problem| pb_problem.is_ok()));
+        assert!(pb_problems.next().is_none());
+
+        // 空の入力は問題を含まない
+        let mut input = std::io::BufReader::new("".as_bytes());
+        assert!(read_opb_batch(&mut input).next().is_none());
+    }
+
+    #[test]
+    fn test_hint() {
+        use super::hint;
+        use pb_engine::{Boolean, Literal};
+
+        let (s, literals) = hint("* hint: x1 -x2 x3\n").unwrap();
+        assert!(s.is_empty());
+        assert!(
+            literals
+                == vec![
+                    Literal::new(0, Boolean::TRUE),
+                    Literal::new(1, Boolean::FALSE),
+                    Literal::new(2, Boolean::TRUE),
+                ]
+        );
+        // hint として解釈できないものは通常のコメントとして扱う
+        assert!(hint("* hint: y1\n").is_err());
+        assert!(hint("* #variable= 3\n").is_err());
+
+        let input = "* hint: -x1 x2\n* comment\n+1 x1 +1 x2 >= 1 ;\n";
+        let pb_problem = read_opb(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+        assert!(
+            pb_problem.hint
+                == Some(vec![
+                    Literal::new(0, Boolean::FALSE),
+                    Literal::new(1, Boolean::TRUE),
+                ])
+        );
+        assert!(pb_problem.constraints.len() == 1);
+    }
+
+    #[test]
+    fn test_soft_constraint() {
+        use super::soft_constraint;
+
+        let (s, (weight, constraint)) = soft_constraint("[3] +1 x1 >= 1 ;\n").unwrap();
+        assert!(s.is_empty());
+        assert!(weight == 3);
+        assert!(constraint.sum.len() == 1);
+        assert!(constraint.rhs == 1);
+
+        let (s, (weight, _)) = soft_constraint("[ 12 ]+1 x1 -1 x2 >= 0 ;\n").unwrap();
+        assert!(s.is_empty());
+        assert!(weight == 12);
+
+        assert!(soft_constraint("+1 x1 >= 1 ;\n").is_err());
+        assert!(soft_constraint("[-3] +1 x1 >= 1 ;\n").is_err());
+    }
+
+    #[test]
+    fn test_read_wbo() {
+        let input = "* #variable= 2 #constraint= 3 #soft= 2 mincost= 2 maxcost= 3 sumcost= 5
+soft: 6 ;
+[2] +1 x1 >= 1 ;
+[3] +1 x2 >= 1 ;
+-1 x1 -1 x2 >= -1 ;
+";
+        let wbo_problem = read_wbo(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+        assert!(wbo_problem.top == Some(6));
+        assert!(wbo_problem.hard.len() == 1);
+        assert!(wbo_problem.soft.len() == 2);
+        assert!(wbo_problem.soft[0].0 == 2);
+        assert!(wbo_problem.soft[1].0 == 3);
+
+        let input = "soft: ;\n[1] +1 x1 >= 1 ;\n";
+        let wbo_problem = read_wbo(&mut std::io::BufReader::new(input.as_bytes())).unwrap();
+        assert!(wbo_problem.top.is_none());
+
+        // soft ヘッダがない
+        let input = "[1] +1 x1 >= 1 ;\n";
+        assert!(read_wbo(&mut std::io::BufReader::new(input.as_bytes())).is_none());
+
+        // 重みや top が i64 に収まらない
+        let input = "soft: ;\n[9223372036854775808] +1 x1 >= 1 ;\n";
+        assert!(read_wbo(&mut std::io::BufReader::new(input.as_bytes())).is_none());
+        let input = "soft: 9223372036854775808 ;\n[1] +1 x1 >= 1 ;\n";
+        assert!(read_wbo(&mut std::io::BufReader::new(input.as_bytes())).is_none());
+    }
+
     //     #[test]
     //     fn test_opb() {
     //         let input = r"* comment
@@ -192,3 +192,29 @@ where
         f.debug_map().entries(self.iter()).finish()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::HeapedMap;
+
+    #[test]
+    fn test_first() {
+        let mut heaped_map =
+            HeapedMap::new(|lhs: &(usize, i32), rhs: &(usize, i32)| lhs.1.cmp(&rhs.1));
+        assert!(heaped_map.first().is_none());
+        for (index, value) in [(0, 5), (3, 2), (1, 8), (4, 2), (2, -1)] {
+            heaped_map.insert(index, value);
+        }
+        heaped_map.insert(1, -3);
+        heaped_map.remove(2);
+
+        // first は次に pop_first で取り出される要素と一致し，要素を取り除かない
+        while !heaped_map.is_empty() {
+            let len = heaped_map.len();
+            let (&index, &value) = heaped_map.first().unwrap();
+            assert!(heaped_map.len() == len);
+            assert!(heaped_map.pop_first() == Some((index, value)));
+        }
+        assert!(heaped_map.first().is_none());
+    }
+}
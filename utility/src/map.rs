@@ -37,6 +37,18 @@ impl<ValueT> Map<ValueT> {
         self.item_array.is_empty()
     }
 
+    /// 再確保せずに保持できる要素の数
+    pub fn capacity(&self) -> usize {
+        self.item_array.capacity()
+    }
+
+    /// 少なくとも additional 個の要素を再確保せずに追加できるようにする
+    ///
+    /// index から位置への対応表は clear しても縮まないため，最大の index まで一度伸ばせば再確保されない
+    pub fn reserve(&mut self, additional: usize) {
+        self.item_array.reserve(additional);
+    }
+
     #[inline(always)]
     pub fn contains_key(&self, index: usize) -> bool {
         if index >= self.index_to_position.len() {
@@ -90,6 +102,22 @@ impl<ValueT> Map<ValueT> {
         }
     }
 
+    /// index に対応する値への可変参照を返す (存在しなければ f() の値を挿入する)
+    #[inline(always)]
+    pub fn get_or_insert_with(&mut self, index: usize, f: impl FnOnce() -> ValueT) -> &mut ValueT {
+        if index >= self.index_to_position.len() {
+            self.index_to_position
+                .resize(index + 1, Self::NULL_POSITION);
+        }
+        let position = &mut self.index_to_position[index];
+        if *position == Self::NULL_POSITION {
+            *position = self.item_array.len();
+            self.item_array.push((index, f()));
+        }
+        debug_assert!(self.item_array[*position].0 == index);
+        return &mut self.item_array[*position].1;
+    }
+
     pub fn extend(&mut self, items: impl Iterator<Item = (usize, ValueT)>) {
         for (index, value) in items {
             self.insert(index, value);
@@ -171,3 +199,50 @@ where
         f.debug_map().entries(self.iter()).finish()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::Map;
+
+    #[test]
+    fn test_get_or_insert_with() {
+        let mut map = Map::default();
+        map.insert(3, 10);
+
+        // 存在する場合は既存の値を返し， f は呼ばれない
+        *map.get_or_insert_with(3, || unreachable!()) += 1;
+        assert_eq!(map.get(3), Some(&11));
+        assert_eq!(map.len(), 1);
+
+        // 存在しない場合は f の値を挿入する
+        *map.get_or_insert_with(7, || 20) += 1;
+        assert_eq!(map.get(7), Some(&21));
+        assert_eq!(map.len(), 2);
+
+        // 削除後の再挿入
+        map.remove(3);
+        assert_eq!(*map.get_or_insert_with(3, || 0), 0);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(7), Some(&21));
+    }
+
+    #[test]
+    fn test_reserve() {
+        let mut map = Map::default();
+        map.insert(99, 0);
+        map.clear();
+        map.reserve(100);
+        let capacity = map.capacity();
+        assert!(capacity >= 100);
+
+        // 予約した数まで挿入しても再確保されず， clear しても容量は保たれる
+        for _ in 0..3 {
+            for index in 0..100 {
+                map.insert(index, index);
+            }
+            assert_eq!(map.capacity(), capacity);
+            map.clear();
+            assert_eq!(map.capacity(), capacity);
+        }
+    }
+}
@@ -12,6 +12,13 @@ impl<ValueT, CompareT> PriorityQueue<ValueT, CompareT>
 where
     CompareT: Fn(&ValueT, &ValueT) -> std::cmp::Ordering,
 {
+    pub fn new(compare: CompareT) -> Self {
+        Self {
+            compare,
+            array: Vec::default(),
+        }
+    }
+
     #[inline(always)]
     pub fn len(&self) -> usize {
         self.array.len()
@@ -56,6 +63,15 @@ where
     pub fn clear(&mut self) {
         self.array.clear();
     }
+
+    /// pop の順序 (compare で小さい順) に全要素を取り出す
+    pub fn into_sorted_vec(mut self) -> Vec<ValueT> {
+        let mut sorted = Vec::with_capacity(self.array.len());
+        while let Some(value) = self.pop() {
+            sorted.push(value);
+        }
+        return sorted;
+    }
 }
 
 impl<ValueT, CompareT> Default for PriorityQueue<ValueT, CompareT>
@@ -92,3 +108,33 @@ where
         self.array.fmt(f)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::PriorityQueue;
+
+    #[test]
+    fn test_into_sorted_vec() {
+        let mut priority_queue = PriorityQueue::new(|lhs: &i32, rhs: &i32| lhs.cmp(rhs));
+        for value in [5, -1, 8, 2, 2, 0, 13, -7, 4] {
+            priority_queue.push(value);
+        }
+        assert_eq!(
+            priority_queue.clone().into_sorted_vec(),
+            vec![-7, -1, 0, 2, 2, 4, 5, 8, 13]
+        );
+
+        // 比較関数を逆にすると降順に取り出される
+        let mut priority_queue = PriorityQueue::new(|lhs: &i32, rhs: &i32| rhs.cmp(lhs));
+        for value in [5, -1, 8, 2, 2, 0, 13, -7, 4] {
+            priority_queue.push(value);
+        }
+        assert_eq!(
+            priority_queue.into_sorted_vec(),
+            vec![13, 8, 5, 4, 2, 2, 0, -1, -7]
+        );
+
+        let priority_queue = PriorityQueue::new(|lhs: &i32, rhs: &i32| lhs.cmp(rhs));
+        assert!(priority_queue.into_sorted_vec().is_empty());
+    }
+}